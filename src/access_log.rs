@@ -0,0 +1,162 @@
+//! An access log of the requests handled by the server.
+
+use chrono::prelude::*;
+use events;
+use rocket::{Request, Response};
+use std::io::Write;
+use std::sync::Mutex;
+
+/// The target of `log` records written by the access log.
+const LOG_TARGET: &str = "rocket_static_fs::access";
+
+/// The format of the lines of the access log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLogFormat {
+    /// The Common Log Format, e.g.
+    /// `127.0.0.1 - - [10/Oct/2018:13:55:36 +0000] "GET /index.html" 200 2326`.
+    ///
+    /// Rocket doesn't tell us the protocol version, so it's left out of the request line.
+    Common,
+    /// One JSON object per line with the fields `time`, `remote`, `method`, `uri`, `status`,
+    /// `bytes`, `duration_ms`, `referer` and `user_agent`.
+    Json,
+}
+
+enum Target {
+    Log,
+    Writer(Mutex<Box<Write + Send>>),
+}
+
+/// An access log for all requests handled by the server, see `StaticFileServerBuilder::access_log`.
+///
+/// Lines are written to the `log` crate with the target `rocket_static_fs::access` and level
+/// info, unless a writer is given. `bytes` is the number of bytes of the file sent, before
+/// compression, or `-` if no file was sent.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::{fs, AccessLog, StaticFileServer};
+/// use std::fs::File;
+///
+/// let log = File::create("access.log").unwrap();
+/// let server = StaticFileServer::builder(fs::LocalFileSystem::new("static"))
+///     .access_log(AccessLog::common().to_writer(log))
+///     .build();
+/// ```
+pub struct AccessLog {
+    format: AccessLogFormat,
+    target: Target,
+}
+
+impl AccessLog {
+    pub fn new(format: AccessLogFormat) -> Self {
+        AccessLog {
+            format,
+            target: Target::Log,
+        }
+    }
+
+    /// Returns an access log in the Common Log Format.
+    pub fn common() -> Self {
+        AccessLog::new(AccessLogFormat::Common)
+    }
+
+    /// Returns an access log writing JSON lines.
+    pub fn json() -> Self {
+        AccessLog::new(AccessLogFormat::Json)
+    }
+
+    /// Writes the lines to the writer instead of the `log` crate.
+    pub fn to_writer<W: Write + Send + 'static>(mut self, writer: W) -> Self {
+        self.target = Target::Writer(Mutex::new(Box::new(writer)));
+        self
+    }
+}
+
+/// Records a request handled by the server.
+pub fn record(log: &AccessLog, request: &Request, response: &Response) {
+    let line = match log.format {
+        AccessLogFormat::Common => common_line(request, response, Utc::now()),
+        AccessLogFormat::Json => json_line(request, response, Utc::now()),
+    };
+
+    match log.target {
+        Target::Log => info!(target: LOG_TARGET, "{}", line),
+        Target::Writer(ref writer) => {
+            // There's nobody to tell about a failing access log, so we carry on
+            if let Ok(mut writer) = writer.lock() {
+                let _ = writeln!(writer, "{}", line);
+            }
+        }
+    }
+}
+
+fn common_line(request: &Request, response: &Response, time: DateTime<Utc>) -> String {
+    format!(
+        "{} - - [{}] \"{} {}\" {} {}",
+        remote(request),
+        time.format("%d/%b/%Y:%H:%M:%S %z"),
+        request.method(),
+        request.uri(),
+        response.status().code,
+        events::body_bytes(request).map_or("-".to_string(), |bytes| bytes.to_string()),
+    )
+}
+
+fn json_line(request: &Request, response: &Response, time: DateTime<Utc>) -> String {
+    let duration = events::started(request).elapsed();
+    let duration_ms = duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1e6;
+    let line = json!({
+        "time": time.to_rfc3339(),
+        "remote": remote(request),
+        "method": request.method().as_str(),
+        "uri": request.uri().to_string(),
+        "status": response.status().code,
+        "bytes": events::body_bytes(request),
+        "duration_ms": duration_ms,
+        "referer": request.headers().get_one("Referer"),
+        "user_agent": request.headers().get_one("User-Agent"),
+    });
+    line.to_string()
+}
+
+/// Returns the address of the client, or `-` if it's unknown.
+fn remote(request: &Request) -> String {
+    request
+        .client_ip()
+        .map_or("-".to_string(), |ip| ip.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{common_line, json_line};
+    use chrono::prelude::*;
+    use events;
+    use rocket;
+    use rocket::http::Status;
+    use rocket::local::Client;
+    use rocket::Response;
+    use serde_json::{self, Value};
+
+    #[test]
+    fn test_lines() {
+        let client = Client::new(rocket::ignite()).expect("valid rocket");
+        let request = client.get("/test/hello.txt?x=1");
+        let mut response = Response::new();
+        response.set_status(Status::Ok);
+        events::set_body_bytes(request.inner(), 12);
+
+        let time = Utc.ymd(2018, 10, 10).and_hms(13, 55, 36);
+        assert_eq!(
+            common_line(request.inner(), &response, time),
+            "- - - [10/Oct/2018:13:55:36 +0000] \"GET /test/hello.txt?x=1\" 200 12"
+        );
+
+        let line: Value = serde_json::from_str(&json_line(request.inner(), &response, time)).unwrap();
+        assert_eq!(line["uri"], "/test/hello.txt?x=1");
+        assert_eq!(line["status"], 200);
+        assert_eq!(line["bytes"], 12);
+        assert_eq!(line["user_agent"], Value::Null);
+    }
+}
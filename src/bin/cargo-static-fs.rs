@@ -0,0 +1,12 @@
+//! The rocket-static-fs tool as cargo subcommand, `cargo static-fs`.
+
+extern crate chrono;
+extern crate filetime;
+extern crate rocket_static_fs;
+
+#[path = "rocket-static-fs.rs"]
+mod tool;
+
+fn main() {
+    tool::main()
+}
@@ -0,0 +1,177 @@
+//! Command line tool for creating and inspecting packages of the EmbeddedFileSystem.
+//!
+//! Requires the `cli` feature, e.g. `cargo install rocket_static_fs --features cli`, which
+//! installs it as `cargo static-fs` as well.
+
+extern crate chrono;
+extern crate filetime;
+extern crate rocket_static_fs;
+
+use chrono::{DateTime, Utc};
+use filetime::FileTime;
+use rocket_static_fs::fs::{EmbeddedFileSystem, FileSystem, Package, PackageCompression, Packager};
+use rocket_static_fs::Glob;
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::process;
+
+#[cfg(feature = "brotli")]
+const USAGE: &str = "Usage (or as `cargo static-fs <command>`):
+    rocket-static-fs pack <dir> <package> [--gzip | --brotli] [--reproducible]
+        [--exclude <glob>]...
+    rocket-static-fs merge <package> <layer>... (later layers override earlier ones)
+    rocket-static-fs list <package>
+    rocket-static-fs extract <package> <dir>
+    rocket-static-fs verify <package>";
+
+#[cfg(not(feature = "brotli"))]
+const USAGE: &str = "Usage (or as `cargo static-fs <command>`):
+    rocket-static-fs pack <dir> <package> [--gzip] [--reproducible]
+        [--exclude <glob>]...
+    rocket-static-fs merge <package> <layer>... (later layers override earlier ones)
+    rocket-static-fs list <package>
+    rocket-static-fs extract <package> <dir>
+    rocket-static-fs verify <package>";
+
+pub fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // Invoked as `cargo static-fs`, cargo passes the name of the subcommand first
+    if args.first().map(|arg| arg.as_str()) == Some("static-fs") {
+        args.remove(0);
+    }
+    let result = match args.first().map(|command| command.as_str()) {
+        Some("pack") if args.len() >= 3 => pack(&args[1], &args[2], &args[3..]),
+        Some("merge") if args.len() >= 3 => merge(&args[1], &args[2..]),
+        Some("list") if args.len() == 2 => list(&args[1]),
+        Some("extract") if args.len() == 3 => extract(&args[1], &args[2]),
+        Some("verify") if args.len() == 2 => verify(&args[1]),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(err) = result {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn pack(dir: &str, package: &str, flags: &[String]) -> Result<(), Box<Error>> {
+    let mut packager = Packager::new(dir);
+    let mut flags = flags.iter();
+    while let Some(flag) = flags.next() {
+        packager = match flag.as_str() {
+            "--gzip" => packager.compression(PackageCompression::Gzip),
+            "--reproducible" => packager.reproducible(),
+            #[cfg(feature = "brotli")]
+            "--brotli" => packager.compression(PackageCompression::Brotli),
+            "--exclude" => {
+                let pattern = flags.next().ok_or("--exclude needs a glob")?;
+                packager.exclude(Glob::new(pattern)?)
+            }
+            flag => return Err(From::from(format!("unknown option {}", flag))),
+        };
+    }
+
+    packager.write_streaming_to(&mut File::create(package)?)
+}
+
+fn merge(package: &str, layers: &[String]) -> Result<(), Box<Error>> {
+    let mut packages = Vec::new();
+    for layer in layers {
+        packages.push(Package::from_file(layer)?);
+    }
+    Package::merge(packages).write(&mut File::create(package)?)
+}
+
+fn list(package: &str) -> Result<(), Box<Error>> {
+    for entry in Package::from_file(package)?.entries() {
+        let modified = DateTime::<Utc>::from(entry.modified).to_rfc3339();
+        println!(
+            "{:>10}  {:>10}  {:>10}  {}  {}",
+            entry.size, entry.stored_size, entry.offset, modified, entry.path
+        );
+    }
+    Ok(())
+}
+
+fn extract(package: &str, dir: &str) -> Result<(), Box<Error>> {
+    let fs = EmbeddedFileSystem::from_file(package)?;
+    let paths = files(&fs, Path::new(""))?;
+    // Paths come from the package, don't write anything if one of them points outside of `dir`
+    for path in &paths {
+        if !path.components().all(|c| match c {
+            Component::Normal(_) => true,
+            _ => false,
+        }) {
+            return Err(From::from(format!("refusing to extract {}", path.display())));
+        }
+    }
+
+    for path in paths {
+        let target = Path::new(dir).join(&path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut reader = fs.open(&path, None)?;
+        io::copy(&mut reader, &mut File::create(&target)?)?;
+
+        if let Some(modified) = fs.metadata(&path)?.modified {
+            filetime::set_file_mtime(&target, FileTime::from_system_time(modified))?;
+        }
+    }
+    Ok(())
+}
+
+fn verify(package: &str) -> Result<(), Box<Error>> {
+    // Loading verifies the checksums of the metadata and of every entry
+    let fs = EmbeddedFileSystem::from_file(package)?;
+    let count = files(&fs, Path::new(""))?.len();
+    println!("{}: {} files, all checksums match", package, count);
+    Ok(())
+}
+
+/// Returns the paths of all files below `dir`, depth first.
+fn files(fs: &EmbeddedFileSystem, dir: &Path) -> Result<Vec<PathBuf>, Box<Error>> {
+    let mut paths = Vec::new();
+    for entry in fs.read_dir(dir)? {
+        let path = dir.join(&entry.name);
+        if entry.is_dir {
+            paths.extend(files(fs, &path)?);
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract;
+    use rocket_static_fs::fs::testing::TempFileSystem;
+    use rocket_static_fs::fs::write_package;
+    use std::fs::File;
+
+    #[test]
+    fn test_extract_escaping_paths() {
+        let mut temp = TempFileSystem::new();
+        temp.insert("public/index.html", "index");
+        temp.insert("outside.txt", "outside");
+        let package = temp.path().join("package.bin");
+        write_package(
+            temp.path().join("public"),
+            &["index.html", "../outside.txt"],
+            &mut File::create(&package).unwrap(),
+        ).unwrap();
+
+        let target = temp.path().join("extracted");
+        let dir = target.join("dir");
+        assert!(extract(package.to_str().unwrap(), dir.to_str().unwrap()).is_err());
+        assert!(!target.join("outside.txt").exists());
+        assert!(!dir.join("index.html").exists());
+    }
+}
@@ -0,0 +1,469 @@
+//! Configuration of the StaticFileServer.
+
+use access_log::AccessLog;
+use events::{ErrorCallback, ErrorEvent, ServeCallback, ServeEvent};
+use fs::{FileSystem, HotCache};
+use listing::{DefaultListingRenderer, ListingRenderer};
+#[cfg(feature = "metrics")]
+use metrics::Metrics;
+use rocket::http::Status;
+use serde_json;
+use serve::ServeOptions;
+use std::collections::{HashMap, HashSet};
+use {netlify, normalize_prefix, read_to_string, Glob, LanguageScheme, RewriteRule,
+     StaticFileServer};
+
+/// The options of a StaticFileServer, collected by the StaticFileServerBuilder.
+pub struct Options {
+    pub prefixes: Vec<String>,
+    pub hide_dotfiles: bool,
+    pub deny_with_not_found: bool,
+    pub directory_listing: bool,
+    pub listing_renderer: Box<ListingRenderer>,
+    pub index_files: Vec<String>,
+    pub directory_redirect: Option<Status>,
+    pub error_pages: HashMap<u16, String>,
+    pub try_files: Vec<String>,
+    pub clean_urls: bool,
+    pub clean_urls_redirect: Option<Status>,
+    pub rewrites: Vec<RewriteRule>,
+    pub netlify_files: bool,
+    pub redirects: Vec<netlify::Redirect>,
+    pub header_rules: Vec<netlify::HeaderRule>,
+    pub file_mappings: HashMap<String, String>,
+    pub excludes: Vec<Glob>,
+    pub allowed_extensions: Option<HashSet<String>>,
+    pub language_scheme: Option<LanguageScheme>,
+    pub cache_max_age: Option<u32>,
+    /// The path of the manifest of fingerprinted files.
+    pub manifest: Option<String>,
+    /// Maps original paths to fingerprinted ones, as read from the manifest.
+    pub fingerprints: HashMap<String, String>,
+    /// The fingerprinted paths, served with immutable caching.
+    pub fingerprinted: HashSet<String>,
+    pub serve: ServeOptions,
+    pub hot_cache: Option<HotCache>,
+    pub on_serve: Option<ServeCallback>,
+    pub on_error: Option<ErrorCallback>,
+    pub access_log: Option<AccessLog>,
+    #[cfg(feature = "metrics")]
+    pub metrics: Option<Metrics>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            prefixes: Vec::new(),
+            hide_dotfiles: false,
+            deny_with_not_found: false,
+            directory_listing: false,
+            listing_renderer: Box::new(DefaultListingRenderer),
+            index_files: vec!["index.html".to_string()],
+            directory_redirect: Some(Status::MovedPermanently),
+            error_pages: HashMap::new(),
+            try_files: Vec::new(),
+            clean_urls: false,
+            clean_urls_redirect: None,
+            rewrites: Vec::new(),
+            netlify_files: true,
+            redirects: Vec::new(),
+            header_rules: Vec::new(),
+            file_mappings: HashMap::new(),
+            excludes: Vec::new(),
+            allowed_extensions: None,
+            language_scheme: None,
+            cache_max_age: None,
+            manifest: None,
+            fingerprints: HashMap::new(),
+            fingerprinted: HashSet::new(),
+            serve: ServeOptions::default(),
+            hot_cache: None,
+            on_serve: None,
+            on_error: None,
+            access_log: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+}
+
+/// Builds a StaticFileServer, see `StaticFileServer::builder`.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::{fs, StaticFileServer};
+///
+/// let server = StaticFileServer::builder(fs::LocalFileSystem::new("static"))
+///     .prefix("/assets")
+///     .cache_max_age(3600)
+///     .directory_listing(true)
+///     .build();
+/// ```
+pub struct StaticFileServerBuilder<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    fs: T,
+    options: Options,
+}
+
+impl<T> StaticFileServerBuilder<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    pub fn new(fs: T) -> Self {
+        StaticFileServerBuilder {
+            fs,
+            options: Options::default(),
+        }
+    }
+
+    /// Builds the StaticFileServer fairing.
+    pub fn build(mut self) -> StaticFileServer<T> {
+        if self.options.prefixes.is_empty() {
+            self.options.prefixes.push("/".to_string());
+        }
+        if self.options.netlify_files {
+            if self.options.redirects.is_empty() {
+                self = self.netlify_redirects();
+            }
+            if self.options.header_rules.is_empty() {
+                self = self.netlify_headers();
+            }
+        }
+
+        StaticFileServer {
+            fs: self.fs,
+            options: self.options,
+        }
+    }
+
+    /// Serves the FileSystem from the given prefix, e.g. `/assets`.
+    ///
+    /// Can be called multiple times to serve from several prefixes. If prefixes overlap, the
+    /// longest matching prefix is used. Defaults to `/` if no prefix is given.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.options.prefixes.push(normalize_prefix(prefix));
+        self
+    }
+
+    /// Refuses to serve any path with a component starting with a dot, e.g. `.git` or `.env`.
+    ///
+    /// Requests for such paths are answered with a 404.
+    pub fn hide_dotfiles(mut self, hide: bool) -> Self {
+        self.options.hide_dotfiles = hide;
+        self
+    }
+
+    /// Answers with a 404 instead of a 403 for paths which are denied or outside of the root.
+    ///
+    /// This way clients can't tell apart paths which don't exist from paths they may not access.
+    pub fn deny_with_not_found(mut self, enabled: bool) -> Self {
+        self.options.deny_with_not_found = enabled;
+        self
+    }
+
+    /// Sets the file names tried in order when a directory is requested.
+    ///
+    /// Defaults to `index.html`.
+    pub fn index_files<I, S>(mut self, files: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.index_files = files.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets the status used to redirect directory requests without a trailing slash.
+    ///
+    /// Defaults to `301 Moved Permanently`, `None` disables the redirect.
+    pub fn directory_redirect(mut self, status: Option<Status>) -> Self {
+        self.options.directory_redirect = status;
+        self
+    }
+
+    /// Renders an HTML index for requests to directories.
+    ///
+    /// Requires the FileSystem to support `is_dir` and `read_dir`.
+    pub fn directory_listing(mut self, enabled: bool) -> Self {
+        self.options.directory_listing = enabled;
+        self
+    }
+
+    /// Replaces the built-in renderer for directory listings.
+    pub fn listing_renderer<R: ListingRenderer + 'static>(mut self, renderer: R) -> Self {
+        self.options.listing_renderer = Box::new(renderer);
+        self
+    }
+
+    /// Serves the file at the given path with a 404 status for everything which isn't found.
+    ///
+    /// The path is relative to the root of the FileSystem, e.g. `404.html`. Without a not found
+    /// page, requests to files which don't exist are answered with the root `index.html`.
+    pub fn not_found_page(self, path: &str) -> Self {
+        self.error_page(Status::NotFound, path)
+    }
+
+    /// Serves the file at the given path as body of all responses with the given error status.
+    ///
+    /// The path is relative to the root of the FileSystem, e.g. `403.html`.
+    pub fn error_page(mut self, status: Status, path: &str) -> Self {
+        self.options.error_pages.insert(status.code, path.to_string());
+        self
+    }
+
+    /// Sets the candidates tried in order for each request, like nginx's `try_files`.
+    ///
+    /// `$path` in a candidate is replaced by the requested path, e.g.
+    /// `["$path", "$path.html", "$path/index.html", "index.html"]`. The first candidate which is
+    /// a file is served, if none is found the request is answered with a 404. Index files and
+    /// the fallback to the root index file are not used if candidates are given.
+    pub fn try_files<I, S>(mut self, candidates: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.options.try_files = candidates.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Serves `about.html` for requests to `about` if `about` itself doesn't exist.
+    pub fn clean_urls(mut self, enabled: bool) -> Self {
+        self.options.clean_urls = enabled;
+        self
+    }
+
+    /// Redirects requests to `about.html` to `about` with the given status if clean URLs are
+    /// enabled.
+    ///
+    /// Defaults to `None`, which serves both URLs.
+    pub fn clean_urls_redirect(mut self, status: Option<Status>) -> Self {
+        self.options.clean_urls_redirect = status;
+        self
+    }
+
+    /// Adds a rule rewriting request paths before they're looked up in the FileSystem.
+    ///
+    /// Rules are tried in the order they're added, only the first matching rule is applied.
+    pub fn rewrite(mut self, rule: RewriteRule) -> Self {
+        self.options.rewrites.push(rule);
+        self
+    }
+
+    /// Sets whether Netlify-style `_redirects` and `_headers` files in the root of the
+    /// FileSystem are honored, defaults to true.
+    ///
+    /// The files are read when the server is built, see `netlify_redirects` and
+    /// `netlify_headers`. They are never served or listed themselves, as they reveal the
+    /// configuration of the site.
+    pub fn netlify_files(mut self, enabled: bool) -> Self {
+        self.options.netlify_files = enabled;
+        self
+    }
+
+    /// Honors the rules of a Netlify-style `_redirects` file in the root of the FileSystem.
+    ///
+    /// Redirects, rewrites (status 200), custom 404s, splats, placeholders and forced rules are
+    /// supported. The file is read once when this is called, so changes only apply after a
+    /// restart. Nothing happens if there is no `_redirects` file. Unless disabled with
+    /// `netlify_files`, this is done when the server is built anyway.
+    pub fn netlify_redirects(mut self) -> Self {
+        if let Ok(redirects) = read_to_string(&self.fs, "_redirects") {
+            self.options.redirects = netlify::parse_redirects(&redirects);
+        }
+        self
+    }
+
+    /// Sets the headers of a Netlify-style `_headers` file in the root of the FileSystem.
+    ///
+    /// The file is read once when this is called, so changes only apply after a restart.
+    /// Nothing happens if there is no `_headers` file. Unless disabled with `netlify_files`,
+    /// this is done when the server is built anyway.
+    pub fn netlify_headers(mut self) -> Self {
+        if let Ok(headers) = read_to_string(&self.fs, "_headers") {
+            self.options.header_rules = netlify::parse_headers(&headers);
+        }
+        self
+    }
+
+    /// Serves the file at `path` for requests to exactly `uri`.
+    ///
+    /// `uri` is the full request path, e.g. `/favicon.ico`, and doesn't have to be inside of
+    /// the prefix. `path` is relative to the root of the FileSystem, e.g.
+    /// `assets/icons/favicon.ico`. Requests to mappings of paths escaping the root of the
+    /// FileSystem are answered with a 404.
+    pub fn map_file(mut self, uri: &str, path: &str) -> Self {
+        self.options
+            .file_mappings
+            .insert(uri.to_string(), path.to_string());
+        self
+    }
+
+    /// Answers requests to paths matching the glob with a 404, even if they exist.
+    ///
+    /// The glob is matched against paths relative to the root of the FileSystem, e.g.
+    /// `**/*.key` or `internal/**`.
+    pub fn exclude(mut self, glob: Glob) -> Self {
+        self.options.excludes.push(glob);
+        self
+    }
+
+    /// Only serves files with one of the given extensions, answering everything else with a 404.
+    ///
+    /// Extensions are given without the dot and compared case-insensitively, e.g.
+    /// `["html", "css", "js"]`.
+    pub fn allowed_extensions<I, S>(mut self, extensions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let extensions = extensions
+            .into_iter()
+            .map(|ext| ext.as_ref().trim_left_matches('.').to_lowercase())
+            .collect();
+        self.options.allowed_extensions = Some(extensions);
+        self
+    }
+
+    /// Serves localized variants of files based on the `Accept-Language` header.
+    ///
+    /// With `LanguageScheme::Suffix`, a request to `index.html` from a client preferring German
+    /// is answered with `index.de.html` if it exists. The original file is served if there's no
+    /// variant in any of the accepted languages.
+    pub fn localized(mut self, scheme: LanguageScheme) -> Self {
+        self.options.language_scheme = Some(scheme);
+        self
+    }
+
+    /// Sets `Cache-Control: max-age` to the given number of seconds for all served files.
+    ///
+    /// A `Cache-Control` header set by a `_headers` rule takes precedence.
+    pub fn cache_max_age(mut self, seconds: u32) -> Self {
+        self.options.cache_max_age = Some(seconds);
+        self
+    }
+
+    /// Keeps the contents of frequently served files in the given cache, so repeated requests
+    /// don't reach the FileSystem.
+    ///
+    /// Only serving goes through the cache, looking up paths while resolving a request still
+    /// asks the FileSystem, wrap it in a StatCacheFileSystem to save those calls too. Hits are
+    /// checked against the size and modification date of the file, see `HotCache` for when
+    /// changed files may still be served from the cache.
+    pub fn hot_cache(mut self, cache: HotCache) -> Self {
+        self.options.hot_cache = Some(cache);
+        self
+    }
+
+    /// Serves the files listed in the JSON manifest at `path` as fingerprinted, with
+    /// `Cache-Control: public, max-age=31536000, immutable`, and the manifest itself with
+    /// `Cache-Control: no-cache`.
+    ///
+    /// The manifest maps original paths to fingerprinted ones, as written by
+    /// `PackageOptions::fingerprint`, see `StaticFileServer::fingerprinted` for looking them
+    /// up. It's read once when this is called. A manifest which is missing or invalid is
+    /// logged as an error, the files are served without immutable caching then.
+    pub fn fingerprint_manifest(mut self, path: &str) -> Self {
+        let path = path.trim_left_matches('/');
+        let manifest = match read_to_string(&self.fs, path) {
+            Ok(manifest) => manifest,
+            Err(err) => {
+                error!("can't read fingerprint manifest {}: {}", path, err);
+                return self;
+            }
+        };
+        match serde_json::from_str::<HashMap<String, String>>(&manifest) {
+            Ok(fingerprints) => {
+                self.options.fingerprinted = fingerprints.values().cloned().collect();
+                self.options.fingerprints = fingerprints;
+                self.options.manifest = Some(path.to_string());
+            }
+            Err(err) => error!("invalid fingerprint manifest {}: {}", path, err),
+        }
+        self
+    }
+
+    /// Compresses files with gzip for clients accepting it, enabled by default.
+    ///
+    /// Disable it if compression is already taken care of, e.g. by a CDN or a reverse proxy.
+    pub fn compression(mut self, enabled: bool) -> Self {
+        self.options.serve.compression = enabled;
+        self
+    }
+
+    /// Answers `Range` requests with partial content, enabled by default.
+    ///
+    /// If disabled, `Accept-Ranges: none` is advertised and full bodies are sent for all
+    /// requests. Useful for backends which can't seek efficiently.
+    pub fn ranges(mut self, enabled: bool) -> Self {
+        self.options.serve.ranges = enabled;
+        self
+    }
+
+    /// Serves files with the given extension with the MIME type, e.g. `("wasm",
+    /// "application/wasm")`.
+    ///
+    /// Takes precedence over the guessed MIME types. Extensions are given without the dot and
+    /// compared case-insensitively.
+    pub fn mime_type(mut self, extension: &str, mime: &str) -> Self {
+        let extension = extension.trim_left_matches('.').to_lowercase();
+        self.options.serve.mime_types.insert(extension, mime.to_string());
+        self
+    }
+
+    /// Sets the MIME type of files without an extension or with an unknown one.
+    ///
+    /// Defaults to `application/octet-stream`.
+    pub fn default_mime_type(mut self, mime: &str) -> Self {
+        self.options.serve.default_mime_type = mime.to_string();
+        self
+    }
+
+    /// Determines the MIME type of files without an extension or with an unknown one from the
+    /// first bytes of the file, disabled by default.
+    ///
+    /// The default MIME type is used if the content isn't recognized. Requires the `sniff`
+    /// feature.
+    #[cfg(feature = "sniff")]
+    pub fn sniff_content_type(mut self, enabled: bool) -> Self {
+        self.options.serve.sniff = enabled;
+        self
+    }
+
+    /// Calls the function after a file was served, e.g. for logging or analytics.
+    ///
+    /// Only regular files are reported, error pages are reported to `on_error` instead.
+    pub fn on_serve<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&ServeEvent) + Send + Sync + 'static,
+    {
+        self.options.on_serve = Some(Box::new(callback));
+        self
+    }
+
+    /// Calls the function whenever a request is answered with an error status, including 404s.
+    pub fn on_error<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&ErrorEvent) + Send + Sync + 'static,
+    {
+        self.options.on_error = Some(Box::new(callback));
+        self
+    }
+
+    /// Writes an access log line for every request handled by the server.
+    pub fn access_log(mut self, log: AccessLog) -> Self {
+        self.options.access_log = Some(log);
+        self
+    }
+
+    /// Records Prometheus metrics of all requests handled by the server.
+    ///
+    /// Requires the `metrics` feature.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(mut self, metrics: Metrics) -> Self {
+        self.options.metrics = Some(metrics);
+        self
+    }
+}
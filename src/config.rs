@@ -0,0 +1,233 @@
+//! Configuration via `Rocket.toml` and the environment.
+
+use fs::{FileSystem, LocalFileSystem};
+use rocket::config::{Config, ConfigError, Value};
+use rocket::fairing::AdHoc;
+use {StaticFileServer, StaticFileServerBuilder};
+
+/// The name of the table in Rocket's configuration holding our settings.
+const CONFIG_TABLE: &str = "static_fs";
+
+/// The directory served by `StaticFileServer::fairing` if no root is configured.
+const DEFAULT_ROOT: &str = "static";
+
+impl<T> StaticFileServerBuilder<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    /// Applies the settings of the `[static_fs]` table of Rocket's configuration.
+    ///
+    /// The following keys are supported, all of them are optional:
+    ///
+    /// ```toml
+    /// [global.static_fs]
+    /// prefix = "/assets"            # or a list of prefixes
+    /// cache_max_age = 3600
+    /// compression = false
+    /// ranges = false
+    /// directory_listing = true
+    /// hide_dotfiles = true
+    /// deny_with_not_found = true
+    /// clean_urls = true
+    /// index_files = ["index.html", "index.htm"]
+    /// not_found_page = "404.html"
+    /// mime_types = { wasm = "application/wasm" }
+    /// default_mime_type = "application/octet-stream"
+    /// ```
+    ///
+    /// Settings can be overridden from the environment as well, e.g. with
+    /// `ROCKET_STATIC_FS={cache_max_age=60}`. Nothing happens if there's no such table.
+    pub fn configure(mut self, config: &Config) -> Result<Self, ConfigError> {
+        let table = match config.get_table(CONFIG_TABLE) {
+            Ok(table) => table,
+            Err(ConfigError::Missing(_)) => return Ok(self),
+            Err(err) => return Err(err),
+        };
+
+        for (key, value) in table {
+            let name = format!("{}.{}", CONFIG_TABLE, key);
+            self = match key.as_str() {
+                "prefix" => {
+                    for prefix in string_list(&name, value)? {
+                        self = self.prefix(&prefix);
+                    }
+                    self
+                }
+                "cache_max_age" => match value.as_integer() {
+                    Some(seconds) if seconds >= 0 && seconds <= i64::from(u32::max_value()) => {
+                        self.cache_max_age(seconds as u32)
+                    }
+                    _ => return Err(bad_type(&name, "unsigned 32-bit integer", value)),
+                },
+                "compression" => self.compression(boolean(&name, value)?),
+                "directory_listing" => self.directory_listing(boolean(&name, value)?),
+                "ranges" => self.ranges(boolean(&name, value)?),
+                "hide_dotfiles" => self.hide_dotfiles(boolean(&name, value)?),
+                "deny_with_not_found" => self.deny_with_not_found(boolean(&name, value)?),
+                "clean_urls" => self.clean_urls(boolean(&name, value)?),
+                "index_files" => self.index_files(string_list(&name, value)?),
+                "not_found_page" => match value.as_str() {
+                    Some(page) => self.not_found_page(page),
+                    None => return Err(bad_type(&name, "string", value)),
+                },
+                "default_mime_type" => match value.as_str() {
+                    Some(mime) => self.default_mime_type(mime),
+                    None => return Err(bad_type(&name, "string", value)),
+                },
+                "mime_types" => {
+                    let types = value
+                        .as_table()
+                        .ok_or_else(|| bad_type(&name, "table of strings", value))?;
+                    for (extension, mime) in types {
+                        match mime.as_str() {
+                            Some(mime) => self = self.mime_type(extension, mime),
+                            None => return Err(bad_type(&name, "table of strings", value)),
+                        }
+                    }
+                    self
+                }
+                // The root is only used by `StaticFileServer::fairing`
+                "root" => self,
+                // Ignored so settings of newer versions don't break older ones, but likely a typo
+                _ => {
+                    warn!("Ignoring unknown setting {}", name);
+                    self
+                }
+            };
+        }
+
+        Ok(self)
+    }
+}
+
+impl StaticFileServer<LocalFileSystem> {
+    /// Returns a fairing serving a local directory configured in `Rocket.toml`.
+    ///
+    /// The directory is set with the `root` key of the `[static_fs]` table, relative paths are
+    /// relative to the directory of `Rocket.toml`. It defaults to `static`. All other settings
+    /// are applied as described at `StaticFileServerBuilder::configure`, the prefix defaults
+    /// to `/`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// extern crate rocket;
+    /// extern crate rocket_static_fs;
+    ///
+    /// use rocket_static_fs::StaticFileServer;
+    ///
+    /// fn main() {
+    ///     rocket::ignite().attach(StaticFileServer::fairing());
+    /// }
+    /// ```
+    pub fn fairing() -> AdHoc {
+        AdHoc::on_attach("static_file_server_config", |rocket| {
+            let server = {
+                let config = rocket.config();
+                configured_root(config).and_then(|root| {
+                    let fs = LocalFileSystem::new(config.root_relative(root));
+                    StaticFileServer::builder(fs).configure(config)
+                })
+            };
+
+            match server {
+                Ok(builder) => Ok(rocket.attach(builder.build())),
+                Err(err) => {
+                    error!("invalid static file server configuration: {}", err);
+                    Err(rocket)
+                }
+            }
+        })
+    }
+}
+
+/// Returns the configured root directory.
+fn configured_root(config: &Config) -> Result<String, ConfigError> {
+    let table = match config.get_table(CONFIG_TABLE) {
+        Ok(table) => table,
+        Err(ConfigError::Missing(_)) => return Ok(DEFAULT_ROOT.to_string()),
+        Err(err) => return Err(err),
+    };
+
+    match table.get("root") {
+        Some(value) => match value.as_str() {
+            Some(root) => Ok(root.to_string()),
+            None => Err(bad_type(&format!("{}.root", CONFIG_TABLE), "string", value)),
+        },
+        None => Ok(DEFAULT_ROOT.to_string()),
+    }
+}
+
+fn boolean(name: &str, value: &Value) -> Result<bool, ConfigError> {
+    value
+        .as_bool()
+        .ok_or_else(|| bad_type(name, "boolean", value))
+}
+
+/// Accepts a single string as well as a list of strings.
+fn string_list(name: &str, value: &Value) -> Result<Vec<String>, ConfigError> {
+    if let Some(s) = value.as_str() {
+        return Ok(vec![s.to_string()]);
+    }
+
+    let values = value
+        .as_array()
+        .ok_or_else(|| bad_type(name, "string or array of strings", value))?;
+    values
+        .iter()
+        .map(|v| {
+            v.as_str()
+                .map(|s| s.to_string())
+                .ok_or_else(|| bad_type(name, "string or array of strings", value))
+        })
+        .collect()
+}
+
+fn bad_type(name: &str, expected: &'static str, actual: &Value) -> ConfigError {
+    ConfigError::BadType(name.to_string(), expected, actual.type_str(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use fs::LocalFileSystem;
+    use rocket;
+    use rocket::config::{Config, Environment, Value};
+    use rocket::http::Status;
+    use rocket::local::Client;
+    use std::collections::BTreeMap;
+    use StaticFileServer;
+
+    fn config(table: BTreeMap<String, Value>) -> Config {
+        Config::build(Environment::Development)
+            .extra("static_fs", table)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_configure() {
+        let mut table = BTreeMap::new();
+        table.insert("prefix".to_string(), Value::from("/test"));
+        table.insert("cache_max_age".to_string(), Value::from(60));
+        let config = config(table);
+
+        let server = StaticFileServer::builder(LocalFileSystem::new("testdata"))
+            .configure(&config)
+            .unwrap()
+            .build();
+        let client = Client::new(rocket::custom(config).attach(server)).expect("valid rocket");
+
+        let resp = client.get("/test/hello.txt").dispatch();
+        assert_eq!(resp.status(), Status::Ok);
+        assert_eq!(resp.headers().get_one("Cache-Control"), Some("max-age=60"));
+    }
+
+    #[test]
+    fn test_configure_bad_type() {
+        let mut table = BTreeMap::new();
+        table.insert("directory_listing".to_string(), Value::from("yes"));
+        let config = config(table);
+
+        let builder = StaticFileServer::builder(LocalFileSystem::new("testdata"));
+        assert!(builder.configure(&config).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! The error type of FileSystem operations.
+
+use rocket::http::Status;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+
+/// Errors returned by FileSystem implementations.
+///
+/// The server maps these to HTTP statuses, see `Error::status`.
+#[derive(Debug)]
+pub enum Error {
+    /// The path doesn't exist.
+    NotFound,
+    /// The path exists but may not be accessed.
+    PermissionDenied,
+    /// Any other I/O error.
+    Io(io::Error),
+    /// An error specific to the backend, e.g. an unsupported operation or a failed request.
+    Backend(String),
+}
+
+impl Error {
+    /// Creates a backend specific error with the given message.
+    pub fn backend<S: Into<String>>(message: S) -> Self {
+        Error::Backend(message.into())
+    }
+
+    /// Returns the status to respond with: 404 for `NotFound`, 403 for `PermissionDenied` and
+    /// 500 for everything else.
+    ///
+    /// The StaticFileServer answers with a 404 instead of the 403 if `deny_with_not_found` is set.
+    pub fn status(&self) -> Status {
+        match *self {
+            Error::NotFound => Status::NotFound,
+            Error::PermissionDenied => Status::Forbidden,
+            Error::Io(_) | Error::Backend(_) => Status::InternalServerError,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::NotFound => Error::NotFound,
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::NotFound => "file does not exist",
+            Error::PermissionDenied => "permission denied",
+            Error::Io(ref err) => err.description(),
+            Error::Backend(ref message) => message,
+        }
+    }
+
+    fn cause(&self) -> Option<&StdError> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::Io(ref err) => write!(f, "i/o error: {}", err),
+            _ => f.write_str(self.description()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_io_error() {
+        let err: Error = io::Error::new(io::ErrorKind::NotFound, "gone").into();
+        assert!(match err { Error::NotFound => true, _ => false });
+
+        let err: Error = io::Error::new(io::ErrorKind::PermissionDenied, "nope").into();
+        assert!(match err { Error::PermissionDenied => true, _ => false });
+
+        let err: Error = io::Error::new(io::ErrorKind::UnexpectedEof, "short").into();
+        assert!(match err { Error::Io(_) => true, _ => false });
+    }
+}
@@ -0,0 +1,61 @@
+//! Information about handled requests, passed to the callbacks and the access log.
+
+use rocket::http::Status;
+use rocket::Request;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Information about a served file, passed to the `on_serve` callback.
+#[derive(Debug)]
+pub struct ServeEvent<'a> {
+    /// The path of the request, e.g. `/assets/app.js`.
+    pub uri: &'a str,
+    /// The path of the served file relative to the root of the FileSystem.
+    pub path: &'a str,
+    /// The status of the response, e.g. `206 Partial Content` for range requests.
+    pub status: Status,
+    /// The number of bytes of the file sent in the body, before compression.
+    pub bytes: u64,
+    /// The time it took to prepare the response, sending the body isn't included.
+    pub duration: Duration,
+}
+
+/// Information about a request which couldn't be served, passed to the `on_error` callback.
+#[derive(Debug)]
+pub struct ErrorEvent<'a> {
+    /// The path of the request, e.g. `/assets/app.js`.
+    pub uri: &'a str,
+    /// The error status the request is answered with.
+    pub status: Status,
+    /// The time it took to prepare the response.
+    pub duration: Duration,
+}
+
+pub type ServeCallback = Box<Fn(&ServeEvent) + Send + Sync>;
+pub type ErrorCallback = Box<Fn(&ErrorEvent) + Send + Sync>;
+
+/// The time we started handling a request, kept in the request local cache.
+struct Started(Instant);
+
+/// Returns the time we started handling the request.
+///
+/// The first call for a request starts the clock.
+pub fn started(request: &Request) -> Instant {
+    request.local_cache(|| Started(Instant::now())).0
+}
+
+/// The number of bytes of the file sent for a request, kept in the request local cache.
+struct BodyBytes(Mutex<Option<u64>>);
+
+/// Remembers the number of bytes of the file sent in the body of the response.
+pub fn set_body_bytes(request: &Request, bytes: u64) {
+    let cached = request.local_cache(|| BodyBytes(Mutex::new(None)));
+    *cached.0.lock().unwrap() = Some(bytes);
+}
+
+/// Returns the number of bytes of the file sent in the body, if a file was served.
+pub fn body_bytes(request: &Request) -> Option<u64> {
+    let cached = request.local_cache(|| BodyBytes(Mutex::new(None)));
+    let bytes = *cached.0.lock().unwrap();
+    bytes
+}
@@ -0,0 +1,160 @@
+use fs::remote::{self, ObjectStore};
+use fs::token::{self, TokenCache};
+use reqwest::header::{AUTHORIZATION, RANGE};
+use reqwest::{Client, Method, Response};
+use std::path::Path;
+use std::time::Duration;
+use Error;
+
+/// The endpoint of the Azure Instance Metadata Service handing out managed identity tokens.
+const IDENTITY_ENDPOINT: &str = "http://169.254.169.254/metadata/identity/oauth2/token";
+
+/// The version of the Blob service API, bearer tokens require at least 2017-11-09.
+const API_VERSION: &str = "2019-02-02";
+
+/// Provides a FileSystem serving the blobs of an Azure Blob Storage container.
+///
+/// Works like the S3FileSystem: paths are mapped to blob names, optionally below a prefix,
+/// metadata is fetched with HEAD requests and ranges are requested from Azure directly.
+/// Requests are authorized with a shared access signature (SAS) or the managed identity of
+/// the Azure VM or App Service, otherwise the container has to be public. Listings aren't
+/// supported.
+///
+/// Every lookup is a request to Azure, see the `fs` module on caching. Requires the `azure`
+/// feature.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::AzureBlobFileSystem;
+///
+/// let fs = AzureBlobFileSystem::new("myaccount", "assets").managed_identity();
+/// ```
+pub struct AzureBlobFileSystem {
+    client: Client,
+    /// The URL of the container, e.g. `https://account.blob.core.windows.net/container`.
+    container_url: String,
+    prefix: String,
+    auth: Auth,
+    token: TokenCache,
+}
+
+enum Auth {
+    Anonymous,
+    /// The query string of a shared access signature.
+    Sas(String),
+    /// A managed identity, with the client ID of a user-assigned one.
+    ManagedIdentity(Option<String>),
+}
+
+impl AzureBlobFileSystem {
+    /// Serves the container of the storage account without authorization.
+    pub fn new(account: &str, container: &str) -> Self {
+        AzureBlobFileSystem {
+            client: Client::new(),
+            container_url: format!("https://{}.blob.core.windows.net/{}", account, container),
+            prefix: String::new(),
+            auth: Auth::Anonymous,
+            token: TokenCache::new(),
+        }
+    }
+
+    /// Authorizes requests with a shared access signature, e.g. `sv=2019-02-02&sr=c&sig=...`.
+    pub fn sas(mut self, token: &str) -> Self {
+        self.auth = Auth::Sas(token.trim_left_matches('?').to_string());
+        self
+    }
+
+    /// Authorizes requests with the system-assigned managed identity.
+    pub fn managed_identity(mut self) -> Self {
+        self.auth = Auth::ManagedIdentity(None);
+        self
+    }
+
+    /// Authorizes requests with the user-assigned managed identity having the given client ID.
+    pub fn user_assigned_identity(mut self, client_id: &str) -> Self {
+        self.auth = Auth::ManagedIdentity(Some(client_id.to_string()));
+        self
+    }
+
+    /// Serves only the blobs below a prefix, e.g. `public` serves `public/index.html` as
+    /// `index.html`.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = remote::normalize_prefix(prefix);
+        self
+    }
+
+    /// Gets an access token for the managed identity from the Instance Metadata Service.
+    fn fetch_token(&self, client_id: Option<&str>) -> Result<(String, Duration), Error> {
+        let mut query = vec![
+            ("api-version", "2018-02-01"),
+            ("resource", "https://storage.azure.com/"),
+        ];
+        if let Some(client_id) = client_id {
+            query.push(("client_id", client_id));
+        }
+
+        token::fetch_token(
+            self.client
+                .get(IDENTITY_ENDPOINT)
+                .query(&query)
+                .header("Metadata", "true"),
+        )
+    }
+
+    /// Returns the URL of the blob at `path`, including the shared access signature if any.
+    fn url(&self, path: &Path) -> Result<String, Error> {
+        let url = remote::url(&self.container_url, &self.prefix, path)?;
+        match self.auth {
+            Auth::Sas(ref sas) => Ok(format!("{}?{}", url, sas)),
+            _ => Ok(url),
+        }
+    }
+}
+
+impl ObjectStore for AzureBlobFileSystem {
+    /// Sends a request for the blob at `path`, from `start` on if given.
+    fn send(&self, method: Method, path: &Path, start: Option<u64>) -> Result<Response, Error> {
+        let url = self.url(path)?;
+        let mut request = self
+            .client
+            .request(method, &url)
+            .header("x-ms-version", API_VERSION);
+        if let Some(start) = start {
+            request = request.header(RANGE, remote::range(start).as_str());
+        }
+        if let Auth::ManagedIdentity(ref client_id) = self.auth {
+            let token = self
+                .token
+                .get(|| self.fetch_token(client_id.as_ref().map(|id| id.as_str())))?;
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token).as_str());
+        }
+        remote::check(request.send())
+    }
+}
+
+object_file_system!(AzureBlobFileSystem);
+
+#[cfg(test)]
+mod tests {
+    use super::AzureBlobFileSystem;
+    use std::path::Path;
+
+    #[test]
+    fn test_url() {
+        let fs = AzureBlobFileSystem::new("account", "assets");
+        assert_eq!(
+            fs.url(Path::new("css/app.css")).unwrap(),
+            "https://account.blob.core.windows.net/assets/css/app.css"
+        );
+        assert!(fs.url(Path::new("")).is_err());
+
+        let fs = AzureBlobFileSystem::new("account", "assets")
+            .prefix("/public/")
+            .sas("?sig=abc");
+        assert_eq!(
+            fs.url(Path::new("my file.txt")).unwrap(),
+            "https://account.blob.core.windows.net/assets/public/my%20file.txt?sig=abc"
+        );
+    }
+}
@@ -0,0 +1,530 @@
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use Error;
+
+/// What an entry costs on top of its data, roughly the size of the bookkeeping.
+const ENTRY_OVERHEAD: u64 = 256;
+
+/// Caches metadata and contents of the files of another FileSystem in memory.
+///
+/// The cache is bounded by the total number of bytes, the least recently used files are
+/// evicted first. Files larger than `max_file_size` are only cached with their metadata. Paths
+/// which don't exist are cached as well, so new files only show up once their entry expires
+/// or is invalidated. Without a TTL entries never expire.
+///
+/// Directory listings aren't cached.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{CachingFileSystem, LocalFileSystem};
+/// use std::time::Duration;
+///
+/// let fs = CachingFileSystem::new(LocalFileSystem::new("static"), 64 * 1024 * 1024)
+///     .max_file_size(1024 * 1024)
+///     .ttl(Duration::from_secs(60));
+/// ```
+pub struct CachingFileSystem<T> {
+    inner: T,
+    max_bytes: u64,
+    max_file_size: u64,
+    ttl: Option<Duration>,
+    /// Whether hits are checked against the metadata of the inner FileSystem.
+    revalidate: bool,
+    lru: Arc<Mutex<Lru>>,
+}
+
+/// An in-memory cache of small files in front of the FileSystem of a StaticFileServer, see
+/// `StaticFileServerBuilder::hot_cache`.
+///
+/// Works like a CachingFileSystem, but keeps the type of the server's FileSystem. Clones
+/// share their entries, so a clone can be kept around to invalidate files which changed.
+///
+/// The cache sits in front of the server's FileSystem, so `FileSystem::changed` calls, e.g. of
+/// a WatchingFileSystem, don't reach it. Instead every hit is checked against the size and
+/// modification date the FileSystem reports, which is much cheaper than reading the file.
+/// A file which changes without changing either, or on a FileSystem without modification
+/// dates, is served from the cache until it's invalidated or its entry expires. Checking can
+/// be turned off for FileSystems whose files never change, e.g. an EmbeddedFileSystem.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::HotCache;
+///
+/// let cache = HotCache::new(16 * 1024 * 1024).max_file_size(64 * 1024);
+/// ```
+#[derive(Clone)]
+pub struct HotCache {
+    max_bytes: u64,
+    max_file_size: u64,
+    ttl: Option<Duration>,
+    revalidate: bool,
+    lru: Arc<Mutex<Lru>>,
+}
+
+impl HotCache {
+    /// Caches up to `max_bytes` of files.
+    pub fn new(max_bytes: u64) -> Self {
+        HotCache {
+            max_bytes,
+            max_file_size: max_bytes,
+            ttl: None,
+            revalidate: true,
+            lru: Arc::new(Mutex::new(Lru::new())),
+        }
+    }
+
+    /// Sets the size of the largest file whose contents are cached, defaults to `max_bytes`.
+    pub fn max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Sets how long entries are used before they're fetched again.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets whether hits are checked against the metadata of the FileSystem, defaults to true.
+    pub fn revalidate(mut self, revalidate: bool) -> Self {
+        self.revalidate = revalidate;
+        self
+    }
+
+    /// Drops the entry of a path, e.g. after the file changed.
+    pub fn invalidate(&self, path: &str) {
+        self.lru.lock().unwrap().remove(path.trim_left_matches('/'));
+    }
+
+    /// Drops the entries of a path and of everything below it, or all entries if the path is
+    /// `None`, like `FileSystem::changed`.
+    pub fn changed(&self, path: Option<&Path>) {
+        let mut lru = self.lru.lock().unwrap();
+        match path {
+            Some(path) => lru.remove_below(&path.to_string_lossy()),
+            None => lru.clear(),
+        }
+    }
+
+    /// Drops all entries.
+    pub fn clear(&self) {
+        self.lru.lock().unwrap().clear();
+    }
+
+    /// Returns a FileSystem reading `fs` through this cache.
+    pub fn cached<'a, T: FileSystem + ?Sized>(&self, fs: &'a T) -> CachingFileSystem<&'a T> {
+        CachingFileSystem {
+            inner: fs,
+            max_bytes: self.max_bytes,
+            max_file_size: self.max_file_size,
+            ttl: self.ttl,
+            revalidate: self.revalidate,
+            lru: self.lru.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Entry {
+    /// None if there's nothing at the path.
+    metadata: Option<Metadata>,
+    etag: Option<String>,
+    content_type: Option<String>,
+    data: Option<Arc<[u8]>>,
+    fetched: Instant,
+}
+
+impl Entry {
+    fn cost(&self, path: &str) -> u64 {
+        let data = self.data.as_ref().map_or(0, |data| data.len() as u64);
+        ENTRY_OVERHEAD + path.len() as u64 + data
+    }
+}
+
+struct Lru {
+    /// Maps paths to their entry and the time of their last use.
+    entries: HashMap<String, (Entry, u64)>,
+    /// Maps the times of last use to the paths, the least recently used one first.
+    order: BTreeMap<u64, String>,
+    clock: u64,
+    bytes: u64,
+}
+
+impl Lru {
+    fn new() -> Self {
+        Lru {
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            clock: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Returns the entry of the path if it's fresh, marking it as used.
+    fn get(&mut self, path: &str, ttl: Option<Duration>) -> Option<Entry> {
+        let stale = match self.entries.get(path) {
+            Some(&(ref entry, _)) => ttl.map_or(false, |ttl| entry.fetched.elapsed() >= ttl),
+            None => return None,
+        };
+        if stale {
+            self.remove(path);
+            return None;
+        }
+
+        self.clock += 1;
+        let &mut (ref entry, ref mut used) = self.entries.get_mut(path)?;
+        self.order.remove(used);
+        *used = self.clock;
+        self.order.insert(self.clock, path.to_string());
+        Some(entry.clone())
+    }
+
+    /// Adds an entry, evicting the least recently used ones while the cache is too large.
+    fn insert(&mut self, path: &str, entry: Entry, max_bytes: u64) {
+        self.remove(path);
+
+        self.clock += 1;
+        self.bytes += entry.cost(path);
+        self.entries.insert(path.to_string(), (entry, self.clock));
+        self.order.insert(self.clock, path.to_string());
+
+        while self.bytes > max_bytes {
+            let oldest = match self.order.values().next() {
+                Some(oldest) => oldest.clone(),
+                None => break,
+            };
+            self.remove(&oldest);
+        }
+    }
+
+    fn remove(&mut self, path: &str) {
+        if let Some((entry, used)) = self.entries.remove(path) {
+            self.bytes -= entry.cost(path);
+            self.order.remove(&used);
+        }
+    }
+
+    /// Removes the entries of a path and of all paths below it.
+    fn remove_below(&mut self, path: &str) {
+        if path.is_empty() {
+            self.clear();
+            return;
+        }
+
+        let dir = format!("{}/", path);
+        let paths: Vec<String> = self.entries
+            .keys()
+            .filter(|key| key.starts_with(&dir))
+            .cloned()
+            .collect();
+        for path in paths {
+            self.remove(&path);
+        }
+        self.remove(path);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+        self.bytes = 0;
+    }
+}
+
+impl<T: FileSystem> CachingFileSystem<T> {
+    /// Caches up to `max_bytes` of the inner FileSystem.
+    pub fn new(inner: T, max_bytes: u64) -> Self {
+        CachingFileSystem {
+            inner,
+            max_bytes,
+            max_file_size: max_bytes,
+            ttl: None,
+            revalidate: false,
+            lru: Arc::new(Mutex::new(Lru::new())),
+        }
+    }
+
+    /// Sets the size of the largest file whose contents are cached, defaults to `max_bytes`.
+    pub fn max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// Sets how long entries are used before they're fetched again.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Drops the entry of a path, e.g. after the file changed.
+    pub fn invalidate(&self, path: &str) {
+        self.lru.lock().unwrap().remove(path.trim_left_matches('/'));
+    }
+
+    /// Drops all entries.
+    pub fn clear(&self) {
+        self.lru.lock().unwrap().clear();
+    }
+
+    /// Returns the cached entry of the path, fetching its metadata if there's none.
+    fn entry(&self, path: &Path) -> Result<Entry, Error> {
+        let key = path.to_string_lossy();
+        let cached = self.lru.lock().unwrap().get(&key, self.ttl);
+        let metadata = match cached {
+            Some(entry) => {
+                if !self.revalidate {
+                    return Ok(entry);
+                }
+                let metadata = self.inner.metadata(path);
+                if is_unchanged(&entry, &metadata) {
+                    return Ok(entry);
+                }
+                metadata
+            }
+            None => self.inner.metadata(path),
+        };
+
+        let entry = match metadata {
+            Ok(metadata) => Entry {
+                etag: if metadata.is_file {
+                    self.inner.etag(path)
+                } else {
+                    None
+                },
+                content_type: if metadata.is_file {
+                    self.inner.content_type(path)
+                } else {
+                    None
+                },
+                metadata: Some(metadata),
+                data: None,
+                fetched: Instant::now(),
+            },
+            Err(Error::NotFound) => Entry {
+                metadata: None,
+                etag: None,
+                content_type: None,
+                data: None,
+                fetched: Instant::now(),
+            },
+            Err(err) => return Err(err),
+        };
+
+        self.lru
+            .lock()
+            .unwrap()
+            .insert(&key, entry.clone(), self.max_bytes);
+        Ok(entry)
+    }
+
+    /// Returns the contents of the file, if it's small enough to be cached.
+    fn data(&self, path: &Path) -> Result<Option<Arc<[u8]>>, Error> {
+        let mut entry = self.entry(path)?;
+        if let Some(ref data) = entry.data {
+            return Ok(Some(data.clone()));
+        }
+
+        let size = match entry.metadata {
+            Some(ref metadata) if metadata.is_file => metadata.size,
+            _ => return Err(Error::NotFound),
+        };
+        if size > self.max_file_size {
+            return Ok(None);
+        }
+
+        let mut bytes = Vec::with_capacity(size as usize);
+        self.inner.open(path, None)?.read_to_end(&mut bytes)?;
+        let data: Arc<[u8]> = Arc::from(bytes);
+        entry.data = Some(data.clone());
+        self.lru
+            .lock()
+            .unwrap()
+            .insert(&path.to_string_lossy(), entry, self.max_bytes);
+        Ok(Some(data))
+    }
+
+    fn metadata_of(&self, path: &Path) -> Result<Metadata, Error> {
+        self.entry(path)?.metadata.ok_or(Error::NotFound)
+    }
+}
+
+/// Returns true if the entry still matches the current metadata of its path.
+fn is_unchanged(entry: &Entry, metadata: &Result<Metadata, Error>) -> bool {
+    match (&entry.metadata, metadata) {
+        (&Some(ref cached), &Ok(ref current)) => {
+            cached.size == current.size
+                && cached.modified == current.modified
+                && cached.is_file == current.is_file
+                && cached.is_dir == current.is_dir
+        }
+        (&None, &Err(Error::NotFound)) => true,
+        _ => false,
+    }
+}
+
+impl<T: FileSystem> FileSystem for CachingFileSystem<T> {
+    fn is_file(&self, path: &Path) -> bool {
+        self.metadata_of(path).map_or(false, |meta| meta.is_file)
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        match self.metadata_of(path)?.modified {
+            Some(modified) => Ok(modified),
+            None => self.inner.last_modified(path),
+        }
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        Ok(self.metadata_of(path)?.size)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        match self.data(path)? {
+            Some(data) => {
+                let mut reader = Cursor::new(data);
+                if let Some(start) = start {
+                    reader.seek(SeekFrom::Start(start))?;
+                }
+                Ok(Box::new(reader))
+            }
+            None => self.inner.open(path, start),
+        }
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.inner.path_valid(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        match self.data(path)? {
+            Some(data) => Ok(Box::new(Cursor::new(data))),
+            None => self.inner.open_seek(path),
+        }
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.metadata_of(path).map_or(false, |meta| meta.is_dir)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        self.inner.read_dir(path)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.entry(path).ok().and_then(|entry| entry.etag)
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.entry(path).ok().and_then(|entry| entry.content_type)
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        self.inner.open_encoded(path, encoding)
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        self.inner.as_bytes(path)
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        {
+            let mut lru = self.lru.lock().unwrap();
+            match path {
+                Some(path) => lru.remove_below(&path.to_string_lossy()),
+                None => lru.clear(),
+            }
+        }
+        self.inner.changed(path);
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        self.metadata_of(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CachingFileSystem;
+    use fs::{FileSystem, InMemoryFileSystem};
+    use std::io::Read;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::SystemTime;
+    use Error;
+
+    /// Counts how often files are opened.
+    struct Counting {
+        fs: InMemoryFileSystem,
+        opened: AtomicUsize,
+    }
+
+    impl FileSystem for Counting {
+        fn is_file(&self, path: &Path) -> bool {
+            self.fs.is_file(path)
+        }
+
+        fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+            self.fs.last_modified(path)
+        }
+
+        fn size(&self, path: &Path) -> Result<u64, Error> {
+            self.fs.size(path)
+        }
+
+        fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+            self.opened.fetch_add(1, Ordering::SeqCst);
+            self.fs.open(path, start)
+        }
+
+        fn path_valid(&self, path: &Path) -> bool {
+            self.fs.path_valid(path)
+        }
+    }
+
+    fn read(fs: &FileSystem, path: &str) -> String {
+        let mut s = String::new();
+        fs.open(Path::new(path), None)
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        s
+    }
+
+    #[test]
+    fn test_caching_file_system() {
+        let mut files = InMemoryFileSystem::new();
+        files.insert("a.txt", "aaaa");
+        files.insert("b.txt", "bbbb");
+        files.insert("large.txt", "large file");
+        let counting = Counting {
+            fs: files,
+            opened: AtomicUsize::new(0),
+        };
+
+        // Room for about two small files
+        let fs = CachingFileSystem::new(&counting, 2 * 256 + 20).max_file_size(8);
+        assert_eq!(read(&fs, "a.txt"), "aaaa");
+        assert_eq!(read(&fs, "a.txt"), "aaaa");
+        assert_eq!(counting.opened.load(Ordering::SeqCst), 1);
+
+        // Large files are read from the inner FileSystem every time
+        read(&fs, "large.txt");
+        read(&fs, "large.txt");
+        assert_eq!(counting.opened.load(Ordering::SeqCst), 3);
+
+        // a.txt was used least recently, so it's evicted for b.txt
+        read(&fs, "b.txt");
+        read(&fs, "a.txt");
+        assert_eq!(counting.opened.load(Ordering::SeqCst), 5);
+
+        fs.invalidate("a.txt");
+        read(&fs, "a.txt");
+        assert_eq!(counting.opened.load(Ordering::SeqCst), 6);
+        assert!(!fs.is_file(Path::new("missing.txt")));
+    }
+}
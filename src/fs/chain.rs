@@ -0,0 +1,165 @@
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use Error;
+use std::io::Read;
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Serves files from the first of two FileSystems which has them.
+///
+/// Chains can be extended with `or`, so any number of FileSystems can be tried in order.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{ChainedFileSystem, LocalFileSystem};
+///
+/// // Local overrides take precedence over the shipped assets
+/// let fs = ChainedFileSystem::new(LocalFileSystem::new("overrides"), LocalFileSystem::new("assets"));
+/// ```
+pub struct ChainedFileSystem<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: FileSystem, B: FileSystem> ChainedFileSystem<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        ChainedFileSystem { first, second }
+    }
+
+    /// Appends another FileSystem which is tried last.
+    pub fn or<C: FileSystem>(self, next: C) -> ChainedFileSystem<Self, C> {
+        ChainedFileSystem::new(self, next)
+    }
+}
+
+impl<A: FileSystem, B: FileSystem> FileSystem for ChainedFileSystem<A, B> {
+    fn is_file(&self, path: &Path) -> bool {
+        self.first.is_file(path) || self.second.is_file(path)
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        if self.first.is_file(path) {
+            self.first.last_modified(path)
+        } else {
+            self.second.last_modified(path)
+        }
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        if self.first.is_file(path) {
+            self.first.size(path)
+        } else {
+            self.second.size(path)
+        }
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        if self.first.is_file(path) {
+            self.first.open(path, start)
+        } else {
+            self.second.open(path, start)
+        }
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.first.path_valid(path) || self.second.path_valid(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        if self.first.is_file(path) {
+            self.first.open_seek(path)
+        } else {
+            self.second.open_seek(path)
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        match self.first.metadata(path) {
+            Ok(ref meta) if meta.is_file => Ok(meta.clone()),
+            _ => self.second.metadata(path),
+        }
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        if self.first.is_file(path) {
+            self.first.etag(path)
+        } else {
+            self.second.etag(path)
+        }
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        if self.first.is_file(path) {
+            self.first.content_type(path)
+        } else {
+            self.second.content_type(path)
+        }
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        if self.first.is_file(path) {
+            self.first.open_encoded(path, encoding)
+        } else {
+            self.second.open_encoded(path, encoding)
+        }
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        if self.first.is_file(path) {
+            self.first.as_bytes(path)
+        } else {
+            self.second.as_bytes(path)
+        }
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        self.first.changed(path);
+        self.second.changed(path);
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.first.is_dir(path) || self.second.is_dir(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        if self.first.is_dir(path) {
+            self.first.read_dir(path)
+        } else {
+            self.second.read_dir(path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChainedFileSystem;
+    use fs::{FileSystem, LocalFileSystem};
+    use std::fs;
+    use std::io::Read;
+    use std::path::Path;
+
+    #[test]
+    fn test_chained_file_system() {
+        let chain = ChainedFileSystem::new(
+            LocalFileSystem::new("src/fs/embedded"),
+            LocalFileSystem::new("src/fs"),
+        ).or(LocalFileSystem::new("testdata"));
+
+        let read = |path: &str| {
+            let mut s = String::new();
+            chain
+                .open(Path::new(path), None)
+                .unwrap()
+                .read_to_string(&mut s)
+                .unwrap();
+            s
+        };
+        let embedded = fs::read_to_string("src/fs/embedded/mod.rs").unwrap();
+        assert_eq!(read("mod.rs"), embedded);
+        assert_eq!(read("chain.rs"), fs::read_to_string("src/fs/chain.rs").unwrap());
+        assert_eq!(read("hello.txt"), "Hello World!");
+        assert_eq!(chain.size(Path::new("mod.rs")).unwrap(), embedded.len() as u64);
+        assert!(!chain.is_file(Path::new("missing.txt")));
+        assert!(chain.open(Path::new("missing.txt"), None).is_err());
+    }
+}
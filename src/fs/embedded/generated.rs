@@ -0,0 +1,322 @@
+use super::{collect_dirs, collect_files, etag, sha256, PackageOptions};
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::fs::File;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A file of generated source, see `write_generated_source`.
+///
+/// The fields are public so generated source can build the files as constants, you don't need
+/// to touch them yourself.
+#[derive(Debug)]
+pub struct GeneratedFile {
+    /// The path relative to the root, e.g. `css/app.css`.
+    pub path: &'static str,
+    pub data: &'static [u8],
+    /// The modification date in seconds since the epoch.
+    pub modified: u64,
+    pub mime: Option<&'static str>,
+    pub etag: &'static str,
+}
+
+/// Provides a FileSystem serving files embedded as generated Rust source.
+///
+/// An alternative to packages: `write_generated_source` writes a Rust expression embedding
+/// every file with its own `include_bytes!`, which is included into the application with
+/// `include!`. Nothing has to be parsed at startup and cargo only re-embeds the files which
+/// changed. The files are sorted by path, so lookups are binary searches.
+///
+/// # Example
+///
+/// In `build.rs`:
+///
+/// ```
+/// extern crate rocket_static_fs;
+///
+/// use rocket_static_fs::fs::{create_generated_source_from_dir, PackageOptions};
+/// use std::fs::File;
+///
+/// fn main() {
+///     let out = concat!(env!("CARGO_MANIFEST_DIR"), "/target/assets.rs");
+///     let assets_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata");
+///     let mut out = File::create(out).unwrap();
+///     create_generated_source_from_dir(assets_dir, &PackageOptions::new(), &mut out).unwrap();
+/// }
+/// ```
+///
+/// In `main.rs`:
+///
+/// ```
+/// extern crate rocket_static_fs;
+///
+/// use rocket_static_fs::fs::{GeneratedFile, GeneratedFileSystem};
+///
+/// static ASSETS: &[GeneratedFile] =
+///     include!(concat!(env!("CARGO_MANIFEST_DIR"), "/target/assets.rs"));
+///
+/// fn main() {
+///     let fs = GeneratedFileSystem::new(ASSETS);
+/// }
+/// ```
+pub struct GeneratedFileSystem {
+    files: &'static [GeneratedFile],
+    /// Maps the path of every directory to the names of its children, the root is "".
+    dirs: HashMap<String, BTreeSet<String>>,
+}
+
+impl GeneratedFileSystem {
+    pub fn new(files: &'static [GeneratedFile]) -> Self {
+        let paths: Vec<String> = files.iter().map(|file| file.path.to_string()).collect();
+        GeneratedFileSystem {
+            files,
+            dirs: collect_dirs(&paths),
+        }
+    }
+
+    fn file(&self, path: &Path) -> Result<&'static GeneratedFile, ::Error> {
+        let path = path.to_str().ok_or(::Error::NotFound)?;
+        let files = self.files;
+        match files.binary_search_by(|file| file.path.cmp(path)) {
+            Ok(i) => Ok(&files[i]),
+            Err(_) => Err(::Error::NotFound),
+        }
+    }
+}
+
+fn modified(file: &GeneratedFile) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(file.modified)
+}
+
+impl FileSystem for GeneratedFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.file(path).is_ok()
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, ::Error> {
+        Ok(modified(self.file(path)?))
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, ::Error> {
+        Ok(self.file(path)?.data.len() as u64)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, ::Error> {
+        let mut reader = Cursor::new(self.file(path)?.data);
+        reader.set_position(start.unwrap_or(0));
+        Ok(Box::new(reader))
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, ::Error> {
+        Ok(Box::new(Cursor::new(self.file(path)?.data)))
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        self.file(path).ok().map(|file| file.data)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.to_str()
+            .map_or(false, |path| self.dirs.contains_key(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, ::Error> {
+        let dir = path.to_str().ok_or(::Error::NotFound)?;
+        let children = self.dirs.get(dir).ok_or(::Error::NotFound)?;
+
+        let entries = children
+            .iter()
+            .map(|name| {
+                let child = if dir.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{}", dir, name)
+                };
+                match self.file(Path::new(&child)) {
+                    Ok(file) => DirEntry {
+                        name: name.clone(),
+                        is_dir: false,
+                        size: file.data.len() as u64,
+                        modified: Some(modified(file)),
+                    },
+                    Err(_) => DirEntry {
+                        name: name.clone(),
+                        is_dir: true,
+                        size: 0,
+                        modified: None,
+                    },
+                }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.file(path).ok().map(|file| file.etag.to_string())
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.file(path)
+            .ok()
+            .and_then(|file| file.mime)
+            .map(|mime| mime.to_string())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, ::Error> {
+        if let Ok(file) = self.file(path) {
+            return Ok(Metadata {
+                size: file.data.len() as u64,
+                modified: Some(modified(file)),
+                is_file: true,
+                is_dir: false,
+            });
+        }
+
+        if self.is_dir(path) {
+            Ok(Metadata {
+                size: 0,
+                modified: None,
+                is_file: false,
+                is_dir: true,
+            })
+        } else {
+            Err(::Error::NotFound)
+        }
+    }
+}
+
+/// Writes Rust source embedding the given files below `root`, to be served by a
+/// `GeneratedFileSystem`.
+///
+/// The source is an expression of the type `&[GeneratedFile]` including every file with
+/// `include_bytes!` by its absolute path. The MIME types and modification dates are determined
+/// like for packages, compression and encryption don't apply.
+pub fn write_generated_source<W, T, P>(
+    root: P,
+    input_files: &[T],
+    options: &PackageOptions,
+    writer: &mut W,
+) -> Result<(), Box<Error>>
+where
+    P: AsRef<Path>,
+    W: Write,
+    T: AsRef<str> + Clone + Ord,
+{
+    let root = root.as_ref().canonicalize()?;
+    let mut files = Vec::from(input_files);
+    files.sort();
+    files.dedup();
+
+    writeln!(writer, "// Generated by rocket_static_fs, don't edit.")?;
+    writeln!(writer, "&[")?;
+    for f in &files {
+        let path = root.join(f.as_ref());
+        let modified = options.modified_of(path.metadata()?.modified()?).max(0);
+
+        let mut data = Vec::new();
+        File::open(&path)?.read_to_end(&mut data)?;
+        let path = path
+            .to_str()
+            .ok_or_else(|| format!("path is no valid UTF-8: {}", path.display()))?;
+
+        writeln!(writer, "    ::rocket_static_fs::fs::GeneratedFile {{")?;
+        writeln!(writer, "        path: {:?},", f.as_ref())?;
+        writeln!(writer, "        data: include_bytes!({:?}),", path)?;
+        writeln!(writer, "        modified: {},", modified)?;
+        writeln!(writer, "        mime: {:?},", options.mime_type_of(f.as_ref()))?;
+        writeln!(writer, "        etag: {:?},", etag(&sha256(&data)))?;
+        writeln!(writer, "    }},")?;
+    }
+    writeln!(writer, "]")?;
+
+    Ok(())
+}
+
+/// Writes Rust source embedding all files below `dir`, see `write_generated_source`.
+pub fn create_generated_source_from_dir<P, W>(
+    dir: P,
+    options: &PackageOptions,
+    writer: &mut W,
+) -> Result<(), Box<Error>>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    let (root, files, _) = collect_files(dir, &[], &[])?;
+    write_generated_source(root, &files, options, writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_generated_source, GeneratedFile, GeneratedFileSystem};
+    use fs::embedded::PackageOptions;
+    use fs::FileSystem;
+    use std::io::Read;
+    use std::path::Path;
+
+    static FILES: &[GeneratedFile] = &[
+        GeneratedFile {
+            path: "css/app.css",
+            data: b"body {}",
+            modified: 1_500_000_000,
+            mime: Some("text/css"),
+            etag: "\"1\"",
+        },
+        GeneratedFile {
+            path: "index.html",
+            data: b"<h1>Hello</h1>",
+            modified: 1_500_000_000,
+            mime: None,
+            etag: "\"2\"",
+        },
+    ];
+
+    #[test]
+    fn test_generated_file_system() {
+        let fs = GeneratedFileSystem::new(FILES);
+
+        assert!(fs.is_file(Path::new("css/app.css")));
+        assert!(fs.is_dir(Path::new("css")));
+        assert!(!fs.is_file(Path::new("css")));
+        assert!(!fs.is_file(Path::new("missing.html")));
+        assert_eq!(fs.content_type(Path::new("css/app.css")), Some("text/css".to_string()));
+        assert_eq!(fs.etag(Path::new("index.html")), Some("\"2\"".to_string()));
+        assert_eq!(fs.as_bytes(Path::new("css/app.css")), Some(&b"body {}"[..]));
+        assert_eq!(fs.as_bytes(Path::new("css")), None);
+
+        let mut s = String::new();
+        fs.open(Path::new("index.html"), Some(4))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "Hello</h1>");
+
+        let names: Vec<String> = fs.read_dir(Path::new(""))
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["css", "index.html"]);
+    }
+
+    #[test]
+    fn test_write_generated_source() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata");
+        let mut source = Vec::new();
+        let files = ["inner/other.txt", "hello.txt"];
+        write_generated_source(dir, &files, &PackageOptions::new(), &mut source).unwrap();
+        let source = String::from_utf8(source).unwrap();
+
+        assert!(source.starts_with("// Generated by rocket_static_fs"));
+        let hello = source.find("\"hello.txt\"").unwrap();
+        assert!(hello < source.find("\"inner/other.txt\"").unwrap());
+        assert!(source.contains("testdata/hello.txt\"),"));
+        assert!(source.contains("mime: Some(\"text/plain\"),"));
+    }
+}
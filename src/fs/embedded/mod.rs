@@ -1,13 +1,43 @@
+#[cfg(feature = "brotli")]
+use brotli;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use chrono::{DateTime, TimeZone, Utc};
-use fs::FileSystem;
-use std::collections::HashMap;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+#[cfg(feature = "encryption")]
+use fs::encrypted::{decrypt_file, encrypt_file, key_from_env};
+use fs::{DirEntry, FileSystem, LocalFileSystem, Metadata, ReadSeek};
+#[cfg(feature = "encryption")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+use mime_guess::get_mime_type_opt;
+use serde_json;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::env;
 use std::error::Error;
 use std::fs::File;
 use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
-use std::path::Path;
-use std::time::SystemTime;
+use std::mem;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
+use Glob;
+
+mod generated;
+mod packager;
+
+pub use self::generated::{
+    create_generated_source_from_dir, write_generated_source, GeneratedFile, GeneratedFileSystem,
+};
+pub use self::packager::{PackageChanges, Packager};
 
 /// Provides a FileSystem which is embedded in the binary.
 ///
@@ -31,7 +61,14 @@ use walkdir::WalkDir;
 /// }
 /// ```
 ///
-/// This will create the package every time you build your application.
+/// This will create the package every time you build your application. To shrink the binary,
+/// use `create_package_from_dir_with` and `PackageCompression::Gzip` (or `Brotli` with the
+/// `brotli` feature) instead. Entries are decompressed when they are opened, gzipped entries
+/// are sent to clients accepting gzip as they are.
+///
+/// The MIME types of the files are determined when the package is written, see
+/// `PackageOptions::mime_type`, and served as they are. So are the ETags, which are made of
+/// content hashes and stay the same as long as the content does.
 ///
 /// To finally load it in your application.
 ///
@@ -49,86 +86,1044 @@ use walkdir::WalkDir;
 ///     // Do your setup like shown in root of the documentation.
 /// }
 /// ```
+///
+/// The `embed_package!` macro does the same in one go.
+///
+/// Packages can also be shipped next to the binary and loaded at startup with `from_file`, or
+/// mapped into memory with `open_mmap`.
 pub struct EmbeddedFileSystem {
     package: Package,
+    verify_reads: bool,
+    /// The directory files are read from first, see `source_dir`.
+    source: Option<LocalFileSystem>,
+}
+
+/// Embeds the package at the given path into the binary and loads it as an EmbeddedFileSystem.
+///
+/// The path is resolved like the one of `include_bytes!`, relative to the current file. Panics
+/// with the path and the error if the package is invalid, it was built with the binary after
+/// all.
+///
+/// # Example
+///
+/// ```
+/// #[macro_use]
+/// extern crate rocket_static_fs;
+///
+/// fn main() {
+///     let fs = embed_package!(concat!(env!("CARGO_MANIFEST_DIR"), "/target/test.package"));
+///
+///     // Do your setup like shown in root of the documentation.
+/// }
+/// ```
+#[macro_export]
+macro_rules! embed_package {
+    ($path:expr) => {
+        $crate::fs::EmbeddedFileSystem::from_bytes(include_bytes!($path))
+            .unwrap_or_else(|err| panic!("invalid package {}: {}", $path, err))
+    };
 }
 
 impl EmbeddedFileSystem {
+    /// Loads a package, failing if its checksums don't match its content.
     pub fn from_bytes(bytes: &'static [u8]) -> Result<Self, Box<Error>> {
-        let package = Package::from_bytes(bytes)?;
-        Ok(EmbeddedFileSystem { package })
+        Ok(EmbeddedFileSystem::from_package(Package::from_bytes(bytes)?))
+    }
+
+    /// Loads a package held in memory, e.g. downloaded at startup.
+    pub fn from_vec(bytes: Vec<u8>) -> Result<Self, Box<Error>> {
+        Ok(EmbeddedFileSystem::from_package(Package::from_vec(bytes)?))
+    }
+
+    /// Reads the package file at `path` into memory and loads it.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<Error>> {
+        Ok(EmbeddedFileSystem::from_package(Package::from_file(path)?))
+    }
+
+    /// Maps the package file at `path` into memory and serves the files out of the mapping, so
+    /// only the parts being read are loaded. Requires the `mmap` feature.
+    ///
+    /// Only the metadata is verified when loading, checking every entry would read the whole
+    /// file. The entries are verified whenever they are opened instead, use
+    /// `verify_reads(false)` to skip that.
+    ///
+    /// The file must never be modified or truncated while it's mapped: reading a truncated
+    /// mapping crashes the process, which verifying can't prevent. Replace a package by
+    /// writing the new one next to it and renaming it over the old one, the mapping keeps
+    /// the old file alive until it's dropped.
+    #[cfg(feature = "mmap")]
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Self, Box<Error>> {
+        let file = File::open(path)?;
+        // SAFETY: The mapping is only read from, which is sound as long as nothing modifies
+        // or truncates the file while it's mapped, as required by the documentation above.
+        // Packages are replaced by renaming, which leaves the mapped file untouched.
+        let mmap = unsafe { Mmap::map(&file)? };
+        let package = Package::from_data(Arc::new(mmap), false)?;
+        Ok(EmbeddedFileSystem::from_package(package).verify_reads(true))
+    }
+
+    /// Loads several packages and serves them as one, files of later packages override the
+    /// ones of earlier packages, see `Package::merge`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let fs = EmbeddedFileSystem::from_layers(&[
+    ///     include_bytes!("base.package"),
+    ///     include_bytes!("brand.package"),
+    /// ]).unwrap();
+    /// ```
+    pub fn from_layers(layers: &[&'static [u8]]) -> Result<Self, Box<Error>> {
+        let mut packages = Vec::new();
+        for &bytes in layers {
+            packages.push(Package::from_bytes(bytes)?);
+        }
+        Ok(EmbeddedFileSystem::from_package(Package::merge(packages)))
+    }
+
+    /// Loads a package split into parts, e.g. by `Packager::split`, so no single
+    /// `include_bytes!` has to embed all of it, see `Package::from_parts`.
+    pub fn from_parts(parts: &[&'static [u8]]) -> Result<Self, Box<Error>> {
+        Ok(EmbeddedFileSystem::from_package(Package::from_parts(parts)?))
+    }
+
+    /// Serves the files of a package which is already loaded.
+    pub fn from_package(package: Package) -> Self {
+        EmbeddedFileSystem {
+            package,
+            verify_reads: false,
+            source: None,
+        }
+    }
+
+    /// Returns the package the files are served from, e.g. to list its entries.
+    pub fn package(&self) -> &Package {
+        &self.package
+    }
+
+    /// Sets the key to decrypt an encrypted package with, see `Package::decryption_key`.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let fs = EmbeddedFileSystem::from_bytes(include_bytes!("assets.package"))?
+    ///     .decryption_key_from_env("ASSETS_KEY")?;
+    /// ```
+    #[cfg(feature = "encryption")]
+    pub fn decryption_key(mut self, key: [u8; 32]) -> Self {
+        self.package = self.package.decryption_key(key);
+        self
+    }
+
+    /// Like `decryption_key`, reading the key from an environment variable, see
+    /// `Package::decryption_key_from_env`.
+    #[cfg(feature = "encryption")]
+    pub fn decryption_key_from_env(mut self, var: &str) -> Result<Self, Box<Error>> {
+        self.package = self.package.decryption_key_from_env(var)?;
+        Ok(self)
+    }
+
+    /// Serves files from `dir` instead of the package if they exist there, e.g. the directory
+    /// the package was built from, so changed assets show up without rebuilding. Files missing
+    /// in `dir` are still served from the package, directory listings show both.
+    ///
+    /// Meant for development, see `source_dir_in_debug`.
+    pub fn source_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.source = Some(LocalFileSystem::new(dir));
+        self
+    }
+
+    /// Like `source_dir` in debug builds, release builds always serve the package.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let fs = EmbeddedFileSystem::from_bytes(include_bytes!("assets.package"))?
+    ///     .source_dir_in_debug(concat!(env!("CARGO_MANIFEST_DIR"), "/assets"));
+    /// ```
+    pub fn source_dir_in_debug<P: AsRef<Path>>(self, dir: P) -> Self {
+        if cfg!(debug_assertions) {
+            self.source_dir(dir)
+        } else {
+            self
+        }
+    }
+
+    fn read_package_dir(&self, path: &Path) -> Result<Vec<DirEntry>, ::Error> {
+        let dir = path.to_str().ok_or(::Error::NotFound)?;
+        let children = match self.package.dirs.get(dir) {
+            Some(children) => children,
+            None => return Err(::Error::NotFound),
+        };
+
+        let entries = children
+            .iter()
+            .map(|name| {
+                let child = if dir.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{}", dir, name)
+                };
+                match self.package.files.get(&child) {
+                    Some(file) => DirEntry {
+                        name: name.clone(),
+                        is_dir: false,
+                        size: file.len,
+                        modified: Some(file.last_modified.into()),
+                    },
+                    None => DirEntry {
+                        name: name.clone(),
+                        is_dir: true,
+                        size: 0,
+                        modified: self.package
+                            .dir_modified
+                            .get(&child)
+                            .map(|modified| (*modified).into()),
+                    },
+                }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    /// Returns the source directory if it has the file at `path`.
+    fn source_file(&self, path: &Path) -> Option<&LocalFileSystem> {
+        self.source.as_ref().filter(|source| source.is_file(path))
+    }
+
+    /// Verifies the checksum of every file again whenever it is opened, e.g. if the package
+    /// lives in memory which could change after loading. Off by default.
+    pub fn verify_reads(mut self, verify_reads: bool) -> Self {
+        self.verify_reads = verify_reads;
+        self
+    }
+
+    fn open_file(&self, path: &Path) -> Result<Cursor<Contents>, ::Error> {
+        if self.verify_reads {
+            let file = path.to_str()
+                .and_then(|path| self.package.files.get(path))
+                .ok_or(::Error::NotFound)?;
+            if !file.verify(self.package.stored(file)) {
+                return Err(::Error::backend(format!(
+                    "checksum mismatch of embedded file {}",
+                    path.display()
+                )));
+            }
+        }
+        self.package.contents(path)
     }
 }
 
 impl FileSystem for EmbeddedFileSystem {
-    fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
-        self.package
-            .files
-            .contains_key(path.as_ref().to_str().unwrap())
+    fn is_file(&self, path: &Path) -> bool {
+        path.to_str().map_or(false, |p| self.package.files.contains_key(p))
+            || self.source_file(path).is_some()
     }
 
-    fn last_modified<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime, Box<Error>> {
-        match self.package.files.get(path.as_ref().to_str().unwrap()) {
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, ::Error> {
+        if let Some(source) = self.source_file(path) {
+            return source.last_modified(path);
+        }
+        let path = path.to_str().ok_or(::Error::NotFound)?;
+        match self.package.files.get(path) {
             Some(file) => Ok(file.last_modified.into()),
-            None => Err(Box::new(::Error::new("file does not exist"))),
+            None => self.package
+                .dir_modified
+                .get(path)
+                .map(|modified| (*modified).into())
+                .ok_or(::Error::NotFound),
         }
     }
 
-    fn size<P: AsRef<Path>>(&self, path: P) -> Result<u64, Box<Error>> {
-        match self.package.files.get(path.as_ref().to_str().unwrap()) {
+    fn size(&self, path: &Path) -> Result<u64, ::Error> {
+        if let Some(source) = self.source_file(path) {
+            return source.size(path);
+        }
+        match path.to_str().and_then(|path| self.package.files.get(path)) {
             Some(file) => Ok(file.len),
-            None => Err(Box::new(::Error::new("file does not exist"))),
+            None => Err(::Error::NotFound),
         }
     }
 
-    fn open<P: AsRef<Path>>(&self, path: P, start: Option<u64>) -> Result<Box<Read>, Box<Error>> {
-        let mut reader = self.package.open(path)?;
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, ::Error> {
+        if let Some(source) = self.source_file(path) {
+            return source.open(path, start);
+        }
+        let mut reader = self.open_file(path)?;
         if let Some(start) = start {
             reader.seek(SeekFrom::Start(start))?;
         }
         Ok(Box::new(reader))
     }
 
-    fn path_valid<P: AsRef<Path>>(&self, path: P) -> bool {
-        self.package
-            .files
-            .contains_key(path.as_ref().to_str().unwrap())
+    fn path_valid(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, ::Error> {
+        if let Some(source) = self.source_file(path) {
+            return source.open_seek(path);
+        }
+        Ok(Box::new(self.open_file(path)?))
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        if let Some(source) = self.source_file(path) {
+            return source.etag(path);
+        }
+        self.package.files.get(path.to_str()?)?.etag()
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        if let Some(source) = self.source_file(path) {
+            return source.content_type(path);
+        }
+        self.package.files.get(path.to_str()?)?.mime.clone()
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        if let Some(source) = self.source_file(path) {
+            return source.open_encoded(path, encoding);
+        }
+        let file = self.package.files.get(path.to_str()?)?;
+        match (file.compression, encoding) {
+            (PackageCompression::Gzip, "gzip") => {
+                Some(Box::new(Cursor::new(self.package.decrypted(file).ok()?)))
+            }
+            _ => None,
+        }
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        if self.source_file(path).is_some() {
+            return None;
+        }
+        let file = self.package.files.get(path.to_str()?)?;
+        if self.verify_reads && !file.verify(self.package.stored(file)) {
+            return None;
+        }
+        self.package.static_file(file)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.to_str().map_or(false, |p| self.package.dirs.contains_key(p))
+            || self.source.as_ref().map_or(false, |source| source.is_dir(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, ::Error> {
+        let entries = self.read_package_dir(path);
+        let source_entries = match self.source {
+            Some(ref source) if source.is_dir(path) => source.read_dir(path)?,
+            _ => return entries,
+        };
+
+        // Files in the source directory replace the ones of the package
+        let mut entries = entries.unwrap_or_default();
+        for entry in source_entries {
+            entries.retain(|e| e.name != entry.name);
+            entries.push(entry);
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, ::Error> {
+        if let Some(source) = self.source_file(path) {
+            return source.metadata(path);
+        }
+        let path = path.to_str().ok_or(::Error::NotFound)?;
+        match self.package.files.get(path) {
+            Some(file) => Ok(Metadata {
+                size: file.len,
+                modified: Some(file.last_modified.into()),
+                is_file: true,
+                is_dir: false,
+            }),
+            None if self.package.dirs.contains_key(path) => Ok(Metadata {
+                size: 0,
+                modified: self.package
+                    .dir_modified
+                    .get(path)
+                    .map(|modified| (*modified).into()),
+                is_file: false,
+                is_dir: true,
+            }),
+            None => match self.source {
+                Some(ref source) => source.metadata(Path::new(path)),
+                None => Err(::Error::NotFound),
+            },
+        }
+    }
+}
+
+/// How the entries of a package are stored, see `write_package_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageCompression {
+    /// Stores the files as they are.
+    None,
+    /// Stores the files gzipped, they are sent to clients accepting gzip without unpacking.
+    Gzip,
+    /// Stores the files compressed with brotli, which is smaller than gzip for text. Requires
+    /// the `brotli` feature.
+    #[cfg(feature = "brotli")]
+    Brotli,
+}
+
+impl Default for PackageCompression {
+    fn default() -> Self {
+        PackageCompression::None
+    }
+}
+
+impl PackageCompression {
+    fn from_flag(flag: u8) -> Result<Self, Box<Error>> {
+        match flag {
+            0 => Ok(PackageCompression::None),
+            1 => Ok(PackageCompression::Gzip),
+            #[cfg(feature = "brotli")]
+            2 => Ok(PackageCompression::Brotli),
+            _ => Err(From::from(format!(
+                "package entry compressed with unsupported method {}",
+                flag
+            ))),
+        }
+    }
+
+    fn flag(&self) -> u8 {
+        match *self {
+            PackageCompression::None => 0,
+            PackageCompression::Gzip => 1,
+            #[cfg(feature = "brotli")]
+            PackageCompression::Brotli => 2,
+        }
+    }
+
+    fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match *self {
+            PackageCompression::None => Ok(data.to_vec()),
+            PackageCompression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), ::flate2::Compression::best());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "brotli")]
+            PackageCompression::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 11, 22);
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                Ok(encoder.into_inner())
+            }
+        }
+    }
+
+    fn decompress(&self, data: &[u8], len: u64) -> io::Result<Vec<u8>> {
+        // The length comes from the package, don't trust it with more than a guess and read at
+        // most one byte more than it, which is enough to tell that it's wrong
+        let mut decompressed = Vec::with_capacity(cmp::min(len, MAX_PREALLOC) as usize);
+        let limit = len.saturating_add(1);
+        match *self {
+            PackageCompression::None => decompressed.extend_from_slice(data),
+            PackageCompression::Gzip => {
+                GzDecoder::new(data).take(limit).read_to_end(&mut decompressed)?;
+            }
+            #[cfg(feature = "brotli")]
+            PackageCompression::Brotli => {
+                brotli::Decompressor::new(data, 4096)
+                    .take(limit)
+                    .read_to_end(&mut decompressed)?;
+            }
+        }
+
+        if decompressed.len() as u64 != len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "package entry decompressed to the wrong length",
+            ));
+        }
+        Ok(decompressed)
+    }
+}
+
+/// Options of writing a package, see `write_package_with`.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{PackageCompression, PackageOptions};
+///
+/// let options = PackageOptions::new()
+///     .compression(PackageCompression::Gzip)
+///     .mime_type("wasm", "application/wasm");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PackageOptions {
+    compression: PackageCompression,
+    /// How the metadata of the package is compressed.
+    meta_compression: PackageCompression,
+    /// The alignment of the entries in bytes, 0 and 1 leave them unaligned.
+    align: u64,
+    /// The number of threads preparing the files, 0 and 1 prepare them on the calling thread.
+    threads: usize,
+    /// Maps lowercase extensions to the MIME types stored for them.
+    mime_types: HashMap<String, String>,
+    #[cfg(feature = "encryption")]
+    key: Option<[u8; 32]>,
+    /// The latest modification date stored, in seconds since the epoch.
+    max_modified: Option<i64>,
+    /// The path of the manifest of fingerprinted files, if files are fingerprinted.
+    manifest: Option<String>,
+    /// Files keeping their names when fingerprinting.
+    keep_names: Vec<Glob>,
+}
+
+impl PackageOptions {
+    /// Stores the files as they are, with the MIME types guessed from their extensions.
+    pub fn new() -> Self {
+        PackageOptions::default()
+    }
+
+    /// Compresses the files with the given method.
+    pub fn compression(mut self, compression: PackageCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Compresses the metadata of the package, the paths and sizes of the files, with the given
+    /// method. Worth it for packages of many small files, whose metadata repeats the same
+    /// directories over and over. Loading the package decompresses it once.
+    pub fn compress_metadata(mut self, compression: PackageCompression) -> Self {
+        self.meta_compression = compression;
+        self
+    }
+
+    /// Aligns the entries of the package to multiples of `align` bytes from its start, e.g. to
+    /// 4096 so the files of a memory-mapped package start on pages. The gaps are filled with
+    /// zeros.
+    pub fn align(mut self, align: u64) -> Self {
+        self.align = align;
+        self
+    }
+
+    /// Reads, compresses and encrypts the files on the given number of threads, one by default.
+    /// The package is the same whatever the number, only `write_package_with` and what's built
+    /// on it make use of it.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Stores the given MIME type for files with the given extension instead of the guessed one.
+    pub fn mime_type(mut self, extension: &str, mime: &str) -> Self {
+        self.mime_types.insert(extension.to_lowercase(), mime.to_string());
+        self
+    }
+
+    /// Stores modification dates later than `max` as `max`, e.g. `UNIX_EPOCH` to store none at
+    /// all. Otherwise the dates of a fresh checkout make every package differ.
+    pub fn clamp_modified(mut self, max: SystemTime) -> Self {
+        self.max_modified = Some(DateTime::<Utc>::from(max).timestamp());
+        self
+    }
+
+    /// Makes packages reproducible: the same files produce the same package on every checkout.
+    ///
+    /// Clamps the modification dates to `SOURCE_DATE_EPOCH` if that environment variable holds
+    /// a timestamp, as set by reproducible build environments, or to zero otherwise. Files are
+    /// always written sorted by path.
+    pub fn reproducible(self) -> Self {
+        let epoch = env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|epoch| epoch.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        self.clamp_modified(UNIX_EPOCH + Duration::from_secs(epoch))
+    }
+
+    /// Renames the files to include a hash of their content, e.g. `css/app.css` to
+    /// `css/app.1a2b3c4d5e6f7a8b.css`, so they can be cached forever and a changed file gets a
+    /// new URL. A JSON manifest mapping the original paths to the new ones is written to the
+    /// package at `manifest`, e.g. `manifest.json`.
+    ///
+    /// HTML files and files matching a glob given to `keep_name` keep their names, they are
+    /// the entry points referring to the others. See `fingerprint_manifest` of the
+    /// StaticFileServer for serving the package.
+    pub fn fingerprint(mut self, manifest: &str) -> Self {
+        self.manifest = Some(manifest.trim_left_matches('/').to_string());
+        self
+    }
+
+    /// Keeps the names of files matching the glob when fingerprinting, e.g. `robots.txt`.
+    pub fn keep_name(mut self, glob: Glob) -> Self {
+        self.keep_names.push(glob);
+        self
+    }
+
+    /// Returns the fingerprinted path of a file, if files are fingerprinted and it doesn't
+    /// keep its name.
+    fn fingerprinted_path(&self, path: &str, content_hash: &[u8; 32]) -> Option<String> {
+        self.manifest.as_ref()?;
+        let keep_name = path.to_lowercase().ends_with(".html")
+            || self.keep_names.iter().any(|glob| glob.is_match(path));
+        if keep_name {
+            return None;
+        }
+
+        let hash = hex(&content_hash[..8]);
+        let name_start = path.rfind('/').map_or(0, |i| i + 1);
+        match path[name_start..].rfind('.') {
+            Some(dot) if dot > 0 => {
+                let dot = name_start + dot;
+                Some(format!("{}.{}{}", &path[..dot], hash, &path[dot..]))
+            }
+            _ => Some(format!("{}.{}", path, hash)),
+        }
+    }
+
+    /// Returns the modification date to store for a file, in seconds since the epoch.
+    fn modified_of(&self, modified: SystemTime) -> i64 {
+        let modified = DateTime::<Utc>::from(modified).timestamp();
+        match self.max_modified {
+            Some(max) => modified.min(max),
+            None => modified,
+        }
+    }
+
+    /// Encrypts the entries with AES-256-GCM, so the files can't be extracted from the binary
+    /// without the key. Paths, sizes and MIME types stay readable. Requires the `encryption`
+    /// feature.
+    ///
+    /// Nonces, ETags and fingerprints are derived from the contents with the key, so without it
+    /// they don't allow confirming a guess of the contents. They are deterministic though, files
+    /// with the same contents end up with the same entries in all packages using the same key.
+    ///
+    /// The package is loaded as usual, the key is needed to open the files, see
+    /// `EmbeddedFileSystem::decryption_key`.
+    #[cfg(feature = "encryption")]
+    pub fn encrypt(mut self, key: [u8; 32]) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    /// Encrypts the entry of a file if a key is set, returns whether it did.
+    #[cfg(feature = "encryption")]
+    fn encrypt_entry(&self, stored: Vec<u8>) -> (bool, Vec<u8>) {
+        match self.key {
+            Some(ref key) => {
+                // Deriving the nonce from the entry keeps packages reproducible and entries
+                // deduplicated, a nonce is only used twice for the same plaintext. It's keyed,
+                // so it doesn't reveal a hash of the plaintext.
+                let mut nonce = [0; 12];
+                nonce.copy_from_slice(&hmac_sha256(key, b"nonce", &stored)[..12]);
+                (true, encrypt_file(key, nonce, &stored))
+            }
+            None => (false, stored),
+        }
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn encrypt_entry(&self, stored: Vec<u8>) -> (bool, Vec<u8>) {
+        (false, stored)
+    }
+
+    /// Returns the hash stored for contents with the given SHA-256 hash, which is keyed if
+    /// entries are encrypted.
+    #[cfg(feature = "encryption")]
+    fn content_hash_of(&self, sha256: [u8; 32]) -> [u8; 32] {
+        match self.key {
+            Some(ref key) => hmac_sha256(key, b"content", &sha256),
+            None => sha256,
+        }
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn content_hash_of(&self, sha256: [u8; 32]) -> [u8; 32] {
+        sha256
+    }
+
+    /// Returns whether entries are encrypted.
+    #[cfg(feature = "encryption")]
+    fn encrypts(&self) -> bool {
+        self.key.is_some()
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn encrypts(&self) -> bool {
+        false
+    }
+
+    /// Returns the MIME type to store for the file, if it's known.
+    fn mime_type_of(&self, path: &str) -> Option<String> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|ext| ext.to_str())?
+            .to_lowercase();
+        match self.mime_types.get(&extension) {
+            Some(mime) => Some(mime.clone()),
+            None => get_mime_type_opt(&extension).map(|mime| mime.to_string()),
+        }
     }
 }
 
-struct Package {
+/// The memory a package is read from, e.g. a static slice or a `Vec<u8>`.
+type PackageBytes = AsRef<[u8]> + Send + Sync;
+
+/// A package written by `write_package`, for inspecting it without serving it.
+///
+/// The `EmbeddedFileSystem` serving a package gives access to it with `package()`.
+///
+/// # Example
+///
+/// ```no_run
+/// extern crate rocket_static_fs;
+///
+/// use rocket_static_fs::fs::Package;
+///
+/// fn main() {
+///     let package = Package::from_file(concat!(
+///         env!("CARGO_MANIFEST_DIR"),
+///         "/target/test.package"
+///     )).unwrap();
+///     for entry in package.entries() {
+///         println!("{} {} bytes at {}", entry.path, entry.size, entry.offset);
+///     }
+/// }
+/// ```
+pub struct Package {
     files: HashMap<String, InternalFile>,
-    data: &'static [u8],
+    /// Maps the path of every directory to the names of its children, the root is "".
+    dirs: HashMap<String, BTreeSet<String>>,
+    /// The modification dates of the directories, packages before version 4 have none.
+    dir_modified: HashMap<String, DateTime<Utc>>,
+    /// The packages the entries are read from, more than one if packages were merged.
+    layers: Vec<Layer>,
+}
+
+/// The memory of a loaded package.
+struct Layer {
+    bytes: Arc<PackageBytes>,
+    /// Where the entries start in the bytes, after the metadata.
+    data_start: usize,
+    /// Where the bytes start in the entries, only the parts of a package loaded with
+    /// `from_parts` start in between.
+    offset: u64,
+    /// The bytes again if they live as long as the program, e.g. embedded with
+    /// `include_bytes!`, so entries can be handed out without copying them.
+    static_bytes: Option<&'static [u8]>,
+    /// The key encrypted entries are decrypted with.
+    #[cfg(feature = "encryption")]
+    key: Option<[u8; 32]>,
+}
+
+impl Layer {
+    /// Returns where the entry of the file is in the bytes.
+    fn range(&self, file: &InternalFile) -> Range<usize> {
+        let start = self.data_start + (file.start - self.offset) as usize;
+        start..start + file.stored_len as usize
+    }
+}
+
+/// An entry of a package, keeping the package in memory while it's read.
+struct Entry {
+    bytes: Arc<PackageBytes>,
+    start: usize,
+    end: usize,
+}
+
+impl AsRef<[u8]> for Entry {
+    fn as_ref(&self) -> &[u8] {
+        &(*self.bytes).as_ref()[self.start..self.end]
+    }
+}
+
+/// The content of a file read from a package.
+enum Contents {
+    Stored(Entry),
+    /// Decompressed or decrypted.
+    Decoded(Vec<u8>),
+}
+
+impl AsRef<[u8]> for Contents {
+    fn as_ref(&self) -> &[u8] {
+        match *self {
+            Contents::Stored(ref entry) => entry.as_ref(),
+            Contents::Decoded(ref data) => &data[..],
+        }
+    }
 }
 
 struct InternalFile {
+    /// The index of the layer the entry is stored in.
+    layer: usize,
     last_modified: DateTime<Utc>,
+    /// The size of the file, after decompressing it.
     len: u64,
     start: u64,
+    compression: PackageCompression,
+    /// Whether the entry is encrypted, after compressing it.
+    encrypted: bool,
+    /// The size of the entry in the package.
+    stored_len: u64,
+    /// The SHA-256 hash of the entry in the package, packages of version 0 have none.
+    checksum: Option<[u8; 32]>,
+    /// The MIME type determined when writing the package, packages before version 2 have none.
+    mime: Option<String>,
+    /// The SHA-256 hash of the content the ETag is derived from, packages before version 3
+    /// have none.
+    content_hash: Option<[u8; 32]>,
 }
 
-impl Package {
-    pub fn from_bytes(bytes: &'static [u8]) -> Result<Self, Box<Error>> {
+impl InternalFile {
+    fn verify(&self, stored: &[u8]) -> bool {
+        self.checksum.map_or(true, |checksum| sha256(stored) == checksum)
+    }
+
+    fn etag(&self) -> Option<String> {
+        self.content_hash.as_ref().map(etag)
+    }
+}
+
+/// A file in a package, see `Package::entries`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageEntry {
+    /// The path relative to the root, e.g. `css/app.css`.
+    pub path: String,
+    /// The size of the file, after decompressing it.
+    pub size: u64,
+    pub modified: SystemTime,
+    /// Where the entry starts in the data section of the package, which follows the metadata.
+    /// Files with the same content share their entry.
+    pub offset: u64,
+    /// The size of the entry in the package.
+    pub stored_size: u64,
+    pub compression: PackageCompression,
+    pub mime: Option<String>,
+    pub etag: Option<String>,
+}
+
+/// Returns a strong ETag made of the first half of the SHA-256 hash of a file.
+fn etag(content_hash: &[u8; 32]) -> String {
+    format!("\"{}\"", hex(&content_hash[..16]))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    hex.concat()
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&Sha256::digest(data));
+    hash
+}
+
+/// Returns the HMAC-SHA256 of `data` for the given purpose, so keyed hashes of the same data
+/// differ between purposes.
+#[cfg(feature = "encryption")]
+fn hmac_sha256(key: &[u8; 32], purpose: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = Hmac::<Sha256>::new_varkey(key).expect("HMAC accepts keys of any length");
+    mac.input(purpose);
+    mac.input(&[0]);
+    mac.input(data);
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&mac.result().code());
+    hash
+}
+
+/// Starts the entries of files since version 4 of the format.
+const FILE_ENTRY: u8 = 0;
+/// Starts the entries of directories, which only hold the path and the modification date.
+const DIRECTORY_ENTRY: u8 = 1;
+
+/// Set in the compression flag of encrypted entries.
+const ENCRYPTED: u8 = 0x80;
+
+/// Starts packages since version 1 of the format, followed by the version.
+const MAGIC: &[u8; 8] = b"RSFSPKG\0";
+
+/// The version of the package format written by `write_package`.
+///
+/// Version 0 are the packages of rocket_static_fs 0.3 and earlier, which start with the
+/// length of the metadata right away and have neither compression nor checksums. Version 1
+/// has no MIME types, version 2 no content hashes, version 3 no directory entries, version 4
+/// no compression of the metadata, version 5 no alignment of the entries. Since version 5 the
+/// checksum of the metadata covers the header as well.
+const VERSION: u32 = 6;
+
+/// The most memory reserved up front for data whose length is read from a package.
+const MAX_PREALLOC: u64 = 16 * 1024 * 1024;
+
+/// How many times larger than its compressed form metadata can plausibly be. Deflate can't
+/// do better than about 1032:1, metadata with its hashes doesn't come close with any format.
+const MAX_META_RATIO: u64 = 1100;
+
+/// The header of a package, everything in front of the metadata.
+struct Header {
+    version: u32,
+    meta_compression: PackageCompression,
+    /// The length of the metadata after decompressing it, packages before version 5 have none.
+    raw_meta_len: Option<u64>,
+    /// The alignment of the entries, 0 and 1 if they aren't aligned.
+    align: u64,
+    /// The length of the metadata as it is stored.
+    meta_len: u64,
+    meta_checksum: Option<[u8; 32]>,
+    /// Where the metadata starts in the package.
+    meta_start: u64,
+}
+
+impl Header {
+    /// Reads the header from the start of a package, `bytes` may end anywhere after it.
+    fn read(bytes: &[u8]) -> Result<Self, Box<Error>> {
         let mut cursor = Cursor::new(bytes);
+        let version = if bytes.starts_with(MAGIC) {
+            cursor.set_position(MAGIC.len() as u64);
+            cursor.read_u32::<BigEndian>()?
+        } else {
+            0
+        };
+        if version > VERSION {
+            return Err(From::from(format!(
+                "package has format version {}, but only versions up to {} are supported",
+                version, VERSION
+            )));
+        }
+
+        let (meta_compression, raw_meta_len) = if version >= 5 {
+            let compression = PackageCompression::from_flag(cursor.read_u8()?)?;
+            (compression, Some(cursor.read_u64::<BigEndian>()?))
+        } else {
+            (PackageCompression::None, None)
+        };
+        let align = if version >= 6 { cursor.read_u64::<BigEndian>()? } else { 0 };
         let meta_len = cursor.read_u64::<BigEndian>()?;
+        let meta_checksum = if version >= 1 {
+            let mut checksum = [0; 32];
+            cursor.read_exact(&mut checksum)?;
+            Some(checksum)
+        } else {
+            None
+        };
+
+        if version == 0 && meta_len == 0 {
+            return Err(From::from("package has no metadata, maybe it's no package at all"));
+        }
+
+        Ok(Header {
+            version,
+            meta_compression,
+            raw_meta_len,
+            align,
+            meta_len,
+            meta_checksum,
+            meta_start: cursor.position(),
+        })
+    }
+
+    /// Returns where the entries start in a package of `len` bytes, after the metadata.
+    fn data_start(&self, len: u64) -> Result<u64, Box<Error>> {
+        Ok(self.meta_start
+            .checked_add(self.meta_len)
+            .and_then(|end| align_up(end, self.align))
+            .filter(|&end| end <= len)
+            .ok_or("package metadata is truncated, maybe it's no package at all")?)
+    }
+
+    /// Reads the files and the modification dates of the directories from the metadata,
+    /// `bytes` starts with the header and may end anywhere after the metadata.
+    fn read_meta(
+        &self,
+        bytes: &[u8],
+    ) -> Result<(HashMap<String, InternalFile>, HashMap<String, DateTime<Utc>>), Box<Error>> {
+        let version = self.version;
+        let meta_start = self.meta_start as usize;
+        let stored_meta = &bytes[meta_start..meta_start + self.meta_len as usize];
+        if let Some(checksum) = self.meta_checksum {
+            // Since version 5 the checksum covers the header up to the checksum as well
+            let header: &[u8] = if version >= 5 {
+                &bytes[..meta_start - checksum.len()]
+            } else {
+                &[]
+            };
+            if meta_checksum_of(header, stored_meta) != checksum {
+                return Err(From::from("package metadata checksum mismatch"));
+            }
+        }
+        let meta = match self.raw_meta_len {
+            Some(len) if self.meta_compression != PackageCompression::None => {
+                if len > self.meta_len.saturating_mul(MAX_META_RATIO) {
+                    return Err(From::from(format!(
+                        "package metadata can't be decompressed to {} bytes",
+                        len
+                    )));
+                }
+                Cow::Owned(
+                    self.meta_compression
+                        .decompress(stored_meta, len)
+                        .map_err(|err| format!("package metadata can't be decompressed: {}", err))?,
+                )
+            }
+            _ => Cow::Borrowed(stored_meta),
+        };
+        let meta_len = meta.len() as u64;
+        let mut cursor = Cursor::new(&meta[..]);
 
         let mut files = HashMap::new();
+        let mut dir_modified = HashMap::new();
         let mut read = 0;
 
         while read < meta_len {
             let cursor_start = cursor.position();
+            let kind = if version >= 4 { cursor.read_u8()? } else { FILE_ENTRY };
+            if kind != FILE_ENTRY && kind != DIRECTORY_ENTRY {
+                return Err(From::from(format!("package entry of unknown kind {}", kind)));
+            }
             let path_len = cursor.read_u64::<BigEndian>()? as u64;
             let mut path = String::new();
             let cursor_clone = cursor.clone();
             let mut path_reader = cursor_clone.take(path_len);
             path_reader.read_to_string(&mut path)?;
+            if path.len() as u64 != path_len {
+                return Err(From::from("package metadata is truncated"));
+            }
             cursor.seek(SeekFrom::Current(path_len as i64))?;
 
             let last_modified_seconds = cursor.read_i64::<BigEndian>()?;
-            let last_modified: DateTime<Utc> = Utc.timestamp(last_modified_seconds, 0);
+            let last_modified: DateTime<Utc> = Utc
+                .timestamp_opt(last_modified_seconds, 0)
+                .single()
+                .ok_or_else(|| format!("package entry {} has an invalid modification date", path))?;
+            if kind == DIRECTORY_ENTRY {
+                dir_modified.insert(path, last_modified);
+                read += cursor.position() - cursor_start;
+                continue;
+            }
 
             let len = cursor.read_u64::<BigEndian>()?;
             let start = cursor.read_u64::<BigEndian>()?;
+            let (compression, encrypted, stored_len, checksum) = if version >= 1 {
+                let flag = cursor.read_u8()?;
+                let compression = PackageCompression::from_flag(flag & !ENCRYPTED)?;
+                let stored_len = cursor.read_u64::<BigEndian>()?;
+                let mut checksum = [0; 32];
+                cursor.read_exact(&mut checksum)?;
+                (compression, flag & ENCRYPTED != 0, stored_len, Some(checksum))
+            } else {
+                (PackageCompression::None, false, len, None)
+            };
+            let mime = if version >= 2 {
+                let mime_len = cursor.read_u64::<BigEndian>()?;
+                let mut mime = String::new();
+                (&mut cursor).take(mime_len).read_to_string(&mut mime)?;
+                Some(mime).filter(|mime| !mime.is_empty())
+            } else {
+                None
+            };
+            let content_hash = if version >= 3 {
+                let mut content_hash = [0; 32];
+                cursor.read_exact(&mut content_hash)?;
+                Some(content_hash)
+            } else {
+                None
+            };
 
             let cursor_end = cursor.position();
 
@@ -137,107 +1132,1122 @@ impl Package {
             files.insert(
                 path,
                 InternalFile {
+                    layer: 0,
                     last_modified,
                     len,
                     start,
+                    compression,
+                    encrypted,
+                    stored_len,
+                    checksum,
+                    mime,
+                    content_hash,
                 },
             );
         }
 
-        let data = &bytes[(meta_len + 8) as usize..];
-        Ok(Package { files, data })
+        if read != meta_len {
+            return Err(From::from("package metadata ends within an entry"));
+        }
+        Ok((files, dir_modified))
     }
+}
 
-    fn open<P>(&self, path: P) -> Result<Cursor<&'static [u8]>, Box<Error>>
-    where
-        P: AsRef<Path>,
-    {
-        match self.files.get(path.as_ref().to_str().unwrap()) {
-            Some(file) => {
-                let start = file.start as usize;
-                let end = (file.start + file.len) as usize;
-                let slice = &self.data[start..end];
-                Ok(Cursor::new(slice))
-            }
-            None => Err(Box::new(::Error::new("file does not exist"))),
+/// Checks that the entries of the files fit into the `data_len` bytes following the metadata.
+fn check_entries(
+    version: u32,
+    files: &HashMap<String, InternalFile>,
+    data_len: u64,
+) -> Result<(), Box<Error>> {
+    // Packages without a version are only recognized by their structure, so their data has
+    // to end with the last entry
+    let data_end = files.values().map(|file| file.start.saturating_add(file.stored_len)).max();
+    if version == 0 && data_end != Some(data_len) {
+        return Err(From::from("package data doesn't match its metadata"));
+    }
+    for (path, file) in files {
+        let end = file.start.checked_add(file.stored_len);
+        if end.map_or(true, |end| end > data_len) {
+            return Err(From::from(format!("package entry {} is truncated", path)));
         }
     }
+    Ok(())
 }
 
-pub fn write_package<W, T, P>(root: P, input_files: &[T], writer: &mut W) -> Result<(), Box<Error>>
-where
-    P: AsRef<Path>,
-    W: Write + WriteBytesExt,
-    T: AsRef<str> + Clone + Ord,
-{
-    let mut files = Vec::from(input_files);
-    files.sort();
-
-    let mut file_sizes = Vec::new();
-    let mut file_modification_times = Vec::new();
-    let mut meta_len = 0;
-    for f in &files {
-        // 8 * 4 = 32 cause of last_modified + path_len + start + len which are all 64bit
-        meta_len += 32;
-        meta_len += f.as_ref().as_bytes().len();
-
-        let meta = root.as_ref().join(f.as_ref()).metadata()?;
-        let file_size = meta.len();
-        file_sizes.push(file_size);
-
-        let mod_time = meta.modified()?;
-        file_modification_times.push(mod_time);
+/// Returns the bytes from `start` to `end` of a package split into parts, copying them only
+/// if they span parts. The range is cut off at the end of the package.
+fn read_parts(parts: &[&'static [u8]], start: u64, end: u64) -> Cow<'static, [u8]> {
+    let mut copy = Vec::new();
+    let mut part_start = 0;
+    for &part in parts {
+        let part_end = part_start + part.len() as u64;
+        if start < part_end && part_start < end {
+            let from = start.saturating_sub(part_start) as usize;
+            let to = (cmp::min(end, part_end) - part_start) as usize;
+            if copy.is_empty() && part_start <= start && end <= part_end {
+                return Cow::Borrowed(&part[from..to]);
+            }
+            copy.extend_from_slice(&part[from..to]);
+        }
+        part_start = part_end;
     }
+    Cow::Owned(copy)
+}
 
-    let mut data_offset = 0;
-    writer.write_u64::<BigEndian>(meta_len as u64)?;
+impl Package {
+    /// Loads a package, failing if its checksums don't match its content.
+    pub fn from_bytes(bytes: &'static [u8]) -> Result<Self, Box<Error>> {
+        let mut package = Package::from_data(Arc::new(bytes), true)?;
+        package.layers[0].static_bytes = Some(bytes);
+        Ok(package)
+    }
+
+    /// Loads a package held in memory.
+    pub fn from_vec(bytes: Vec<u8>) -> Result<Self, Box<Error>> {
+        Package::from_data(Arc::new(bytes), true)
+    }
+
+    /// Reads the package file at `path` into memory and loads it.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<Error>> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        Package::from_vec(bytes)
+    }
+
+    /// Sets the key to decrypt the entries of a package written with `PackageOptions::encrypt`.
+    /// Requires the `encryption` feature.
+    ///
+    /// The key applies to the package as it is, set it before merging packages encrypted with
+    /// different keys.
+    #[cfg(feature = "encryption")]
+    pub fn decryption_key(mut self, key: [u8; 32]) -> Self {
+        for layer in &mut self.layers {
+            layer.key = Some(key);
+        }
+        self
+    }
+
+    /// Like `decryption_key`, reading the key from the environment variable `var` as 64 hex
+    /// digits, so it isn't baked into the binary next to the package.
+    #[cfg(feature = "encryption")]
+    pub fn decryption_key_from_env(self, var: &str) -> Result<Self, Box<Error>> {
+        Ok(self.decryption_key(key_from_env(var)?))
+    }
+
+    /// Loads a package, verifying the checksums of the entries if `verify_entries` is set. The
+    /// metadata is verified in any case.
+    fn from_data(backing: Arc<PackageBytes>, verify_entries: bool) -> Result<Self, Box<Error>> {
+        let bytes: &[u8] = (*backing).as_ref();
+        let header = Header::read(bytes)?;
+        let data_start = header.data_start(bytes.len() as u64)?;
+        let (files, dir_modified) = header.read_meta(bytes)?;
+        check_entries(header.version, &files, bytes.len() as u64 - data_start)?;
+
+        let layer = Layer {
+            bytes: backing,
+            data_start: data_start as usize,
+            offset: 0,
+            static_bytes: None,
+            #[cfg(feature = "encryption")]
+            key: None,
+        };
+        let package = Package::new(files, dir_modified, vec![layer]);
+        if verify_entries {
+            package.verify()?;
+        }
+        Ok(package)
+    }
+
+    /// Loads a package split into parts, e.g. by `Packager::split`, failing if its checksums
+    /// don't match its content.
+    ///
+    /// Entries are served straight from the parts like with `from_bytes`, only the few entries
+    /// spanning two parts are copied when loading, as is the metadata if it does.
+    pub fn from_parts(parts: &[&'static [u8]]) -> Result<Self, Box<Error>> {
+        let len: u64 = parts.iter().map(|part| part.len() as u64).sum();
+        let header = Header::read(&read_parts(parts, 0, cmp::min(HEADER_LEN, len)))?;
+        let data_start = header.data_start(len)?;
+        let (mut files, dir_modified) =
+            header.read_meta(&read_parts(parts, 0, header.meta_start + header.meta_len))?;
+        check_entries(header.version, &files, len - data_start)?;
+
+        // Where the parts start in the package
+        let mut part_starts = Vec::with_capacity(parts.len() + 1);
+        let mut part_start = 0;
+        for part in parts {
+            part_starts.push(part_start);
+            part_start += part.len() as u64;
+        }
+        part_starts.push(part_start);
+
+        let mut layers: Vec<Layer> = parts
+            .iter()
+            .zip(&part_starts)
+            .map(|(&part, &part_start)| Layer {
+                bytes: Arc::new(part),
+                data_start: data_start.saturating_sub(part_start) as usize,
+                offset: part_start.saturating_sub(data_start),
+                static_bytes: Some(part),
+                #[cfg(feature = "encryption")]
+                key: None,
+            })
+            .collect();
+        // The layers of copied entries by their offset, files with the same content share it
+        let mut copies = HashMap::new();
+        for file in files.values_mut() {
+            let offset = file.start;
+            let start = data_start + offset;
+            let end = start + file.stored_len;
+            let part = part_starts
+                .windows(2)
+                .position(|bounds| bounds[0] <= start && end <= bounds[1]);
+            file.layer = match part {
+                Some(part) => part,
+                None => *copies.entry(offset).or_insert_with(|| {
+                    layers.push(Layer {
+                        bytes: Arc::new(read_parts(parts, start, end).into_owned()),
+                        data_start: 0,
+                        offset,
+                        static_bytes: None,
+                        #[cfg(feature = "encryption")]
+                        key: None,
+                    });
+                    layers.len() - 1
+                }),
+            };
+        }
+
+        let package = Package::new(files, dir_modified, layers);
+        package.verify()?;
+        Ok(package)
+    }
+
+    fn new(
+        files: HashMap<String, InternalFile>,
+        dir_modified: HashMap<String, DateTime<Utc>>,
+        layers: Vec<Layer>,
+    ) -> Self {
+        let mut dirs = collect_dirs(files.keys().chain(dir_modified.keys()));
+        // Directories without files, which `collect_dirs` only knows as children
+        for dir in dir_modified.keys() {
+            dirs.entry(dir.clone()).or_insert_with(BTreeSet::new);
+        }
+        Package {
+            files,
+            dirs,
+            dir_modified,
+            layers,
+        }
+    }
+
+    /// Returns the entry of the file as it is stored in the package.
+    fn stored(&self, file: &InternalFile) -> &[u8] {
+        let layer = &self.layers[file.layer];
+        &(*layer.bytes).as_ref()[layer.range(file)]
+    }
+
+    /// Returns the file if it is stored as it is in a package living as long as the program.
+    fn static_file(&self, file: &InternalFile) -> Option<&'static [u8]> {
+        if file.encrypted || file.compression != PackageCompression::None {
+            return None;
+        }
+        let layer = &self.layers[file.layer];
+        layer.static_bytes.map(|bytes| &bytes[layer.range(file)])
+    }
+
+    /// Like `stored`, but the entry keeps the package in memory.
+    fn entry(&self, file: &InternalFile) -> Entry {
+        let layer = &self.layers[file.layer];
+        let range = layer.range(file);
+        Entry {
+            bytes: layer.bytes.clone(),
+            start: range.start,
+            end: range.end,
+        }
+    }
+
+    /// Returns the files of the package, sorted by path.
+    pub fn entries(&self) -> Vec<PackageEntry> {
+        let mut entries: Vec<PackageEntry> = self.files
+            .iter()
+            .map(|(path, file)| PackageEntry {
+                path: path.clone(),
+                size: file.len,
+                modified: file.last_modified.into(),
+                offset: file.start,
+                stored_size: file.stored_len,
+                compression: file.compression,
+                mime: file.mime.clone(),
+                etag: file.etag(),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries
+    }
+
+    /// Opens the file at `path`, decompressing it if needed.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<Box<ReadSeek>, ::Error> {
+        Ok(Box::new(self.contents(path)?))
+    }
+
+    /// Reads the whole file at `path`, decompressing it if needed.
+    pub fn read<P: AsRef<Path>>(&self, path: P) -> Result<Vec<u8>, ::Error> {
+        match self.contents(path)?.into_inner() {
+            Contents::Decoded(data) => Ok(data),
+            stored => Ok(stored.as_ref().to_vec()),
+        }
+    }
+
+    /// Returns the entry of the file at `path` as it is stored in the package, e.g. gzipped.
+    pub fn read_stored<P: AsRef<Path>>(&self, path: P) -> Result<&[u8], ::Error> {
+        let path = path.as_ref().to_str().ok_or(::Error::NotFound)?;
+        let file = self.files.get(path).ok_or(::Error::NotFound)?;
+        Ok(self.stored(file))
+    }
+
+    /// Checks the checksums of all entries, which packages loaded with
+    /// `EmbeddedFileSystem::open_mmap` skip.
+    pub fn verify(&self) -> Result<(), Box<Error>> {
+        let mut paths: Vec<&String> = self.files.keys().collect();
+        paths.sort();
+        for path in paths {
+            let file = &self.files[path];
+            if !file.verify(self.stored(file)) {
+                return Err(From::from(format!("package entry {} checksum mismatch", path)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges packages into one, files of later packages replace the ones with the same path
+    /// in earlier packages. The entries stay where they are, nothing is copied.
+    ///
+    /// Useful to layer packages, e.g. a base package and one overriding some files per brand.
+    pub fn merge<I>(packages: I) -> Package
+    where
+        I: IntoIterator<Item = Package>,
+    {
+        let mut files = HashMap::new();
+        let mut dir_modified = HashMap::new();
+        let mut layers = Vec::new();
+        for package in packages {
+            let first_layer = layers.len();
+            layers.extend(package.layers);
+            for (path, mut file) in package.files {
+                file.layer += first_layer;
+                files.insert(path, file);
+            }
+            dir_modified.extend(package.dir_modified);
+        }
+        Package::new(files, dir_modified, layers)
+    }
 
-    for (i, f) in files.iter().enumerate() {
-        // written in the following order: path_len, path, last_modified, len, start
-        writer.write_u64::<BigEndian>(f.as_ref().as_bytes().len() as u64)?;
-        write!(writer, "{}", f.as_ref())?;
+    /// Writes the package as a single package again, e.g. the result of `merge` at build
+    /// time. The entries are copied as they are stored, without compressing them again.
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Box<Error>> {
+        let mut paths: Vec<&String> = self.files.keys().collect();
+        paths.sort();
 
-        let last_modified: DateTime<Utc> = DateTime::from(file_modification_times[i]);
-        writer.write_i64::<BigEndian>(last_modified.timestamp())?;
+        let mut files = Vec::new();
+        for path in paths {
+            let file = &self.files[path];
+            let content_hash = match file.content_hash {
+                Some(content_hash) => content_hash,
+                None => sha256(&self.read(path)?),
+            };
+            files.push(PackageFile {
+                path: path.clone(),
+                last_modified: file.last_modified.timestamp(),
+                len: file.len,
+                compression: file.compression,
+                encrypted: file.encrypted,
+                stored: Cow::Borrowed(self.stored(file)),
+                mime: file.mime.clone(),
+                content_hash,
+            });
+        }
 
-        let file_size = &file_sizes[i];
-        writer.write_u64::<BigEndian>(*file_size)?;
+        let mut dirs: Vec<(String, i64)> = self.dir_modified
+            .iter()
+            .map(|(path, modified)| (path.clone(), modified.timestamp()))
+            .collect();
+        dirs.sort();
+        write_files(&files, &dirs, &PackageOptions::new(), writer)
+    }
 
-        writer.write_u64::<BigEndian>(data_offset as u64)?;
+    fn contents<P>(&self, path: P) -> Result<Cursor<Contents>, ::Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref().to_str().ok_or(::Error::NotFound)?;
+        let file = self.files.get(path).ok_or(::Error::NotFound)?;
+        let stored = self.decrypted(file)?;
+        match file.compression {
+            PackageCompression::None => Ok(Cursor::new(stored)),
+            compression => {
+                let data = compression.decompress(stored.as_ref(), file.len)?;
+                Ok(Cursor::new(Contents::Decoded(data)))
+            }
+        }
+    }
+
+    /// Returns the entry of the file, decrypted if it's encrypted but still compressed.
+    fn decrypted(&self, file: &InternalFile) -> Result<Contents, ::Error> {
+        if !file.encrypted {
+            return Ok(Contents::Stored(self.entry(file)));
+        }
+        self.decrypt(&self.layers[file.layer], self.stored(file))
+            .map(Contents::Decoded)
+    }
+
+    #[cfg(feature = "encryption")]
+    fn decrypt(&self, layer: &Layer, stored: &[u8]) -> Result<Vec<u8>, ::Error> {
+        match layer.key {
+            Some(ref key) => decrypt_file(key, stored),
+            None => Err(::Error::backend("package is encrypted, but no key was given")),
+        }
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn decrypt(&self, _layer: &Layer, _stored: &[u8]) -> Result<Vec<u8>, ::Error> {
+        Err(::Error::backend("package is encrypted, which requires the encryption feature"))
+    }
+}
+
+/// Derives the directory structure from the paths of the files.
+pub fn collect_dirs<'a, I>(paths: I) -> HashMap<String, BTreeSet<String>>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let mut dirs: HashMap<String, BTreeSet<String>> = HashMap::new();
+    dirs.insert(String::new(), BTreeSet::new());
 
-        data_offset += (*file_size) as usize;
+    for path in paths {
+        let mut parent = String::new();
+        for component in path.split('/') {
+            dirs.entry(parent.clone())
+                .or_insert_with(BTreeSet::new)
+                .insert(component.to_string());
+            if !parent.is_empty() {
+                parent.push('/');
+            }
+            parent.push_str(component);
+        }
     }
+    dirs
+}
 
+/// Writes the given files below `root` to a package, storing them as they are.
+pub fn write_package<W, T, P>(root: P, input_files: &[T], writer: &mut W) -> Result<(), Box<Error>>
+where
+    P: AsRef<Path>,
+    W: Write + WriteBytesExt,
+    T: AsRef<str> + Clone + Ord,
+{
+    write_package_with(root, input_files, &PackageOptions::new(), writer)
+}
+
+/// Writes the given files below `root` to a package as configured by `options`.
+///
+/// Compressed files which don't get smaller, e.g. images, are stored as they are. Files with
+/// the same content are stored once. The directories of the files are recorded with their
+/// modification dates, paths of directories add them even if they're empty. The package holds
+/// SHA-256 checksums of its metadata and of every entry, which are verified when it's loaded.
+pub fn write_package_with<W, T, P>(
+    root: P,
+    input_files: &[T],
+    options: &PackageOptions,
+    writer: &mut W,
+) -> Result<(), Box<Error>>
+where
+    P: AsRef<Path>,
+    W: Write + WriteBytesExt,
+    T: AsRef<str> + Clone + Ord,
+{
+    let mut files = Vec::from(input_files);
+    files.sort();
+
+    let mut package_files = Vec::new();
+    // The directories of the files and the ones given, e.g. empty ones
+    let mut dirs = BTreeSet::new();
+    // Maps the original paths of fingerprinted files to their new ones
+    let mut fingerprints = BTreeMap::new();
+    let mut latest_modified = 0;
+    let mut paths = Vec::new();
     for f in &files {
-        let mut file = File::open(root.as_ref().join(f.as_ref()))?;
-        io::copy(&mut file, writer)?;
+        if root.as_ref().join(f.as_ref()).metadata()?.is_dir() {
+            dirs.insert(f.as_ref().trim_right_matches('/').to_string());
+            continue;
+        }
+        for (i, _) in f.as_ref().match_indices('/') {
+            dirs.insert(f.as_ref()[..i].to_string());
+        }
+        paths.push(f.as_ref().to_string());
     }
 
+    for mut file in read_package_files(root.as_ref(), &paths, options)? {
+        latest_modified = latest_modified.max(file.last_modified);
+        if let Some(fingerprinted) = options.fingerprinted_path(&file.path, &file.content_hash) {
+            fingerprints.insert(file.path.clone(), fingerprinted.clone());
+            file.path = fingerprinted;
+        }
+        package_files.push(file);
+    }
+
+    if let Some(ref manifest) = options.manifest {
+        let json = serde_json::to_vec_pretty(&fingerprints)?;
+        package_files.push(package_file(manifest, json, latest_modified, options)?);
+        package_files.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    let dir_entries = dir_entries(root.as_ref(), dirs, options)?;
+    write_files(&package_files, &dir_entries, options, writer)
+}
+
+/// Like `write_package_with`, holding at most one file in memory at a time, e.g. to package
+/// large trees of media files with little memory. The package is the same.
+///
+/// Every file is read twice, once to hash it and once to write it. Files stored as they are,
+/// neither compressed nor encrypted, are copied without holding them in memory at all. The
+/// metadata is written after the entries into the space left for it, so the writer has to be
+/// seekable, e.g. a `File`, and it can't be compressed.
+pub fn write_package_streaming<W, T, P>(
+    root: P,
+    input_files: &[T],
+    options: &PackageOptions,
+    writer: &mut W,
+) -> Result<(), Box<Error>>
+where
+    P: AsRef<Path>,
+    W: Write + Seek,
+    T: AsRef<str> + Clone + Ord,
+{
+    if options.meta_compression != PackageCompression::None {
+        return Err(From::from("compressed metadata can't be streamed, use write_package_with"));
+    }
+    let mut files = Vec::from(input_files);
+    files.sort();
+
+    // The first pass hashes the files, which determines their fingerprints and duplicates
+    let mut package_files = Vec::new();
+    // Maps the paths in the package to the files they're read from
+    let mut sources = HashMap::new();
+    let mut dirs = BTreeSet::new();
+    let mut fingerprints = BTreeMap::new();
+    let mut latest_modified = 0;
+    for f in &files {
+        let path = root.as_ref().join(f.as_ref());
+        let meta = path.metadata()?;
+        if meta.is_dir() {
+            dirs.insert(f.as_ref().trim_right_matches('/').to_string());
+            continue;
+        }
+        for (i, _) in f.as_ref().match_indices('/') {
+            dirs.insert(f.as_ref()[..i].to_string());
+        }
+
+        let (len, content_hash) = copy_hashed(&mut File::open(&path)?, &mut io::sink())?;
+        let content_hash = options.content_hash_of(content_hash);
+        let last_modified = options.modified_of(meta.modified()?);
+        latest_modified = latest_modified.max(last_modified);
+        let mut file = PackageFile {
+            path: f.as_ref().to_string(),
+            last_modified,
+            len,
+            compression: PackageCompression::None,
+            encrypted: false,
+            stored: Cow::Borrowed(&[]),
+            mime: options.mime_type_of(f.as_ref()),
+            content_hash,
+        };
+        if let Some(fingerprinted) = options.fingerprinted_path(f.as_ref(), &content_hash) {
+            fingerprints.insert(f.as_ref().to_string(), fingerprinted.clone());
+            file.path = fingerprinted;
+        }
+        sources.insert(file.path.clone(), path);
+        package_files.push(file);
+    }
+
+    if let Some(ref manifest) = options.manifest {
+        let json = serde_json::to_vec_pretty(&fingerprints)?;
+        sources.remove(manifest);
+        package_files.push(package_file(manifest, json, latest_modified, options)?);
+        package_files.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+    let dir_entries = dir_entries(root.as_ref(), dirs, options)?;
+
+    // The length of the metadata doesn't depend on how the entries turn out
+    let placeholders: Vec<StoredEntry> = package_files
+        .iter()
+        .map(|_| StoredEntry { start: 0, len: 0, flag: 0, checksum: [0; 32] })
+        .collect();
+    let meta_len = package_metadata(&package_files, &placeholders, &dir_entries)?.len();
+    let header_start = writer.seek(SeekFrom::Current(0))?;
+    write_header(writer, &vec![0; meta_len], PackageCompression::None, options.align)?;
+
+    // Files with the same content are stored the same way, so they share one entry
+    let mut written: HashMap<[u8; 32], StoredEntry> = HashMap::new();
+    let mut entries = Vec::new();
+    let mut data_offset = 0;
+    for f in &package_files {
+        if let Some(&entry) = written.get(&f.content_hash) {
+            entries.push(entry);
+            continue;
+        }
+
+        let start = aligned(data_offset, options.align)?;
+        pad(writer, start - data_offset)?;
+        let entry = match sources.get(&f.path) {
+            Some(source) => stream_entry(source, f, options, start, writer)?,
+            None => {
+                writer.write_all(&f.stored)?;
+                StoredEntry::of(f, start)
+            }
+        };
+        data_offset = start + entry.len;
+        written.insert(f.content_hash, entry);
+        entries.push(entry);
+    }
+
+    let end = writer.seek(SeekFrom::Current(0))?;
+    writer.seek(SeekFrom::Start(header_start))?;
+    let meta = package_metadata(&package_files, &entries, &dir_entries)?;
+    write_header(writer, &meta, PackageCompression::None, options.align)?;
+    writer.seek(SeekFrom::Start(end))?;
     Ok(())
 }
 
+/// Writes the entry of a file read from `source` to `writer` as configured by `options`, see
+/// `write_package_streaming`.
+fn stream_entry<W: Write>(
+    source: &Path,
+    file: &PackageFile,
+    options: &PackageOptions,
+    start: u64,
+    writer: &mut W,
+) -> Result<StoredEntry, Box<Error>> {
+    let changed = || format!("{} changed while it was packaged", source.display());
+    if options.compression == PackageCompression::None && !options.encrypts() {
+        let (len, checksum) = copy_hashed(&mut File::open(source)?, writer)?;
+        if checksum != file.content_hash {
+            return Err(From::from(changed()));
+        }
+        return Ok(StoredEntry {
+            start,
+            len,
+            flag: PackageCompression::None.flag(),
+            checksum,
+        });
+    }
+
+    let mut data = Vec::new();
+    File::open(source)?.read_to_end(&mut data)?;
+    let packaged = package_file(&file.path, data, file.last_modified, options)?;
+    if packaged.content_hash != file.content_hash {
+        return Err(From::from(changed()));
+    }
+    writer.write_all(&packaged.stored)?;
+    Ok(StoredEntry::of(&packaged, start))
+}
+
+/// Copies `reader` to `writer`, returns the number of bytes copied and their SHA-256 hash.
+fn copy_hashed<R: Read, W: Write>(reader: &mut R, writer: &mut W) -> io::Result<(u64, [u8; 32])> {
+    let mut hasher = Sha256::new();
+    let mut buf = [0; 64 * 1024];
+    let mut len = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        hasher.input(&buf[..n]);
+        writer.write_all(&buf[..n])?;
+        len += n as u64;
+    }
+
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&hasher.result());
+    Ok((len, hash))
+}
+
+/// Returns the directories below `root` with the modification dates to store for them.
+fn dir_entries(
+    root: &Path,
+    dirs: BTreeSet<String>,
+    options: &PackageOptions,
+) -> Result<Vec<(String, i64)>, Box<Error>> {
+    let mut entries = Vec::new();
+    for dir in dirs {
+        let modified = root.join(&dir).metadata()?.modified()?;
+        entries.push((dir, options.modified_of(modified)));
+    }
+    Ok(entries)
+}
+
+/// Reads the files at `paths` below `root` to be written to a package, on as many threads as
+/// `options` asks for. The files are returned in the order of the paths.
+fn read_package_files(
+    root: &Path,
+    paths: &[String],
+    options: &PackageOptions,
+) -> Result<Vec<PackageFile<'static>>, Box<Error>> {
+    let threads = options.threads.min(paths.len());
+    if threads <= 1 {
+        return paths
+            .iter()
+            .map(|path| read_package_file(root, path, options))
+            .collect();
+    }
+
+    // The workers take the next path until none are left, errors are passed as strings
+    // because boxed errors can't be sent between threads
+    let shared = Arc::new((root.to_path_buf(), paths.to_vec(), options.clone()));
+    let next = Arc::new(AtomicUsize::new(0));
+    let results = Arc::new(Mutex::new(Vec::with_capacity(paths.len())));
+    let workers: Vec<_> = (0..threads)
+        .map(|_| {
+            let (shared, next, results) = (shared.clone(), next.clone(), results.clone());
+            thread::spawn(move || {
+                let (ref root, ref paths, ref options) = *shared;
+                loop {
+                    let i = next.fetch_add(1, Ordering::SeqCst);
+                    if i >= paths.len() {
+                        break;
+                    }
+                    let file = read_package_file(root, &paths[i], options)
+                        .map_err(|err| format!("{}: {}", paths[i], err));
+                    results.lock().unwrap().push((i, file));
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().map_err(|_| "a thread reading files panicked")?;
+    }
+
+    let mut results = mem::replace(&mut *results.lock().unwrap(), Vec::new());
+    results.sort_by_key(|&(i, _)| i);
+    results
+        .into_iter()
+        .map(|(_, file)| file.map_err(From::from))
+        .collect()
+}
+
+/// Reads the file at `path` below `root` and prepares it with `package_file`.
+fn read_package_file(
+    root: &Path,
+    path: &str,
+    options: &PackageOptions,
+) -> Result<PackageFile<'static>, Box<Error>> {
+    let full_path = root.join(path);
+    let mut data = Vec::new();
+    File::open(&full_path)?.read_to_end(&mut data)?;
+    let last_modified = options.modified_of(full_path.metadata()?.modified()?);
+    package_file(path, data, last_modified, options)
+}
+
+/// Compresses and encrypts a file to be written to a package as configured by `options`.
+fn package_file(
+    path: &str,
+    data: Vec<u8>,
+    last_modified: i64,
+    options: &PackageOptions,
+) -> Result<PackageFile<'static>, Box<Error>> {
+    let len = data.len() as u64;
+    let content_hash = options.content_hash_of(sha256(&data));
+    // The entry as it is stored, compressed or not
+    let (compression, stored) = match options.compression {
+        PackageCompression::None => (PackageCompression::None, data),
+        compression => {
+            let compressed = compression.compress(&data)?;
+            if (compressed.len() as u64) < len {
+                (compression, compressed)
+            } else {
+                (PackageCompression::None, data)
+            }
+        }
+    };
+    let (encrypted, stored) = options.encrypt_entry(stored);
+    Ok(PackageFile {
+        path: path.to_string(),
+        last_modified,
+        len,
+        compression,
+        encrypted,
+        stored: Cow::Owned(stored),
+        mime: options.mime_type_of(path),
+        content_hash,
+    })
+}
+
+/// A file to be written to a package by `write_files`.
+struct PackageFile<'a> {
+    path: String,
+    /// The modification date in seconds since the epoch.
+    last_modified: i64,
+    /// The size of the file, after decompressing it.
+    len: u64,
+    compression: PackageCompression,
+    encrypted: bool,
+    /// The entry as it is stored, compressed or not.
+    stored: Cow<'a, [u8]>,
+    mime: Option<String>,
+    /// The SHA-256 hash of the content, after decompressing it.
+    content_hash: [u8; 32],
+}
+
+/// Writes a package of the given files and directories with their modification dates, both
+/// sorted by path, laid out as configured by `options`.
+fn write_files<W: Write>(
+    files: &[PackageFile],
+    dirs: &[(String, i64)],
+    options: &PackageOptions,
+    writer: &mut W,
+) -> Result<(), Box<Error>> {
+    let mut data_offset = 0;
+    // Maps the checksums of the entries written so far to their start, files with the same
+    // content share one entry
+    let mut written: HashMap<[u8; 32], u64> = HashMap::new();
+    let mut unique_files = Vec::new();
+    let mut entries = Vec::new();
+    for f in files {
+        let mut entry = StoredEntry::of(f, 0);
+        match written.get(&entry.checksum) {
+            Some(&start) => entry.start = start,
+            None => {
+                entry.start = aligned(data_offset, options.align)?;
+                written.insert(entry.checksum, entry.start);
+                unique_files.push((entry.start - data_offset, &f.stored));
+                data_offset = entry.start + entry.len;
+            }
+        }
+        entries.push(entry);
+    }
+
+    let meta = package_metadata(files, &entries, dirs)?;
+    write_header(writer, &meta, options.meta_compression, options.align)?;
+    for (padding, stored) in unique_files {
+        pad(writer, padding)?;
+        writer.write_all(stored)?;
+    }
+
+    Ok(())
+}
+
+/// Where and how the entry of a file is stored in a package.
+#[derive(Clone, Copy)]
+struct StoredEntry {
+    /// The offset of the entry in the data of the package.
+    start: u64,
+    len: u64,
+    /// The compression flag, with `ENCRYPTED` set for encrypted entries.
+    flag: u8,
+    checksum: [u8; 32],
+}
+
+impl StoredEntry {
+    /// Describes the entry of a file holding its stored content, placed at `start`.
+    fn of(file: &PackageFile, start: u64) -> Self {
+        let encrypted = if file.encrypted { ENCRYPTED } else { 0 };
+        StoredEntry {
+            start,
+            len: file.stored.len() as u64,
+            flag: file.compression.flag() | encrypted,
+            checksum: sha256(&file.stored),
+        }
+    }
+}
+
+/// Serializes the metadata of a package, `entries` tells where the `files` are stored.
+fn package_metadata(
+    files: &[PackageFile],
+    entries: &[StoredEntry],
+    dirs: &[(String, i64)],
+) -> io::Result<Vec<u8>> {
+    let mut meta = Vec::new();
+    for (f, entry) in files.iter().zip(entries) {
+        // written in the following order: kind, path_len, path, last_modified, len, start,
+        // compression, stored_len, checksum, mime_len, mime, content_hash
+        meta.write_u8(FILE_ENTRY)?;
+        meta.write_u64::<BigEndian>(f.path.as_bytes().len() as u64)?;
+        write!(meta, "{}", f.path)?;
+        meta.write_i64::<BigEndian>(f.last_modified)?;
+        meta.write_u64::<BigEndian>(f.len)?;
+        meta.write_u64::<BigEndian>(entry.start)?;
+        meta.write_u8(entry.flag)?;
+        meta.write_u64::<BigEndian>(entry.len)?;
+        meta.write_all(&entry.checksum)?;
+
+        let mime = f.mime.as_ref().map_or("", |mime| mime.as_str());
+        meta.write_u64::<BigEndian>(mime.len() as u64)?;
+        write!(meta, "{}", mime)?;
+
+        meta.write_all(&f.content_hash)?;
+    }
+    for &(ref path, last_modified) in dirs {
+        meta.write_u8(DIRECTORY_ENTRY)?;
+        meta.write_u64::<BigEndian>(path.as_bytes().len() as u64)?;
+        write!(meta, "{}", path)?;
+        meta.write_i64::<BigEndian>(last_modified)?;
+    }
+    Ok(meta)
+}
+
+/// Writes the header of a package, followed by the metadata with its lengths and checksum and
+/// the padding aligning the entries to `align` bytes.
+///
+/// The metadata is compressed with `compression` unless it doesn't get smaller.
+fn write_header<W: Write>(
+    writer: &mut W,
+    meta: &[u8],
+    compression: PackageCompression,
+    align: u64,
+) -> Result<(), Box<Error>> {
+    let (compression, stored) = match compression {
+        PackageCompression::None => (compression, Cow::Borrowed(meta)),
+        compression => {
+            let compressed = compression.compress(meta)?;
+            if compressed.len() < meta.len() {
+                (compression, Cow::Owned(compressed))
+            } else {
+                (PackageCompression::None, Cow::Borrowed(meta))
+            }
+        }
+    };
+
+    let mut header = Vec::with_capacity(HEADER_LEN as usize);
+    header.write_all(MAGIC)?;
+    header.write_u32::<BigEndian>(VERSION)?;
+    header.write_u8(compression.flag())?;
+    header.write_u64::<BigEndian>(meta.len() as u64)?;
+    header.write_u64::<BigEndian>(align)?;
+    header.write_u64::<BigEndian>(stored.len() as u64)?;
+    let checksum = meta_checksum_of(&header, &stored);
+    writer.write_all(&header)?;
+    writer.write_all(&checksum)?;
+    writer.write_all(&stored)?;
+
+    let len = HEADER_LEN + stored.len() as u64;
+    pad(writer, aligned(len, align)? - len)?;
+    Ok(())
+}
+
+/// The length of the header of a package up to the metadata.
+const HEADER_LEN: u64 = 69;
+
+/// Returns `offset` rounded up to the next multiple of `align`, if that fits into 64 bits.
+fn align_up(offset: u64, align: u64) -> Option<u64> {
+    if align <= 1 {
+        return Some(offset);
+    }
+    offset
+        .checked_add(align - 1)
+        .map(|offset| offset / align * align)
+}
+
+/// Like `align_up`, failing if the offset doesn't fit into 64 bits.
+fn aligned(offset: u64, align: u64) -> io::Result<u64> {
+    align_up(offset, align)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "alignment is too large"))
+}
+
+/// Writes `len` zeros.
+fn pad<W: Write>(writer: &mut W, len: u64) -> io::Result<()> {
+    io::copy(&mut io::repeat(0).take(len), writer).map(|_| ())
+}
+
+/// Returns the checksum of the stored metadata and the header fields in front of it.
+fn meta_checksum_of(header: &[u8], stored_meta: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.input(header);
+    hasher.input(stored_meta);
+    let mut hash = [0; 32];
+    hash.copy_from_slice(&hasher.result());
+    hash
+}
+
 pub fn create_package_from_dir<P, W>(dir: P, writer: &mut W) -> Result<(), Box<Error>>
 where
     P: AsRef<Path>,
     W: Write,
 {
+    create_package_from_dir_with(dir, &PackageOptions::new(), writer)
+}
+
+/// Like `create_package_from_dir`, writing the package as configured by `options`.
+pub fn create_package_from_dir_with<P, W>(
+    dir: P,
+    options: &PackageOptions,
+    writer: &mut W,
+) -> Result<(), Box<Error>>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    create_package_from_dir_filtered(dir, &[], &[], options, writer)
+}
+
+/// Like `create_package_from_dir_with`, only packaging the files passing the given globs.
+///
+/// A shorthand for configuring a `Packager`, which offers a few more settings.
+///
+/// The globs work like the ones of `GlobFilteredFileSystem`: a file is packaged if it matches
+/// none of the `exclude` globs and, if there are `include` globs, at least one of them.
+/// Directories matching an exclude glob, e.g. `node_modules/**`, aren't even walked. Globs
+/// are matched against paths relative to `dir`.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{create_package_from_dir_filtered, PackageOptions};
+/// use rocket_static_fs::Glob;
+/// use std::fs::File;
+///
+/// let exclude = vec![
+///     Glob::new("**/*.map").unwrap(),
+///     Glob::new("**/.DS_Store").unwrap(),
+///     Glob::new("node_modules/**").unwrap(),
+/// ];
+/// let mut package = File::create("target/assets.package").unwrap();
+/// let options = PackageOptions::new();
+/// create_package_from_dir_filtered("testdata", &[], &exclude, &options, &mut package).unwrap();
+/// ```
+pub fn create_package_from_dir_filtered<P, W>(
+    dir: P,
+    include: &[Glob],
+    exclude: &[Glob],
+    options: &PackageOptions,
+    writer: &mut W,
+) -> Result<(), Box<Error>>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    let mut packager = Packager::new(dir).options(options.clone());
+    for glob in include {
+        packager = packager.include(glob.clone());
+    }
+    for glob in exclude {
+        packager = packager.exclude(glob.clone());
+    }
+    packager.write_to(writer)
+}
+
+/// Like `create_package_from_dir_filtered`, for build scripts: tells cargo to only run the
+/// build script again when a packaged file changes.
+///
+/// Prints `cargo:rerun-if-changed` for the directory and for every packaged directory and file,
+/// instead of cargo's default of running the build script again whenever any file of the crate
+/// changes. Files being added or removed change their directories, so they rebuild the package
+/// as well.
+///
+/// # Example
+///
+/// In `build.rs`:
+///
+/// ```no_run
+/// extern crate rocket_static_fs;
+///
+/// use rocket_static_fs::fs::{create_package_for_build_script, PackageOptions};
+/// use std::env;
+/// use std::fs::File;
+/// use std::path::Path;
+///
+/// fn main() {
+///     let out = Path::new(&env::var("OUT_DIR").unwrap()).join("assets.package");
+///     let mut package = File::create(out).unwrap();
+///     let options = PackageOptions::new();
+///     create_package_for_build_script("assets", &[], &[], &options, &mut package).unwrap();
+/// }
+/// ```
+pub fn create_package_for_build_script<P, W>(
+    dir: P,
+    include: &[Glob],
+    exclude: &[Glob],
+    options: &PackageOptions,
+    writer: &mut W,
+) -> Result<(), Box<Error>>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    let (root, mut files, dirs) = collect_files(dir, include, exclude)?;
+    for directive in rerun_if_changed(&root, &dirs, &files) {
+        println!("{}", directive);
+    }
+    files.extend(dirs);
+    write_package_with(root, &files, options, writer)
+}
+
+/// Returns the cargo directives to run the build script again when `root`, one of the
+/// directories or files below it or the environment read by `PackageOptions::reproducible`
+/// changes.
+fn rerun_if_changed<T: AsRef<str>>(root: &Path, dirs: &[T], files: &[T]) -> Vec<String> {
+    let paths = dirs.iter().chain(files).map(|path| root.join(path.as_ref()));
+    Some(root.to_path_buf())
+        .into_iter()
+        .chain(paths)
+        .map(|path| format!("cargo:rerun-if-changed={}", path.display()))
+        .chain(Some("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH".to_string()))
+        .collect()
+}
+
+/// Returns the canonical path of `dir` and the paths of the files and of the directories below
+/// it passing the globs, see `create_package_from_dir_filtered`.
+fn collect_files<P: AsRef<Path>>(
+    dir: P,
+    include: &[Glob],
+    exclude: &[Glob],
+) -> Result<(PathBuf, Vec<String>, Vec<String>), Box<Error>> {
     let root = dir.as_ref().canonicalize()?;
+    let excluded = |path: &str| {
+        let contents = format!("{}/", path);
+        exclude
+            .iter()
+            .any(|glob| glob.is_match(path) || glob.is_match(&contents))
+    };
+
     let mut files = Vec::new();
-    for entry in WalkDir::new(&dir) {
+    let mut dirs = Vec::new();
+    let walker = WalkDir::new(&dir).into_iter().filter_entry(|entry| {
+        let relative = entry.path().strip_prefix(&dir).ok().and_then(|p| p.to_str());
+        match relative {
+            Some("") => true,
+            Some(path) => !entry.file_type().is_dir() || !excluded(&path.replace('\\', "/")),
+            None => true,
+        }
+    });
+    for entry in walker {
         let entry = entry?;
-        if entry.metadata()?.is_file() {
-            let file_path = entry.path().canonicalize()?;
-            let path = file_path
-                .to_str()
-                .unwrap()
-                .replacen(root.to_str().unwrap(), "", 1);
-            let path = path.replace('\\', "/");
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() && !metadata.is_dir() {
+            continue;
+        }
+        let file_path = entry.path().canonicalize()?;
+        let path = file_path
+            .to_str()
+            .unwrap()
+            .replacen(root.to_str().unwrap(), "", 1);
+        let path = path.replace('\\', "/");
+        let path = path.trim_left_matches('/');
 
-            files.push(path.trim_left_matches('/').to_string())
+        if metadata.is_dir() {
+            // Excluded directories aren't walked, with include globs only the directories of
+            // the included files are kept
+            if !path.is_empty() && include.is_empty() {
+                dirs.push(path.to_string());
+            }
+            continue;
+        }
+        let included = include.is_empty() || include.iter().any(|glob| glob.is_match(path));
+        if included && !excluded(path) {
+            files.push(path.to_string())
         }
     }
 
-    write_package(root, &files, writer)
+    Ok((root, files, dirs))
 }
 
 #[cfg(test)]
@@ -274,6 +2284,11 @@ mod tests {
                     .read_to_string(&mut hello_str)
                     .unwrap();
                 assert_eq!(hello_str, "Hello World!");
+
+                assert!(p.dirs.contains_key(""));
+                assert!(p.dirs.get("").unwrap().contains("inner"));
+                assert!(p.dirs.get("inner").unwrap().contains("other.txt"));
+                assert!(!p.dirs.contains_key("hello.txt"));
             }
             Err(e) => panic!(format!(
                 "unable to read test.package, maybe you just need to re-run the test: {}",
@@ -281,4 +2296,649 @@ mod tests {
             )),
         }
     }
+
+    #[test]
+    fn test_compressed_package() {
+        use fs::testing::TempFileSystem;
+        use fs::FileSystem;
+
+        let css = "body { color: black; }\n".repeat(100);
+        let mut dir = TempFileSystem::new();
+        dir.insert("css/app.css", css.as_bytes());
+        dir.insert("favicon.ico", &[0u8, 1, 2, 3][..]);
+
+        let mut bytes = Vec::new();
+        write_package_with(
+            dir.path(),
+            &["css/app.css", "favicon.ico"],
+            &PackageOptions::new()
+                .compression(PackageCompression::Gzip)
+                .mime_type("ICO", "image/vnd.microsoft.icon"),
+            &mut bytes,
+        ).unwrap();
+        assert!(bytes.len() < css.len());
+        let fs = EmbeddedFileSystem::from_bytes(Box::leak(bytes.into_boxed_slice())).unwrap();
+
+        assert_eq!(fs.size(Path::new("css/app.css")).unwrap(), css.len() as u64);
+        assert_eq!(fs.content_type(Path::new("css/app.css")), Some("text/css".to_string()));
+        assert_eq!(
+            fs.content_type(Path::new("favicon.ico")),
+            Some("image/vnd.microsoft.icon".to_string())
+        );
+        // The first half of the SHA-256 hash of the icon
+        assert_eq!(
+            fs.etag(Path::new("favicon.ico")),
+            Some("\"054edec1d0211f624fed0cbca9d4f940\"".to_string())
+        );
+        let mut s = String::new();
+        fs.open(Path::new("css/app.css"), Some(6))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, &css[6..]);
+
+        let mut s = String::new();
+        let encoded = fs.open_encoded(Path::new("css/app.css"), "gzip").unwrap();
+        GzDecoder::new(encoded).read_to_string(&mut s).unwrap();
+        assert_eq!(s, css);
+
+        // Too small to get any smaller, so stored as it is
+        assert!(fs.open_encoded(Path::new("favicon.ico"), "gzip").is_none());
+        let mut icon = Vec::new();
+        fs.open(Path::new("favicon.ico"), None)
+            .unwrap()
+            .read_to_end(&mut icon)
+            .unwrap();
+        assert_eq!(icon, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_corrupt_package() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("hello.txt", "Hello World!");
+        let mut bytes = Vec::new();
+        write_package(dir.path(), &["hello.txt"], &mut bytes).unwrap();
+        assert!(Package::from_bytes(Box::leak(bytes.clone().into_boxed_slice())).is_ok());
+
+        // The last byte belongs to the file, the 79th to its path in the metadata and the 20th
+        // to the length of the uncompressed metadata in the header
+        for &i in &[bytes.len() - 1, 79, 20] {
+            let mut corrupt = bytes.clone();
+            corrupt[i] ^= 1;
+            assert!(Package::from_bytes(Box::leak(corrupt.into_boxed_slice())).is_err());
+        }
+        assert!(Package::from_bytes(Box::leak(bytes[..72].to_vec().into_boxed_slice())).is_err());
+    }
+
+    #[test]
+    fn test_package_versions() {
+        let mut bytes = Vec::new();
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/testdata");
+        write_package(dir, &["hello.txt"], &mut bytes).unwrap();
+        assert!(bytes.starts_with(MAGIC));
+
+        let mut future = bytes.clone();
+        future[MAGIC.len() + 3] = 9;
+        let err = Package::from_bytes(Box::leak(future.into_boxed_slice())).err().unwrap();
+        assert!(err.to_string().contains("version 9"));
+
+        // A package as written by rocket_static_fs 0.3
+        let mut legacy = Vec::new();
+        legacy.write_u64::<BigEndian>(8 + 9 + 24).unwrap();
+        legacy.write_u64::<BigEndian>(9).unwrap();
+        legacy.write_all(b"hello.txt").unwrap();
+        legacy.write_i64::<BigEndian>(1_500_000_000).unwrap();
+        legacy.write_u64::<BigEndian>(12).unwrap();
+        legacy.write_u64::<BigEndian>(0).unwrap();
+        legacy.write_all(b"Hello World!").unwrap();
+        let package = Package::from_bytes(Box::leak(legacy.clone().into_boxed_slice())).unwrap();
+        let mut s = String::new();
+        package.open("hello.txt").unwrap().read_to_string(&mut s).unwrap();
+        assert_eq!(s, "Hello World!");
+
+        assert!(Package::from_bytes(b"<!DOCTYPE html>").is_err());
+        assert!(Package::from_bytes(&[0; 64]).is_err());
+
+        let mut trailing = legacy.clone();
+        trailing.extend_from_slice(b"garbage");
+        assert!(Package::from_bytes(Box::leak(trailing.into_boxed_slice())).is_err());
+
+        let mut invalid_date = legacy.clone();
+        (&mut invalid_date[8 + 8 + 9..])
+            .write_i64::<BigEndian>(i64::max_value())
+            .unwrap();
+        assert!(Package::from_bytes(Box::leak(invalid_date.into_boxed_slice())).is_err());
+    }
+
+    #[test]
+    fn test_deduplicated_package() {
+        use fs::testing::TempFileSystem;
+
+        let icon = vec![7u8; 1000];
+        let mut dir = TempFileSystem::new();
+        dir.insert("de/icon.png", &icon);
+        dir.insert("en/icon.png", &icon);
+        dir.insert("en/other.png", &[1u8, 2, 3][..]);
+
+        let mut bytes = Vec::new();
+        let files = ["de/icon.png", "en/icon.png", "en/other.png"];
+        write_package(dir.path(), &files, &mut bytes).unwrap();
+        assert!(bytes.len() < 2 * icon.len());
+
+        let package = Package::from_bytes(Box::leak(bytes.into_boxed_slice())).unwrap();
+        for path in &files[..2] {
+            let mut content = Vec::new();
+            package.open(path).unwrap().read_to_end(&mut content).unwrap();
+            assert_eq!(content, icon);
+        }
+        assert_eq!(package.files["en/other.png"].len, 3);
+    }
+
+    #[test]
+    fn test_package_entries() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("b.txt", "Hello World!");
+        dir.insert("a/c.txt", "Hello World!");
+        dir.insert("a/d.css", "body {}");
+
+        let mut bytes = Vec::new();
+        let options = PackageOptions::new().compression(PackageCompression::Gzip);
+        create_package_from_dir_with(dir.path(), &options, &mut bytes).unwrap();
+        let package = Package::from_vec(bytes).unwrap();
+
+        let entries = package.entries();
+        let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, vec!["a/c.txt", "a/d.css", "b.txt"]);
+        assert_eq!(entries[0].size, 12);
+        assert_eq!(entries[0].offset, entries[2].offset);
+        assert_eq!(entries[0].compression, PackageCompression::Gzip);
+        assert_eq!(entries[1].mime, Some("text/css".to_string()));
+        assert!(entries[1].etag.is_some());
+
+        assert_eq!(package.read("b.txt").unwrap(), b"Hello World!");
+        let stored = package.read_stored("b.txt").unwrap();
+        assert_eq!(stored.len() as u64, entries[2].stored_size);
+        assert!(stored.starts_with(&[0x1f, 0x8b]));
+        assert!(package.read("missing.txt").is_err());
+        assert!(package.verify().is_ok());
+        #[cfg(unix)]
+        {
+            use std::ffi::OsStr;
+            use std::os::unix::ffi::OsStrExt;
+            let invalid = Path::new(OsStr::from_bytes(b"b\xff.txt"));
+            assert!(package.read(invalid).is_err());
+            assert!(package.open(invalid).is_err());
+
+            let fs = EmbeddedFileSystem::from_package(package).verify_reads(true);
+            assert!(!fs.is_file(invalid));
+            assert!(!fs.is_dir(invalid));
+            assert!(fs.read_dir(invalid).is_err());
+            assert!(fs.size(invalid).is_err());
+            assert!(fs.last_modified(invalid).is_err());
+            assert!(fs.metadata(invalid).is_err());
+            assert!(fs.open(invalid, None).is_err());
+        }
+    }
+
+    #[test]
+    fn test_reproducible_package() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("index.html", "<h1>Hello</h1>");
+        dir.insert("css/app.css", "body {}");
+        dir.insert("old.txt", "old");
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        dir.set_modified("old.txt", at(900_000_000));
+
+        let options = PackageOptions::new().clamp_modified(at(1_000_000_000));
+        let mut first = Vec::new();
+        dir.set_modified("index.html", at(1_500_000_000));
+        create_package_from_dir_with(dir.path(), &options, &mut first).unwrap();
+        let mut second = Vec::new();
+        dir.set_modified("index.html", at(1_600_000_000));
+        create_package_from_dir_with(dir.path(), &options, &mut second).unwrap();
+        assert_eq!(first, second);
+
+        let entries = Package::from_vec(first).unwrap().entries();
+        assert_eq!(entries[1].path, "index.html");
+        assert_eq!(entries[1].modified, at(1_000_000_000));
+        assert_eq!(entries[2].modified, at(900_000_000));
+    }
+
+    #[test]
+    fn test_compressed_metadata() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        for i in 0..100 {
+            let path = format!("static/images/icons/icon-{}.svg", i);
+            dir.insert(&path, format!("<svg>{}</svg>", i));
+        }
+        let mut plain = Vec::new();
+        create_package_from_dir(dir.path(), &mut plain).unwrap();
+        let options = PackageOptions::new().compress_metadata(PackageCompression::Gzip);
+        let mut compressed = Vec::new();
+        create_package_from_dir_with(dir.path(), &options, &mut compressed).unwrap();
+        assert!(compressed.len() < plain.len());
+
+        // An absurd length of the uncompressed metadata fails even with a matching checksum
+        let mut absurd = compressed.clone();
+        absurd[13..21].copy_from_slice(&[0xff; 8]);
+        let meta_len = (&absurd[29..37]).read_u64::<BigEndian>().unwrap() as usize;
+        let meta = HEADER_LEN as usize..HEADER_LEN as usize + meta_len;
+        let checksum = meta_checksum_of(&absurd[..37], &absurd[meta]);
+        absurd[37..69].copy_from_slice(&checksum);
+        assert!(Package::from_vec(absurd).is_err());
+
+        let package = Package::from_vec(compressed).unwrap();
+        assert_eq!(package.entries().len(), 100);
+        assert_eq!(package.read("static/images/icons/icon-7.svg").unwrap(), b"<svg>7</svg>");
+
+        let mut streamed = Cursor::new(Vec::new());
+        let (root, files, _) = collect_files(dir.path(), &[], &[]).unwrap();
+        assert!(write_package_streaming(root, &files, &options, &mut streamed).is_err());
+    }
+
+    #[test]
+    fn test_aligned_package() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("index.html", "<h1>Hello</h1>");
+        dir.insert("css/app.css", "body {}");
+        let options = PackageOptions::new().align(4096);
+        let mut bytes = Vec::new();
+        create_package_from_dir_with(dir.path(), &options, &mut bytes).unwrap();
+        assert_eq!(bytes.len(), 4096 * 2 + "<h1>Hello</h1>".len());
+
+        let package = Package::from_vec(bytes).unwrap();
+        assert_eq!(package.layers[0].data_start % 4096, 0);
+        for entry in package.entries() {
+            assert_eq!(entry.offset % 4096, 0);
+        }
+        assert_eq!(package.read("index.html").unwrap(), b"<h1>Hello</h1>");
+    }
+
+    #[test]
+    fn test_package_from_parts() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("index.html", "<h1>Hello</h1>".repeat(20));
+        dir.insert("js/app.js", "console.log('hello');".repeat(20));
+        dir.insert("empty.txt", "");
+        let mut bytes = Vec::new();
+        create_package_from_dir(dir.path(), &mut bytes).unwrap();
+        let whole = Package::from_vec(bytes.clone()).unwrap();
+
+        for &part_size in &[7, 100, 300] {
+            let parts: Vec<&'static [u8]> = bytes
+                .chunks(part_size)
+                .map(|part| &*Box::leak(part.to_vec().into_boxed_slice()))
+                .collect();
+            let package = Package::from_parts(&parts).unwrap();
+            assert_eq!(package.entries(), whole.entries());
+            for entry in whole.entries() {
+                assert_eq!(package.read(&entry.path).unwrap(), whole.read(&entry.path).unwrap());
+            }
+            // Only entries spanning two parts are copied
+            assert!(package.layers.len() < parts.len() * 2);
+        }
+
+        // Entries within one part are served from it
+        let part: &'static [u8] = Box::leak(bytes.clone().into_boxed_slice());
+        let package = Package::from_parts(&[part]).unwrap();
+        let stored = package.static_file(&package.files["index.html"]).unwrap();
+        let part_range = part.as_ptr() as usize..part.as_ptr() as usize + part.len();
+        assert!(part_range.contains(&(stored.as_ptr() as usize)));
+
+        let mut corrupt = bytes;
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 1;
+        let parts: Vec<&'static [u8]> = corrupt
+            .chunks(100)
+            .map(|part| &*Box::leak(part.to_vec().into_boxed_slice()))
+            .collect();
+        assert!(Package::from_parts(&parts).is_err());
+    }
+
+    #[test]
+    fn test_parallel_package() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        for i in 0..20 {
+            dir.insert(&format!("{}/file-{}.txt", i % 3, i), "x".repeat(i * 100));
+        }
+        let options = PackageOptions::new().compression(PackageCompression::Gzip);
+        let mut serial = Vec::new();
+        create_package_from_dir_with(dir.path(), &options, &mut serial).unwrap();
+        let mut parallel = Vec::new();
+        create_package_from_dir_with(dir.path(), &options.threads(4), &mut parallel).unwrap();
+        assert_eq!(parallel, serial);
+    }
+
+    #[test]
+    fn test_streaming_package() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("index.html", "<h1>Hello</h1><h1>Hello</h1><h1>Hello</h1>");
+        dir.insert("copy.html", "<h1>Hello</h1><h1>Hello</h1><h1>Hello</h1>");
+        dir.insert("css/app.css", "body {}");
+        let (root, mut files, dirs) = collect_files(dir.path(), &[], &[]).unwrap();
+        files.extend(dirs);
+
+        for options in &[
+            PackageOptions::new(),
+            PackageOptions::new().compression(PackageCompression::Gzip),
+            PackageOptions::new().fingerprint("manifest.json"),
+            PackageOptions::new().align(512),
+        ] {
+            let mut expected = Vec::new();
+            write_package_with(&root, &files, options, &mut expected).unwrap();
+            let mut streamed = Cursor::new(Vec::new());
+            write_package_streaming(&root, &files, options, &mut streamed).unwrap();
+            assert_eq!(streamed.into_inner(), expected);
+        }
+    }
+
+    #[test]
+    fn test_fingerprinted_package() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("index.html", "<h1>Hello</h1>");
+        dir.insert("css/app.css", "body {}");
+        dir.insert("LICENSE", "MIT");
+        dir.insert("robots.txt", "");
+
+        let options = PackageOptions::new()
+            .fingerprint("manifest.json")
+            .keep_name(Glob::new("robots.txt").unwrap());
+        let mut bytes = Vec::new();
+        create_package_from_dir_with(dir.path(), &options, &mut bytes).unwrap();
+        let package = Package::from_vec(bytes).unwrap();
+
+        let css = format!("css/app.{}.css", hex(&sha256(b"body {}")[..8]));
+        let license = format!("LICENSE.{}", hex(&sha256(b"MIT")[..8]));
+        let paths: Vec<String> = package.entries().into_iter().map(|e| e.path).collect();
+        assert_eq!(
+            paths,
+            vec![license.as_str(), css.as_str(), "index.html", "manifest.json", "robots.txt"]
+        );
+        assert_eq!(package.read(&css).unwrap(), b"body {}");
+
+        let manifest: HashMap<String, String> =
+            serde_json::from_slice(&package.read("manifest.json").unwrap()).unwrap();
+        assert_eq!(manifest.len(), 2);
+        assert_eq!(manifest["css/app.css"], css);
+        assert_eq!(manifest["LICENSE"], license);
+    }
+
+    #[test]
+    fn test_merged_packages() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("base/logo.svg", "<svg>base</svg>");
+        dir.insert("base/css/app.css", "body {}");
+        dir.insert("brand/logo.svg", "<svg>brand</svg>");
+        dir.insert("brand/fonts/brand.woff", "font");
+
+        let mut base = Vec::new();
+        create_package_from_dir(dir.path().join("base"), &mut base).unwrap();
+        let mut brand = Vec::new();
+        let options = PackageOptions::new().compression(PackageCompression::Gzip);
+        create_package_from_dir_with(dir.path().join("brand"), &options, &mut brand).unwrap();
+
+        let layers: Vec<&'static [u8]> = vec![
+            Box::leak(base.into_boxed_slice()),
+            Box::leak(brand.into_boxed_slice()),
+        ];
+        let fs = EmbeddedFileSystem::from_layers(&layers).unwrap();
+        let mut s = String::new();
+        fs.open(Path::new("logo.svg"), None).unwrap().read_to_string(&mut s).unwrap();
+        assert_eq!(s, "<svg>brand</svg>");
+        assert!(fs.is_file(Path::new("css/app.css")));
+        assert!(fs.is_dir(Path::new("fonts")));
+
+        let mut merged = Vec::new();
+        fs.package().write(&mut merged).unwrap();
+        let package = Package::from_vec(merged).unwrap();
+        let paths: Vec<String> = package.entries().into_iter().map(|e| e.path).collect();
+        assert_eq!(paths, vec!["css/app.css", "fonts/brand.woff", "logo.svg"]);
+        assert_eq!(package.read("logo.svg").unwrap(), b"<svg>brand</svg>");
+        assert_eq!(package.entries()[2].etag, fs.etag(Path::new("logo.svg")));
+    }
+
+    #[test]
+    #[cfg(feature = "test_embedded")]
+    fn test_embed_package() {
+        use fs::FileSystem;
+
+        let fs = embed_package!(concat!(env!("CARGO_MANIFEST_DIR"), "/target/test.package"));
+        assert!(fs.is_file(Path::new("hello.txt")));
+        assert!(fs.is_dir(Path::new("inner")));
+    }
+
+    #[test]
+    #[should_panic(expected = "testdata/hello.txt")]
+    fn test_embed_invalid_package() {
+        embed_package!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/hello.txt"));
+    }
+
+    #[test]
+    #[cfg(feature = "encryption")]
+    fn test_encrypted_package() {
+        use fs::testing::TempFileSystem;
+
+        let key = [7u8; 32];
+        let secret = "proprietary asset, not to be found with strings";
+        let mut dir = TempFileSystem::new();
+        dir.insert("secret.txt", secret);
+        dir.insert("copy.txt", secret);
+
+        let mut bytes = Vec::new();
+        let options = PackageOptions::new().encrypt(key);
+        create_package_from_dir_with(dir.path(), &options, &mut bytes).unwrap();
+        assert!(!String::from_utf8_lossy(&bytes).contains("proprietary"));
+
+        let package = Package::from_vec(bytes.clone()).unwrap();
+        assert_eq!(package.entries()[0].offset, package.entries()[1].offset);
+        assert!(package.read("secret.txt").is_err());
+        // Neither the ETag nor the nonce allow checking a guess of the contents
+        let hash = sha256(secret.as_bytes());
+        assert_ne!(package.entries()[0].etag, Some(etag(&hash)));
+        assert_ne!(&package.read_stored("secret.txt").unwrap()[..12], &hash[..12]);
+        let fs = EmbeddedFileSystem::from_vec(bytes.clone()).unwrap();
+        assert!(fs.open(Path::new("secret.txt"), None).is_err());
+        assert_eq!(fs.size(Path::new("secret.txt")).unwrap(), secret.len() as u64);
+
+        let fs = EmbeddedFileSystem::from_vec(bytes).unwrap().decryption_key(key);
+        let mut s = String::new();
+        fs.open(Path::new("secret.txt"), None).unwrap().read_to_string(&mut s).unwrap();
+        assert_eq!(s, secret);
+
+        let fs = EmbeddedFileSystem::from_package(package.decryption_key([8u8; 32]));
+        assert!(fs.open(Path::new("secret.txt"), None).is_err());
+    }
+
+    #[test]
+    fn test_directory_entries() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("docs/guide/index.html", "<h1>Guide</h1>");
+        ::std::fs::create_dir_all(dir.path().join("uploads")).unwrap();
+        let modified = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        dir.set_modified("docs", modified);
+
+        let mut bytes = Vec::new();
+        create_package_from_dir(dir.path(), &mut bytes).unwrap();
+        let fs = EmbeddedFileSystem::from_vec(bytes).unwrap();
+
+        assert!(fs.is_dir(Path::new("uploads")));
+        assert!(fs.read_dir(Path::new("uploads")).unwrap().is_empty());
+        let metadata = fs.metadata(Path::new("docs")).unwrap();
+        assert!(metadata.is_dir);
+        assert_eq!(metadata.modified, Some(modified));
+        assert_eq!(fs.last_modified(Path::new("docs")).unwrap(), modified);
+
+        let entries = fs.read_dir(Path::new("")).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["docs", "uploads"]);
+        assert_eq!(entries[0].modified, Some(modified));
+        assert!(fs.metadata(Path::new("missing")).is_err());
+    }
+
+    #[test]
+    fn test_source_dir() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("assets/app.css", "body {}");
+        dir.insert("assets/app.js", "app();");
+        let mut bytes = Vec::new();
+        create_package_from_dir(dir.path().join("assets"), &mut bytes).unwrap();
+
+        dir.insert("assets/app.css", "body { color: red; }");
+        dir.insert("assets/new.js", "new();");
+        ::std::fs::remove_file(dir.path().join("assets/app.js")).unwrap();
+        let fs = EmbeddedFileSystem::from_vec(bytes)
+            .unwrap()
+            .source_dir(dir.path().join("assets"));
+
+        let mut s = String::new();
+        fs.open(Path::new("app.css"), None).unwrap().read_to_string(&mut s).unwrap();
+        assert_eq!(s, "body { color: red; }");
+        assert_eq!(fs.size(Path::new("app.css")).unwrap(), 20);
+        assert!(fs.is_file(Path::new("new.js")));
+        assert_eq!(fs.size(Path::new("app.js")).unwrap(), 6);
+
+        let names: Vec<String> = fs.read_dir(Path::new(""))
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["app.css", "app.js", "new.js"]);
+    }
+
+    #[test]
+    fn test_embedded_file_system_from_file() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("assets/hello.txt", "Hello World!");
+        let mut package = File::create(dir.path().join("assets.package")).unwrap();
+        create_package_from_dir(dir.path().join("assets"), &mut package).unwrap();
+
+        let fs = EmbeddedFileSystem::from_file(dir.path().join("assets.package")).unwrap();
+        let mut s = String::new();
+        fs.open(Path::new("hello.txt"), Some(6))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "World!");
+
+        assert!(EmbeddedFileSystem::from_file(dir.path().join("missing.package")).is_err());
+        assert!(EmbeddedFileSystem::from_vec(b"Hello World!".to_vec()).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_embedded_file_system_open_mmap() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("assets/hello.txt", "Hello World!");
+        let mut package = File::create(dir.path().join("assets.package")).unwrap();
+        create_package_from_dir(dir.path().join("assets"), &mut package).unwrap();
+
+        let fs = EmbeddedFileSystem::open_mmap(dir.path().join("assets.package")).unwrap();
+        let mut s = String::new();
+        fs.open(Path::new("hello.txt"), None)
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "Hello World!");
+        assert!(fs.open(Path::new("missing.txt"), None).is_err());
+    }
+
+    #[test]
+    fn test_create_package_from_dir_filtered() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("js/app.js", "app();");
+        dir.insert("js/app.js.map", "{}");
+        dir.insert("node_modules/lib/index.js", "lib();");
+        dir.insert(".DS_Store", "");
+
+        let exclude = vec![
+            Glob::new("**/*.map").unwrap(),
+            Glob::new("**/.DS_Store").unwrap(),
+            Glob::new("node_modules/**").unwrap(),
+        ];
+        let mut bytes = Vec::new();
+        create_package_from_dir_filtered(
+            dir.path(),
+            &[],
+            &exclude,
+            &PackageOptions::new(),
+            &mut bytes,
+        ).unwrap();
+        let package = Package::from_bytes(Box::leak(bytes.into_boxed_slice())).unwrap();
+        assert_eq!(package.files.keys().collect::<Vec<_>>(), vec!["js/app.js"]);
+
+        let mut bytes = Vec::new();
+        let include = vec![Glob::new("**/*.map").unwrap()];
+        create_package_from_dir_filtered(
+            dir.path(),
+            &include,
+            &[],
+            &PackageOptions::new(),
+            &mut bytes,
+        ).unwrap();
+        let package = Package::from_bytes(Box::leak(bytes.into_boxed_slice())).unwrap();
+        assert_eq!(package.files.keys().collect::<Vec<_>>(), vec!["js/app.js.map"]);
+    }
+
+    #[test]
+    fn test_create_package_for_build_script() {
+        use fs::testing::TempFileSystem;
+
+        let mut dir = TempFileSystem::new();
+        dir.insert("js/app.js", "app();");
+        dir.insert("node_modules/lib/index.js", "lib();");
+        let exclude = vec![Glob::new("node_modules/**").unwrap()];
+
+        let mut bytes = Vec::new();
+        create_package_for_build_script(
+            dir.path(),
+            &[],
+            &exclude,
+            &PackageOptions::new(),
+            &mut bytes,
+        ).unwrap();
+        let package = Package::from_bytes(Box::leak(bytes.into_boxed_slice())).unwrap();
+        assert_eq!(package.files.keys().collect::<Vec<_>>(), vec!["js/app.js"]);
+
+        // The root and the packaged directories and files trigger a rebuild
+        let (root, files, dirs) = collect_files(dir.path(), &[], &exclude).unwrap();
+        assert_eq!(
+            rerun_if_changed(&root, &dirs, &files),
+            vec![
+                format!("cargo:rerun-if-changed={}", root.display()),
+                format!("cargo:rerun-if-changed={}", root.join("js").display()),
+                format!("cargo:rerun-if-changed={}", root.join("js/app.js").display()),
+                "cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH".to_string(),
+            ]
+        );
+    }
 }
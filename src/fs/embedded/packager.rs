@@ -0,0 +1,553 @@
+use super::{
+    collect_files, copy_hashed, etag, write_package_streaming, write_package_with, Package,
+    PackageCompression, PackageEntry, PackageOptions,
+};
+use chrono::{DateTime, Utc};
+use serde_json;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use Glob;
+
+/// Creates packages from a directory, configured step by step.
+///
+/// `write_to` writes the package to any writer. `build` does everything a build script needs
+/// in one call: it writes `<name>.package` and `<name>.rs` to the `OUT_DIR` of the build and
+/// tells cargo to run the build script again whenever anything in the directory changes. The
+/// name defaults to the name of the directory. `<name>.rs` holds the package as `PACKAGE`, the
+/// directory as `SOURCE_DIR` and a module `paths` with a constant for the path of every file,
+/// e.g. `CSS_APP_CSS` for `css/app.css`, so typos in paths are compile errors.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{PackageCompression, Packager};
+/// use rocket_static_fs::Glob;
+/// use std::fs::File;
+///
+/// let mut package = File::create("target/testdata.package").unwrap();
+/// Packager::new("testdata")
+///     .exclude(Glob::new("**/*.map").unwrap())
+///     .compression(PackageCompression::Gzip)
+///     .reproducible()
+///     .write_to(&mut package)
+///     .unwrap();
+/// ```
+///
+/// In `build.rs`:
+///
+/// ```no_run
+/// extern crate rocket_static_fs;
+///
+/// use rocket_static_fs::fs::Packager;
+///
+/// fn main() {
+///     Packager::new("assets").build().unwrap();
+/// }
+/// ```
+///
+/// In `main.rs`:
+///
+/// ```ignore
+/// extern crate rocket_static_fs;
+///
+/// use rocket_static_fs::fs::EmbeddedFileSystem;
+///
+/// mod assets {
+///     include!(concat!(env!("OUT_DIR"), "/assets.rs"));
+/// }
+///
+/// fn main() {
+///     // Debug builds serve changed files right from the directory
+///     let fs = EmbeddedFileSystem::from_bytes(assets::PACKAGE)
+///         .unwrap()
+///         .source_dir_in_debug(assets::SOURCE_DIR);
+///     println!("serving {}", assets::paths::INDEX_HTML);
+/// }
+/// ```
+pub struct Packager {
+    dir: PathBuf,
+    name: Option<String>,
+    out_dir: Option<PathBuf>,
+    options: PackageOptions,
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+    /// The size of the parts the package is split into by `build`, if it's split.
+    part_size: Option<u64>,
+}
+
+impl Packager {
+    /// Packages the given directory, relative paths are relative to the crate being built.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Packager {
+            dir: dir.as_ref().to_path_buf(),
+            name: None,
+            out_dir: None,
+            options: PackageOptions::new(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            part_size: None,
+        }
+    }
+
+    /// Sets the name of the generated files.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Writes the files to the given directory instead of `OUT_DIR`.
+    pub fn out_dir<P: AsRef<Path>>(mut self, out_dir: P) -> Self {
+        self.out_dir = Some(out_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets compression and MIME types of the package.
+    pub fn options(mut self, options: PackageOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Compresses the files with the given method, see `PackageOptions::compression`.
+    pub fn compression(mut self, compression: PackageCompression) -> Self {
+        self.options = self.options.compression(compression);
+        self
+    }
+
+    /// Stores modification dates later than `max` as `max`, see
+    /// `PackageOptions::clamp_modified`.
+    pub fn clamp_modified(mut self, max: SystemTime) -> Self {
+        self.options = self.options.clamp_modified(max);
+        self
+    }
+
+    /// Makes the package reproducible, see `PackageOptions::reproducible`.
+    pub fn reproducible(mut self) -> Self {
+        self.options = self.options.reproducible();
+        self
+    }
+
+    /// Prepares the files on the given number of threads, see `PackageOptions::threads`.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.options = self.options.threads(threads);
+        self
+    }
+
+    /// Only packages files matching the glob or another include glob, see
+    /// `create_package_from_dir_filtered`.
+    pub fn include(mut self, glob: Glob) -> Self {
+        self.include.push(glob);
+        self
+    }
+
+    /// Leaves out files and directories matching the glob.
+    pub fn exclude(mut self, glob: Glob) -> Self {
+        self.exclude.push(glob);
+        self
+    }
+
+    /// Makes `build` split the package into parts of at most `part_size` bytes, written to
+    /// `<name>.package.0`, `<name>.package.1` and so on, e.g. if embedding a huge package slows
+    /// rustc down or hits limits of the linker. `<name>.rs` then holds the parts as
+    /// `PACKAGE_PARTS` instead of `PACKAGE`, to be loaded with `EmbeddedFileSystem::from_parts`.
+    pub fn split(mut self, part_size: u64) -> Self {
+        self.part_size = Some(part_size.max(1));
+        self
+    }
+
+    /// Writes the package to `writer`, relative directories are relative to the working
+    /// directory.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), Box<Error>> {
+        self.write_package(&self.dir, writer).map(|_| ())
+    }
+
+    /// Like `write_to`, holding at most one file in memory at a time, see
+    /// `write_package_streaming`.
+    pub fn write_streaming_to<W: Write + Seek>(&self, writer: &mut W) -> Result<(), Box<Error>> {
+        let (root, mut files, dirs) = collect_files(&self.dir, &self.include, &self.exclude)?;
+        files.extend(dirs);
+        write_package_streaming(root, &files, &self.options, writer)
+    }
+
+    /// Compares the package file at `package` with the directory, e.g. to tell whether it's up
+    /// to date. A package which can't be loaded counts as empty.
+    ///
+    /// Files differing in size or modification date are modified. If the dates are clamped,
+    /// see `PackageOptions::clamp_modified`, files of the same size are hashed instead. Changes
+    /// of the options, e.g. of the compression, aren't noticed.
+    pub fn changes<P: AsRef<Path>>(&self, package: P) -> Result<PackageChanges, Box<Error>> {
+        self.compare(&self.dir, package.as_ref())
+            .map(|(_, changes)| changes)
+    }
+
+    /// Writes the package to the file at `package` unless it's up to date, see `changes`.
+    /// Returns the changes, e.g. to print them as a summary.
+    pub fn update<P: AsRef<Path>>(&self, package: P) -> Result<PackageChanges, Box<Error>> {
+        self.update_package(&self.dir, package.as_ref())
+            .map(|(_, changes)| changes)
+    }
+
+    /// Updates the package of `dir` at `package`, returns the paths of the packaged files and
+    /// the changes.
+    fn update_package(
+        &self,
+        dir: &Path,
+        package: &Path,
+    ) -> Result<(Vec<String>, PackageChanges), Box<Error>> {
+        let (files, changes) = self.compare(dir, package)?;
+        if !changes.is_empty() || !package.is_file() {
+            self.write_package(dir, &mut File::create(package)?)?;
+        }
+        Ok((files, changes))
+    }
+
+    /// Compares the package at `package` with `dir`, returns the paths of the files in `dir`
+    /// and the changes.
+    fn compare(
+        &self,
+        dir: &Path,
+        package: &Path,
+    ) -> Result<(Vec<String>, PackageChanges), Box<Error>> {
+        let (root, files, _) = collect_files(dir, &self.include, &self.exclude)?;
+        let package = Package::from_file(package).ok();
+
+        // Fingerprinted files are found by the manifest of the package
+        let fingerprints: HashMap<String, String> = match (&package, &self.options.manifest) {
+            (&Some(ref package), &Some(ref manifest)) => package
+                .read(manifest)
+                .ok()
+                .and_then(|json| serde_json::from_slice(&json).ok())
+                .unwrap_or_default(),
+            _ => HashMap::new(),
+        };
+        let mut entries: HashMap<String, PackageEntry> = package
+            .iter()
+            .flat_map(|package| package.entries())
+            .map(|entry| (entry.path.clone(), entry))
+            .collect();
+        if let Some(ref manifest) = self.options.manifest {
+            entries.remove(manifest);
+        }
+
+        let mut changes = PackageChanges::default();
+        for file in &files {
+            let path = fingerprints.get(file).unwrap_or(file);
+            match entries.remove(path) {
+                Some(entry) => {
+                    if self.is_modified(&root.join(file), &entry)? {
+                        changes.modified.push(file.clone());
+                    }
+                }
+                None => changes.added.push(file.clone()),
+            }
+        }
+
+        let originals: HashMap<&String, &String> =
+            fingerprints.iter().map(|(path, fingerprinted)| (fingerprinted, path)).collect();
+        changes.removed = entries
+            .keys()
+            .map(|path| originals.get(path).map_or(path, |path| *path).clone())
+            .collect();
+        changes.added.sort();
+        changes.removed.sort();
+        changes.modified.sort();
+        Ok((files, changes))
+    }
+
+    /// Returns whether the file at `path` differs from its entry in the package.
+    fn is_modified(&self, path: &Path, entry: &PackageEntry) -> Result<bool, Box<Error>> {
+        let metadata = path.metadata()?;
+        if metadata.len() != entry.size {
+            return Ok(true);
+        }
+        let modified = self.options.modified_of(metadata.modified()?);
+        if modified != DateTime::<Utc>::from(entry.modified).timestamp() {
+            return Ok(true);
+        }
+
+        // Clamped dates are equal whatever the content is
+        if self.options.max_modified.map_or(false, |max| modified >= max) {
+            let (_, content_hash) = copy_hashed(&mut File::open(path)?, &mut io::sink())?;
+            let content_hash = self.options.content_hash_of(content_hash);
+            return Ok(entry.etag != Some(etag(&content_hash)));
+        }
+        Ok(false)
+    }
+
+    /// Writes the package of `dir`, returns the paths of the packaged files.
+    fn write_package<W>(&self, dir: &Path, writer: &mut W) -> Result<Vec<String>, Box<Error>>
+    where
+        W: Write,
+    {
+        let (root, files, dirs) = collect_files(dir, &self.include, &self.exclude)?;
+        let mut paths = files.clone();
+        paths.extend(dirs);
+        write_package_with(root, &paths, &self.options, writer)?;
+        Ok(files)
+    }
+
+    /// Writes the package and the source, returns the path of the source.
+    pub fn build(self) -> Result<PathBuf, Box<Error>> {
+        let dir = match env::var_os("CARGO_MANIFEST_DIR") {
+            Some(ref manifest_dir) if self.dir.is_relative() => {
+                Path::new(manifest_dir).join(&self.dir)
+            }
+            _ => self.dir.clone(),
+        };
+        let out_dir = match self.out_dir {
+            Some(ref out_dir) => out_dir.clone(),
+            None => env::var_os("OUT_DIR")
+                .map(PathBuf::from)
+                .ok_or("OUT_DIR isn't set, run the Packager from a build script or set out_dir")?,
+        };
+        let name = match self.name {
+            Some(ref name) => name.clone(),
+            None => dir.file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or("assets")
+                .to_string(),
+        };
+
+        let package = out_dir.join(format!("{}.package", name));
+        let (files, changes) = self.update_package(&dir, &package)?;
+        let parts = match self.part_size {
+            Some(part_size) => Some(split_file(&package, part_size, !changes.is_empty())?),
+            None => None,
+        };
+        println!("cargo:rerun-if-changed={}", dir.display());
+        // Read by `PackageOptions::reproducible`
+        println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+        let source = out_dir.join(format!("{}.rs", name));
+        let mut writer = File::create(&source)?;
+        writeln!(writer, "// Generated by rocket_static_fs, don't edit.")?;
+        writeln!(writer)?;
+        match parts {
+            Some(parts) => {
+                writeln!(writer, "/// The parts of the package, to be loaded with")?;
+                writeln!(writer, "/// `EmbeddedFileSystem::from_parts`.")?;
+                writeln!(writer, "pub static PACKAGE_PARTS: &[&[u8]] = &[")?;
+                for part in parts {
+                    writeln!(writer, "    include_bytes!({:?}),", utf8(&part)?)?;
+                }
+                writeln!(writer, "];")?;
+            }
+            None => {
+                writeln!(
+                    writer,
+                    "/// The package, to be loaded with `EmbeddedFileSystem::from_bytes`."
+                )?;
+                let package = utf8(&package)?;
+                writeln!(writer, "pub static PACKAGE: &[u8] = include_bytes!({:?});", package)?;
+            }
+        }
+        writeln!(writer)?;
+        writeln!(writer, "/// The directory the package was built from, see")?;
+        writeln!(writer, "/// `EmbeddedFileSystem::source_dir_in_debug`.")?;
+        writeln!(writer, "pub const SOURCE_DIR: &str = {:?};", utf8(&dir)?)?;
+        writeln!(writer)?;
+        writeln!(writer, "/// The paths of the files in the package.")?;
+        writeln!(writer, "pub mod paths {{")?;
+        let mut names = HashSet::new();
+        for file in &files {
+            let constant = const_name(file);
+            if names.insert(constant.clone()) {
+                writeln!(writer, "    pub const {}: &str = {:?};", constant, file)?;
+            } else {
+                println!("cargo:warning=no path constant for {}, {} is taken", file, constant);
+            }
+        }
+        writeln!(writer, "}}")?;
+
+        Ok(source)
+    }
+}
+
+/// Splits the file at `path` into files of at most `part_size` bytes named `<path>.<n>`,
+/// returns their paths. Existing parts are only written again if `rewrite` is set or some are
+/// missing, so cargo doesn't embed them again for nothing.
+fn split_file(path: &Path, part_size: u64, rewrite: bool) -> Result<Vec<PathBuf>, Box<Error>> {
+    let len = path.metadata()?.len();
+    let count = ((len + part_size - 1) / part_size).max(1);
+    let parts: Vec<PathBuf> = (0..count)
+        .map(|i| PathBuf::from(format!("{}.{}", path.display(), i)))
+        .collect();
+    if rewrite || parts.iter().any(|part| !part.is_file()) {
+        let mut file = File::open(path)?;
+        for part in &parts {
+            io::copy(&mut (&mut file).take(part_size), &mut File::create(part)?)?;
+        }
+    }
+    Ok(parts)
+}
+
+/// Returns the path as a string for generated source.
+fn utf8(path: &Path) -> Result<&str, Box<Error>> {
+    path.to_str()
+        .ok_or_else(|| From::from(format!("path is no valid UTF-8: {}", path.display())))
+}
+
+/// The differences between a package and the directory it's built from, see
+/// `Packager::changes`. The paths are sorted.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PackageChanges {
+    /// Files missing in the package.
+    pub added: Vec<String>,
+    /// Files missing in the directory.
+    pub removed: Vec<String>,
+    /// Files differing from their entries.
+    pub modified: Vec<String>,
+}
+
+impl PackageChanges {
+    /// Returns true if the package is up to date.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl fmt::Display for PackageChanges {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{} added, {} removed, {} modified",
+            self.added.len(),
+            self.removed.len(),
+            self.modified.len()
+        )
+    }
+}
+
+/// Turns a path into the name of a constant, e.g. `css/app.css` into `CSS_APP_CSS`.
+fn const_name(path: &str) -> String {
+    let mut name: String = path.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    if name.chars().next().map_or(true, |c| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{const_name, Packager};
+    use fs::embedded::{EmbeddedFileSystem, Package, PackageCompression};
+    use fs::FileSystem;
+    use std::path::Path;
+    use fs::testing::TempFileSystem;
+    use std::fs;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_packager() {
+        let mut dir = TempFileSystem::new();
+        dir.insert("assets/css/app.css", "body {}");
+        dir.insert("assets/404.html", "Not Found");
+        dir.insert("assets/app.js.map", "{}");
+
+        let source = Packager::new(dir.path().join("assets"))
+            .out_dir(dir.path())
+            .exclude(::Glob::new("**/*.map").unwrap())
+            .build()
+            .unwrap();
+        assert_eq!(source, dir.path().join("assets.rs"));
+        assert!(dir.path().join("assets.package").is_file());
+
+        let source = fs::read_to_string(source).unwrap();
+        assert!(source.contains("pub static PACKAGE: &[u8] = include_bytes!("));
+        assert!(source.contains("pub const SOURCE_DIR: &str = "));
+        assert!(source.contains("    pub const CSS_APP_CSS: &str = \"css/app.css\";"));
+        assert!(source.contains("    pub const _404_HTML: &str = \"404.html\";"));
+        assert!(!source.contains("app.js.map"));
+
+        assert_eq!(const_name("img/logo-dark.svg"), "IMG_LOGO_DARK_SVG");
+    }
+
+    #[test]
+    fn test_packager_write_to() {
+        let mut dir = TempFileSystem::new();
+        dir.insert("css/app.css", "body {}");
+        dir.insert("app.js.map", "{}");
+
+        let mut bytes = Vec::new();
+        Packager::new(dir.path())
+            .exclude(::Glob::new("**/*.map").unwrap())
+            .compression(PackageCompression::Gzip)
+            .clamp_modified(UNIX_EPOCH + Duration::from_secs(1000))
+            .write_to(&mut bytes)
+            .unwrap();
+
+        let package = Package::from_vec(bytes).unwrap();
+        let entries = package.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path, "css/app.css");
+        assert_eq!(entries[0].compression, PackageCompression::Gzip);
+        assert_eq!(entries[0].modified, UNIX_EPOCH + Duration::from_secs(1000));
+        assert_eq!(package.read("css/app.css").unwrap(), b"body {}");
+    }
+
+    #[test]
+    fn test_packager_update() {
+        let mut dir = TempFileSystem::new();
+        dir.insert("assets/index.html", "<h1>Hello</h1>");
+        dir.insert("assets/app.css", "body {}");
+        dir.insert("assets/old.js", "");
+        let package = dir.path().join("assets.package");
+        let packager = Packager::new(dir.path().join("assets")).clamp_modified(UNIX_EPOCH);
+
+        let changes = packager.update(&package).unwrap();
+        assert_eq!(changes.added.len(), 3);
+        assert!(packager.changes(&package).unwrap().is_empty());
+
+        dir.insert("assets/new.js", "");
+        dir.insert("assets/app.css", "body {} ");
+        dir.insert("assets/index.html", "<h1>Hallo</h1>");
+        fs::remove_file(dir.path().join("assets/old.js")).unwrap();
+        let changes = packager.update(&package).unwrap();
+        assert_eq!(changes.added, vec!["new.js"]);
+        assert_eq!(changes.removed, vec!["old.js"]);
+        assert_eq!(changes.modified, vec!["app.css", "index.html"]);
+        assert_eq!(changes.to_string(), "1 added, 1 removed, 2 modified");
+
+        let package = Package::from_file(&package).unwrap();
+        assert_eq!(package.read("index.html").unwrap(), b"<h1>Hallo</h1>");
+        assert!(packager.changes(dir.path().join("assets.package")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_packager() {
+        let mut dir = TempFileSystem::new();
+        dir.insert("assets/index.html", "<h1>Hello</h1>".repeat(20));
+
+        let source = Packager::new(dir.path().join("assets"))
+            .out_dir(dir.path())
+            .split(100)
+            .build()
+            .unwrap();
+        let source = fs::read_to_string(source).unwrap();
+        assert!(source.contains("pub static PACKAGE_PARTS: &[&[u8]] = &["));
+        assert!(!source.contains("pub static PACKAGE: "));
+
+        let mut parts = Vec::new();
+        for i in 0.. {
+            match fs::read(dir.path().join(format!("assets.package.{}", i))) {
+                Ok(part) => parts.push(part),
+                Err(_) => break,
+            }
+        }
+        assert!(parts.len() > 1);
+        assert!(parts.iter().all(|part| part.len() <= 100));
+        let parts: Vec<&'static [u8]> = parts
+            .into_iter()
+            .map(|part| &*Box::leak(part.into_boxed_slice()))
+            .collect();
+        let fs = EmbeddedFileSystem::from_parts(&parts).unwrap();
+        assert!(fs.is_file(Path::new("index.html")));
+    }
+}
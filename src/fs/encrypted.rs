@@ -0,0 +1,216 @@
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::Aes256Gcm;
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use hex;
+use std::env;
+use std::error::Error as StdError;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::time::SystemTime;
+use Error;
+
+/// Length of the nonce preceding the ciphertext.
+const NONCE_LEN: usize = 12;
+/// Length of the authentication tag following the ciphertext.
+const TAG_LEN: usize = 16;
+/// Bytes an encrypted file is longer than its content.
+const OVERHEAD: u64 = (NONCE_LEN + TAG_LEN) as u64;
+
+/// Decrypts the files of another FileSystem, which are kept encrypted at rest.
+///
+/// Files are encrypted with AES-256-GCM: a 12 byte nonce followed by the ciphertext and the
+/// 16 byte tag, as written by `encrypt_file`. Files are decrypted as a whole when opened, so
+/// this suits small files like fonts. Several keys can be given to rotate them, each file is
+/// decrypted with the first key that authenticates it.
+///
+/// Sizes are those of the decrypted files. Precompressed files of the inner FileSystem aren't
+/// used, as they'd be encrypted as well. Requires the `encryption` feature.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{EncryptedFileSystem, LocalFileSystem};
+///
+/// let key = [0u8; 32]; // load it from your secret store instead
+/// let fs = EncryptedFileSystem::new(LocalFileSystem::new("fonts"), key);
+/// ```
+pub struct EncryptedFileSystem<T> {
+    inner: T,
+    ciphers: Vec<Aes256Gcm>,
+}
+
+impl<T: FileSystem> EncryptedFileSystem<T> {
+    pub fn new(inner: T, key: [u8; 32]) -> Self {
+        EncryptedFileSystem {
+            inner,
+            ciphers: vec![cipher(&key)],
+        }
+    }
+
+    /// Adds a key to decrypt files with which the previous keys don't authenticate, e.g. the
+    /// old key while files are re-encrypted with a new one.
+    pub fn key(mut self, key: [u8; 32]) -> Self {
+        self.ciphers.push(cipher(&key));
+        self
+    }
+
+    fn decrypt(&self, path: &Path) -> Result<Vec<u8>, Error> {
+        let mut encrypted = Vec::new();
+        self.inner.open(path, None)?.read_to_end(&mut encrypted)?;
+        decrypt_with(&self.ciphers, &encrypted)
+    }
+}
+
+/// Decrypts a file written by `encrypt_file` with the first cipher which authenticates it.
+fn decrypt_with(ciphers: &[Aes256Gcm], encrypted: &[u8]) -> Result<Vec<u8>, Error> {
+    if encrypted.len() < OVERHEAD as usize {
+        return Err(Error::backend("file too short to be encrypted"));
+    }
+
+    let (nonce, ciphertext) = encrypted.split_at(NONCE_LEN);
+    let nonce = GenericArray::from_slice(nonce);
+    ciphers
+        .iter()
+        .filter_map(|cipher| cipher.decrypt(nonce, ciphertext).ok())
+        .next()
+        .ok_or_else(|| Error::backend("file could not be decrypted"))
+}
+
+/// Decrypts a file written by `encrypt_file`.
+pub fn decrypt_file(key: &[u8; 32], encrypted: &[u8]) -> Result<Vec<u8>, Error> {
+    decrypt_with(&[cipher(key)], encrypted)
+}
+
+/// Reads a key from the environment variable `var`, given as 64 hex digits.
+pub fn key_from_env(var: &str) -> Result<[u8; 32], Box<StdError>> {
+    let value = env::var(var).map_err(|err| format!("{}: {}", var, err))?;
+    let bytes = hex::decode(value.trim()).map_err(|err| format!("{}: {}", var, err))?;
+    if bytes.len() != 32 {
+        return Err(From::from(format!("{} must hold 32 bytes as 64 hex digits", var)));
+    }
+    let mut key = [0; 32];
+    key.copy_from_slice(&bytes);
+    Ok(key)
+}
+
+/// Encrypts `content` in the format read by the EncryptedFileSystem.
+///
+/// The nonce must never be used twice with the same key, e.g. use 12 random bytes.
+pub fn encrypt_file(key: &[u8; 32], nonce: [u8; 12], content: &[u8]) -> Vec<u8> {
+    let ciphertext = cipher(key)
+        .encrypt(GenericArray::from_slice(&nonce), content)
+        .expect("encrypting to a vec doesn't fail");
+    let mut encrypted = nonce.to_vec();
+    encrypted.extend(ciphertext);
+    encrypted
+}
+
+fn cipher(key: &[u8; 32]) -> Aes256Gcm {
+    Aes256Gcm::new(GenericArray::from_slice(key))
+}
+
+/// Returns the size of the content of an encrypted file of the given size.
+fn content_size(size: u64) -> Result<u64, Error> {
+    size.checked_sub(OVERHEAD)
+        .ok_or_else(|| Error::backend("file too short to be encrypted"))
+}
+
+impl<T: FileSystem> FileSystem for EncryptedFileSystem<T> {
+    fn is_file(&self, path: &Path) -> bool {
+        self.inner.is_file(path)
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        self.inner.last_modified(path)
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        content_size(self.inner.size(path)?)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        let mut reader = Cursor::new(self.decrypt(path)?);
+        reader.set_position(start.unwrap_or(0));
+        Ok(Box::new(reader))
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.inner.path_valid(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        Ok(Box::new(Cursor::new(self.decrypt(path)?)))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.inner.is_dir(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        let mut entries = self.inner.read_dir(path)?;
+        for entry in entries.iter_mut().filter(|entry| !entry.is_dir) {
+            entry.size = entry.size.saturating_sub(OVERHEAD);
+        }
+        Ok(entries)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.inner.etag(path)
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.inner.content_type(path)
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        self.inner.changed(path)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        let mut metadata = self.inner.metadata(path)?;
+        if metadata.is_file {
+            metadata.size = content_size(metadata.size)?;
+        }
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encrypt_file, EncryptedFileSystem};
+    use fs::{FileSystem, InMemoryFileSystem};
+    use std::io::Read;
+    use std::path::Path;
+
+    #[test]
+    fn test_encrypted_file_system() {
+        let old_key = [1u8; 32];
+        let new_key = [2u8; 32];
+        let mut files = InMemoryFileSystem::new();
+        files.insert("font.woff2", encrypt_file(&new_key, [3u8; 12], b"wOF2 font"));
+        files.insert("old.woff2", encrypt_file(&old_key, [4u8; 12], b"old font"));
+        files.insert("plain.txt", "not encrypted, but long enough to pass as such");
+        let fs = EncryptedFileSystem::new(files, new_key).key(old_key);
+
+        assert_eq!(fs.size(Path::new("font.woff2")).unwrap(), 9);
+        assert_eq!(fs.metadata(Path::new("font.woff2")).unwrap().size, 9);
+
+        let mut s = String::new();
+        fs.open(Path::new("font.woff2"), Some(5))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "font");
+
+        let mut s = String::new();
+        fs.open(Path::new("old.woff2"), None)
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "old font");
+
+        assert!(fs.open(Path::new("plain.txt"), None).is_err());
+        assert!(fs.open(Path::new("missing.txt"), None).is_err());
+    }
+}
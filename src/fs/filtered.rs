@@ -0,0 +1,241 @@
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use path;
+use std::io::Read;
+use std::path::Path;
+use std::time::SystemTime;
+use {Error, Glob};
+
+/// Hides the paths of another FileSystem which don't pass allow and deny globs.
+///
+/// A file is visible if it matches none of the deny globs and, if there are allow globs, at
+/// least one of them. A directory is visible unless it matches a deny glob, e.g. `internal/**`
+/// hides the directory `internal` as well. Everything inside of a hidden directory is hidden,
+/// so `internal` alone hides `internal/secret.txt` too. Hidden paths don't exist as far as
+/// every user of the FileSystem is concerned, including directory listings.
+///
+/// Globs are matched against paths relative to the root of the FileSystem.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{GlobFilteredFileSystem, LocalFileSystem};
+/// use rocket_static_fs::Glob;
+///
+/// let fs = GlobFilteredFileSystem::new(LocalFileSystem::new("public"))
+///     .allow(Glob::new("**/*.{html,css,js,png}").unwrap())
+///     .deny(Glob::new("drafts/**").unwrap());
+/// ```
+pub struct GlobFilteredFileSystem<T> {
+    inner: T,
+    allow: Vec<Glob>,
+    deny: Vec<Glob>,
+}
+
+impl<T: FileSystem> GlobFilteredFileSystem<T> {
+    pub fn new(inner: T) -> Self {
+        GlobFilteredFileSystem {
+            inner,
+            allow: Vec::new(),
+            deny: Vec::new(),
+        }
+    }
+
+    /// Adds a glob files have to match, any of the allow globs will do.
+    pub fn allow(mut self, glob: Glob) -> Self {
+        self.allow.push(glob);
+        self
+    }
+
+    /// Adds a glob hiding files and directories matching it.
+    pub fn deny(mut self, glob: Glob) -> Self {
+        self.deny.push(glob);
+        self
+    }
+
+    fn file_visible(&self, path: &Path) -> bool {
+        let path = match path.to_str() {
+            Some(path) => path,
+            None => return false,
+        };
+        !self.denied(path)
+            && !self.parent_denied(path)
+            && (self.allow.is_empty() || self.allow.iter().any(|g| g.is_match(path)))
+    }
+
+    fn dir_visible(&self, path: &Path) -> bool {
+        match path.to_str() {
+            Some("") => true,
+            Some(path) => !self.denied(path) && !self.parent_denied(path),
+            None => false,
+        }
+    }
+
+    /// Returns true if any of the directories the path is in is denied.
+    fn parent_denied(&self, path: &str) -> bool {
+        path.match_indices('/').any(|(i, _)| self.denied(&path[..i]))
+    }
+
+    /// Returns true if the path or anything it contains is denied.
+    fn denied(&self, path: &str) -> bool {
+        let contents = format!("{}/", path);
+        self.deny
+            .iter()
+            .any(|glob| glob.is_match(path) || glob.is_match(&contents))
+    }
+
+    /// Returns the path if it's a visible file.
+    fn file<'a>(&self, path: &'a Path) -> Result<&'a Path, Error> {
+        if self.file_visible(path) {
+            Ok(path)
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+}
+
+impl<T: FileSystem> FileSystem for GlobFilteredFileSystem<T> {
+    fn is_file(&self, path: &Path) -> bool {
+        self.file_visible(path) && self.inner.is_file(path)
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        if self.file_visible(path) || (self.dir_visible(path) && self.inner.is_dir(path)) {
+            self.inner.last_modified(path)
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        self.inner.size(self.file(path)?)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        self.inner.open(self.file(path)?, start)
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.inner.path_valid(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        self.inner.open_seek(self.file(path)?)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.dir_visible(path) && self.inner.is_dir(path)
+    }
+
+    fn read_dir(&self, dir: &Path) -> Result<Vec<DirEntry>, Error> {
+        if !self.dir_visible(dir) {
+            return Err(Error::NotFound);
+        }
+
+        let dir_str = dir.to_str().ok_or(Error::NotFound)?;
+        let entries = self.inner.read_dir(dir)?;
+        Ok(entries
+            .into_iter()
+            .filter(|entry| {
+                let child = path::join(dir_str, &entry.name);
+                if entry.is_dir {
+                    self.dir_visible(Path::new(&child))
+                } else {
+                    self.file_visible(Path::new(&child))
+                }
+            })
+            .collect())
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.file(path).ok().and_then(|path| self.inner.etag(path))
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.file(path)
+            .ok()
+            .and_then(|path| self.inner.content_type(path))
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        self.file(path)
+            .ok()
+            .and_then(|path| self.inner.open_encoded(path, encoding))
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        self.file(path)
+            .ok()
+            .and_then(|path| self.inner.as_bytes(path))
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        self.inner.changed(path)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        let metadata = self.inner.metadata(path)?;
+        let visible = if metadata.is_file {
+            self.file_visible(path)
+        } else {
+            self.dir_visible(path)
+        };
+        if visible {
+            Ok(metadata)
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GlobFilteredFileSystem;
+    use fs::{FileSystem, InMemoryFileSystem};
+    use std::path::Path;
+    use Glob;
+
+    #[test]
+    fn test_glob_filtered_file_system() {
+        let mut files = InMemoryFileSystem::new();
+        files.insert("index.html", "");
+        files.insert("notes.txt", "");
+        files.insert("drafts/post.html", "");
+        files.insert("blog/post.html", "");
+        let fs = GlobFilteredFileSystem::new(files)
+            .allow(Glob::new("**/*.html").unwrap())
+            .deny(Glob::new("drafts/**").unwrap());
+
+        assert!(fs.is_file(Path::new("index.html")));
+        assert!(fs.is_file(Path::new("blog/post.html")));
+        assert!(!fs.is_file(Path::new("notes.txt")));
+        assert!(!fs.is_file(Path::new("drafts/post.html")));
+        assert!(!fs.is_dir(Path::new("drafts")));
+        assert!(fs.metadata(Path::new("notes.txt")).is_err());
+        assert!(fs.open(Path::new("notes.txt"), None).is_err());
+
+        let names: Vec<String> = fs.read_dir(Path::new(""))
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["blog", "index.html"]);
+    }
+
+    #[test]
+    fn test_denied_directory() {
+        let mut files = InMemoryFileSystem::new();
+        files.insert("index.html", "");
+        files.insert("drafts/post.html", "");
+        files.insert("drafts/old/post.html", "");
+        let fs = GlobFilteredFileSystem::new(files).deny(Glob::new("drafts").unwrap());
+
+        assert!(fs.is_file(Path::new("index.html")));
+        assert!(!fs.is_dir(Path::new("drafts")));
+        assert!(!fs.is_dir(Path::new("drafts/old")));
+        assert!(!fs.is_file(Path::new("drafts/post.html")));
+        assert!(!fs.is_file(Path::new("drafts/old/post.html")));
+        assert!(fs.open(Path::new("drafts/post.html"), None).is_err());
+        assert!(fs.metadata(Path::new("drafts/old")).is_err());
+        assert!(fs.read_dir(Path::new("drafts/old")).is_err());
+    }
+}
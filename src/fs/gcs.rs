@@ -0,0 +1,138 @@
+use chrono::Utc;
+use fs::remote::{self, ObjectStore};
+use fs::token::{self, TokenCache};
+use jsonwebtoken::{self, Algorithm, EncodingKey, Header};
+use reqwest::header::{AUTHORIZATION, RANGE};
+use reqwest::{Client, Method, Response};
+use serde_json::{self, Value};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+use Error;
+
+const ENDPOINT: &str = "https://storage.googleapis.com";
+
+/// The OAuth scope requested for service accounts, reading is all we do.
+const SCOPE: &str = "https://www.googleapis.com/auth/devstorage.read_only";
+
+/// Provides a FileSystem serving the objects of a Google Cloud Storage bucket.
+///
+/// Works like the S3FileSystem: paths are mapped to object names, optionally below a prefix,
+/// metadata is fetched with HEAD requests and ranges are requested from GCS directly. Requests
+/// are authorized with a service account key if one is set, otherwise the bucket has to be
+/// public. Listings aren't supported.
+///
+/// Every lookup is a request to GCS, see the `fs` module on caching. Requires the `gcs`
+/// feature.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::GcsFileSystem;
+///
+/// let fs = GcsFileSystem::new("my-assets")
+///     .service_account_key("service-account.json")
+///     .unwrap();
+/// ```
+pub struct GcsFileSystem {
+    client: Client,
+    bucket: String,
+    prefix: String,
+    account: Option<ServiceAccount>,
+    token: TokenCache,
+}
+
+/// The parts of a service account key we need to get access tokens.
+struct ServiceAccount {
+    client_email: String,
+    private_key: EncodingKey,
+    token_uri: String,
+}
+
+impl GcsFileSystem {
+    /// Serves the bucket without authorization.
+    pub fn new(bucket: &str) -> Self {
+        GcsFileSystem {
+            client: Client::new(),
+            bucket: bucket.to_string(),
+            prefix: String::new(),
+            account: None,
+            token: TokenCache::new(),
+        }
+    }
+
+    /// Authorizes requests as the service account of the JSON key file at `path`.
+    pub fn service_account_key<P: AsRef<Path>>(self, path: P) -> Result<Self, Error> {
+        let mut json = String::new();
+        File::open(path)?.read_to_string(&mut json)?;
+        self.service_account_key_json(&json)
+    }
+
+    /// Authorizes requests as the service account of a JSON key, e.g. read from the
+    /// environment.
+    pub fn service_account_key_json(mut self, json: &str) -> Result<Self, Error> {
+        let key: Value = serde_json::from_str(json).map_err(|err| Error::backend(err.to_string()))?;
+        let field = |name: &str| {
+            key[name]
+                .as_str()
+                .ok_or_else(|| Error::backend(format!("service account key without {}", name)))
+        };
+
+        let private_key = EncodingKey::from_rsa_pem(field("private_key")?.as_bytes())
+            .map_err(|err| Error::backend(err.to_string()))?;
+        self.account = Some(ServiceAccount {
+            client_email: field("client_email")?.to_string(),
+            private_key,
+            token_uri: field("token_uri")?.to_string(),
+        });
+        Ok(self)
+    }
+
+    /// Serves only the objects below a prefix, e.g. `public` serves `public/index.html` as
+    /// `index.html`.
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.prefix = remote::normalize_prefix(prefix);
+        self
+    }
+
+    /// Exchanges a JWT signed with the key of the service account for an access token.
+    fn fetch_token(&self, account: &ServiceAccount) -> Result<(String, Duration), Error> {
+        let now = Utc::now().timestamp();
+        let claims = json!({
+            "iss": account.client_email,
+            "scope": SCOPE,
+            "aud": account.token_uri,
+            "iat": now,
+            "exp": now + 3600,
+        });
+        let assertion =
+            jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &account.private_key)
+                .map_err(|err| Error::backend(err.to_string()))?;
+
+        token::fetch_token(self.client.post(&account.token_uri).form(&[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            ("assertion", &assertion),
+        ]))
+    }
+}
+
+impl ObjectStore for GcsFileSystem {
+    /// Sends a request for the object at `path`, from `start` on if given.
+    fn send(&self, method: Method, path: &Path, start: Option<u64>) -> Result<Response, Error> {
+        let bucket_url = format!("{}/{}", ENDPOINT, self.bucket);
+        let url = remote::url(&bucket_url, &self.prefix, path)?;
+
+        let mut request = self.client.request(method, &url);
+        if let Some(start) = start {
+            request = request.header(RANGE, remote::range(start).as_str());
+        }
+        if let Some(ref account) = self.account {
+            let token = self.token.get(|| self.fetch_token(account))?;
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token).as_str());
+        }
+        remote::check(request.send())
+    }
+}
+
+object_file_system!(GcsFileSystem);
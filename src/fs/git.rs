@@ -0,0 +1,252 @@
+use fs::embedded::collect_dirs;
+use fs::{DirEntry, FileSystem, Metadata};
+use git2::{ObjectType, Oid, Repository, TreeWalkMode, TreeWalkResult};
+use std::collections::{BTreeSet, HashMap};
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use Error;
+
+/// Provides a FileSystem serving the files of a commit of a git repository.
+///
+/// The revision is resolved once, so the served content never changes. Any revision git
+/// understands will do, e.g. `main`, `v1.2.0` or a commit hash. A directory of the commit is
+/// served with `<revision>:<directory>`, e.g. `main:docs`. The repository may be bare.
+///
+/// The ETags of files are the IDs of their blobs and all files are dated to the commit.
+/// Requires the `git` feature.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::GitFileSystem;
+///
+/// let fs = GitFileSystem::open("/srv/git/docs.git", "main:site").unwrap();
+/// ```
+pub struct GitFileSystem {
+    repository: Mutex<Repository>,
+    files: HashMap<String, GitBlob>,
+    /// Maps the path of every directory to the names of its children, the root is "".
+    dirs: HashMap<String, BTreeSet<String>>,
+    committed: SystemTime,
+}
+
+struct GitBlob {
+    id: Oid,
+    size: u64,
+}
+
+impl GitFileSystem {
+    /// Opens the repository at `path` and indexes the tree of `revision`.
+    pub fn open<P: AsRef<Path>>(path: P, revision: &str) -> Result<Self, Error> {
+        let repository = Repository::open(path).map_err(git_error)?;
+        let (files, committed) = {
+            let tree = repository
+                .revparse_single(revision)
+                .and_then(|object| object.peel_to_tree())
+                .map_err(git_error)?;
+            let commit = revision.split(':').next().unwrap_or(revision);
+            let commit = repository
+                .revparse_single(commit)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(git_error)?;
+            let odb = repository.odb().map_err(git_error)?;
+
+            let mut files = HashMap::new();
+            let mut result = Ok(());
+            tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+                if entry.kind() != Some(ObjectType::Blob) {
+                    return TreeWalkResult::Ok;
+                }
+                let name = match entry.name() {
+                    Some(name) => name,
+                    None => return TreeWalkResult::Skip,
+                };
+                match odb.read_header(entry.id()) {
+                    Ok((size, _)) => {
+                        let blob = GitBlob {
+                            id: entry.id(),
+                            size: size as u64,
+                        };
+                        files.insert(format!("{}{}", dir, name), blob);
+                        TreeWalkResult::Ok
+                    }
+                    Err(err) => {
+                        result = Err(git_error(err));
+                        TreeWalkResult::Abort
+                    }
+                }
+            }).map_err(git_error)?;
+            result?;
+
+            let seconds = commit.time().seconds();
+            let committed = UNIX_EPOCH + Duration::from_secs(if seconds > 0 { seconds as u64 } else { 0 });
+            (files, committed)
+        };
+
+        Ok(GitFileSystem {
+            repository: Mutex::new(repository),
+            dirs: collect_dirs(files.keys()),
+            files,
+            committed,
+        })
+    }
+
+    fn blob(&self, path: &Path) -> Result<&GitBlob, Error> {
+        path.to_str()
+            .and_then(|path| self.files.get(path))
+            .ok_or(Error::NotFound)
+    }
+}
+
+fn git_error(err: ::git2::Error) -> Error {
+    Error::backend(err.message())
+}
+
+impl FileSystem for GitFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.blob(path).is_ok()
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        if self.is_file(path) || self.is_dir(path) {
+            Ok(self.committed)
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        Ok(self.blob(path)?.size)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        let id = self.blob(path)?.id;
+        let content = {
+            let repository = self.repository.lock().unwrap();
+            let blob = repository.find_blob(id).map_err(git_error)?;
+            blob.content().to_vec()
+        };
+        let mut reader = Cursor::new(content);
+        reader.set_position(start.unwrap_or(0));
+        Ok(Box::new(reader))
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.to_str()
+            .map_or(false, |path| self.dirs.contains_key(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        let dir = path.to_str().ok_or(Error::NotFound)?;
+        let children = self.dirs.get(dir).ok_or(Error::NotFound)?;
+
+        let entries = children
+            .iter()
+            .map(|name| {
+                let child = if dir.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{}", dir, name)
+                };
+                match self.files.get(&child) {
+                    Some(blob) => DirEntry {
+                        name: name.clone(),
+                        is_dir: false,
+                        size: blob.size,
+                        modified: Some(self.committed),
+                    },
+                    None => DirEntry {
+                        name: name.clone(),
+                        is_dir: true,
+                        size: 0,
+                        modified: Some(self.committed),
+                    },
+                }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.blob(path).ok().map(|blob| format!("\"{}\"", blob.id))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        if let Ok(blob) = self.blob(path) {
+            return Ok(Metadata {
+                size: blob.size,
+                modified: Some(self.committed),
+                is_file: true,
+                is_dir: false,
+            });
+        }
+
+        if self.is_dir(path) {
+            Ok(Metadata {
+                size: 0,
+                modified: Some(self.committed),
+                is_file: false,
+                is_dir: true,
+            })
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GitFileSystem;
+    use fs::FileSystem;
+    use git2::{Repository, Signature, Time};
+    use std::env;
+    use std::fs;
+    use std::io::Read;
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_git_file_system() {
+        let dir = env::temp_dir().join("rocket_static_fs_test_git");
+        let _ = fs::remove_dir_all(&dir);
+        let repository = Repository::init_bare(&dir).unwrap();
+
+        let index = repository.blob(b"<h1>Hello</h1>").unwrap();
+        let mut site = repository.treebuilder(None).unwrap();
+        site.insert("index.html", index, 0o100644).unwrap();
+        let site = site.write().unwrap();
+        let mut root = repository.treebuilder(None).unwrap();
+        root.insert("site", site, 0o040000).unwrap();
+        let root = repository.find_tree(root.write().unwrap()).unwrap();
+        let signature = Signature::new("Test", "test@example.com", &Time::new(1_500_000_000, 0))
+            .unwrap();
+        repository
+            .commit(Some("refs/heads/main"), &signature, &signature, "Initial", &root, &[])
+            .unwrap();
+
+        let fs = GitFileSystem::open(&dir, "main:site").unwrap();
+        assert!(fs.is_file(Path::new("index.html")));
+        assert!(!fs.is_file(Path::new("site/index.html")));
+        assert_eq!(fs.size(Path::new("index.html")).unwrap(), 14);
+        assert_eq!(
+            fs.last_modified(Path::new("index.html")).unwrap(),
+            UNIX_EPOCH + Duration::from_secs(1_500_000_000)
+        );
+        assert_eq!(fs.etag(Path::new("index.html")), Some(format!("\"{}\"", index)));
+
+        let mut s = String::new();
+        fs.open(Path::new("index.html"), Some(4))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "Hello</h1>");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use fs::remote::{self, ObjectStore};
+use fs::{FileSystem, Metadata};
+use reqwest::header::{HeaderValue, RANGE};
+use reqwest::{Client, Method, Response, Url};
+use std::io::Read;
+use std::path::Path;
+use std::time::SystemTime;
+use Error;
+
+/// Provides a FileSystem serving the files of an upstream HTTP(S) server.
+///
+/// Paths are appended to the base URL. Metadata is fetched with HEAD requests, files with GET
+/// requests, ranges are passed on to the upstream. ETags and MIME types of the upstream are
+/// passed on to clients. The upstream has to send `Content-Length` and `Last-Modified`
+/// headers. Listings aren't supported.
+///
+/// Every lookup is a request to the upstream, see the `fs` module on caching. Requires the
+/// `http` feature.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::HttpFileSystem;
+///
+/// let fs = HttpFileSystem::new("https://static.example.com/assets/")
+///     .unwrap()
+///     .header("Authorization", "Bearer secret")
+///     .unwrap();
+/// ```
+pub struct HttpFileSystem {
+    client: Client,
+    /// The base URL without trailing slash.
+    base_url: String,
+    headers: Vec<(&'static str, HeaderValue)>,
+}
+
+impl HttpFileSystem {
+    /// Serves the files below the given base URL.
+    pub fn new(base_url: &str) -> Result<Self, Error> {
+        let url = Url::parse(base_url).map_err(|err| Error::backend(err.to_string()))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(Error::backend(format!("unsupported scheme {}", url.scheme())));
+        }
+
+        Ok(HttpFileSystem {
+            client: Client::new(),
+            base_url: base_url.trim_right_matches('/').to_string(),
+            headers: Vec::new(),
+        })
+    }
+
+    /// Adds a header sent with every request to the upstream, e.g. for authorization.
+    pub fn header(mut self, name: &'static str, value: &str) -> Result<Self, Error> {
+        let value = HeaderValue::from_str(value).map_err(|err| Error::backend(err.to_string()))?;
+        self.headers.push((name, value));
+        Ok(self)
+    }
+
+    /// Returns the upstream URL of the file at `path`.
+    fn url(&self, path: &Path) -> Result<String, Error> {
+        remote::url(&self.base_url, "", path)
+    }
+}
+
+impl ObjectStore for HttpFileSystem {
+    /// Sends a request for the file at `path`, from `start` on if given.
+    fn send(&self, method: Method, path: &Path, start: Option<u64>) -> Result<Response, Error> {
+        let url = self.url(path)?;
+        let mut request = self.client.request(method, &url);
+        for &(name, ref value) in &self.headers {
+            request = request.header(name, value.clone());
+        }
+        if let Some(start) = start {
+            request = request.header(RANGE, remote::range(start).as_str());
+        }
+        remote::check(request.send())
+    }
+}
+
+impl FileSystem for HttpFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.head(path).is_ok()
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        Ok(self.head(path)?.modified)
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        Ok(self.head(path)?.size)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        remote::body(self.send(Method::GET, path, start)?, start)
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        remote::path_valid(path)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.head(path).ok().and_then(|file| file.etag)
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.head(path).ok().and_then(|file| file.content_type)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        Ok(self.head(path)?.metadata())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HttpFileSystem;
+    use fs::remote::RemoteFile;
+    use reqwest::header::{HeaderMap, HeaderValue, CONTENT_LENGTH, ETAG, LAST_MODIFIED};
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_http_file_system() {
+        assert!(HttpFileSystem::new("ftp://static.example.com").is_err());
+        assert!(HttpFileSystem::new("not a url").is_err());
+
+        let fs = HttpFileSystem::new("https://static.example.com/assets/").unwrap();
+        assert_eq!(
+            fs.url(Path::new("css/my app.css")).unwrap(),
+            "https://static.example.com/assets/css/my%20app.css"
+        );
+        assert!(fs.url(Path::new("")).is_err());
+        assert!(fs.header("Authorization", "Bearer\nsecret").is_err());
+    }
+
+    #[test]
+    fn test_remote_file_from_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_LENGTH, HeaderValue::from_static("12"));
+        assert!(RemoteFile::from_headers(&headers).is_err());
+
+        headers.insert(
+            LAST_MODIFIED,
+            HeaderValue::from_static("Thu, 01 Jan 1970 00:01:00 GMT"),
+        );
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+        let file = RemoteFile::from_headers(&headers).unwrap();
+        let meta = file.metadata();
+        assert!(meta.is_file);
+        assert_eq!(meta.size, 12);
+        assert_eq!(meta.modified, Some(UNIX_EPOCH + Duration::from_secs(60)));
+        assert_eq!(file.etag, Some("\"abc\"".to_string()));
+    }
+}
@@ -0,0 +1,218 @@
+use fs::{DirEntry, FileSystem, Metadata};
+use include_dir::{Dir, File};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::time::SystemTime;
+use Error;
+
+/// Provides a FileSystem serving a directory embedded with `include_dir!`.
+///
+/// Files are dated to their modification date recorded by include_dir, files without one and
+/// directories to the creation of the FileSystem, usually the start of the server, unless
+/// `default_modified` sets another date. ETags are hashes of the contents and are computed
+/// once on creation. Requires the `include_dir` feature.
+///
+/// # Example
+///
+/// ```
+/// #[macro_use]
+/// extern crate include_dir;
+/// extern crate rocket_static_fs;
+///
+/// use include_dir::Dir;
+/// use rocket_static_fs::fs::IncludeDirFileSystem;
+///
+/// static ASSETS: Dir = include_dir!("$CARGO_MANIFEST_DIR/testdata");
+///
+/// fn main() {
+///     let fs = IncludeDirFileSystem::new(&ASSETS);
+/// }
+/// ```
+pub struct IncludeDirFileSystem {
+    dir: &'static Dir<'static>,
+    etags: HashMap<String, String>,
+    default_modified: SystemTime,
+}
+
+impl IncludeDirFileSystem {
+    pub fn new(dir: &'static Dir<'static>) -> Self {
+        let mut etags = HashMap::new();
+        collect_etags(dir, &mut etags);
+        IncludeDirFileSystem {
+            dir,
+            etags,
+            default_modified: SystemTime::now(),
+        }
+    }
+
+    /// Dates directories and files without a recorded modification date to the given time,
+    /// e.g. the build of the binary, instead of the creation of the FileSystem.
+    pub fn default_modified(mut self, modified: SystemTime) -> Self {
+        self.default_modified = modified;
+        self
+    }
+
+    fn file(&self, path: &Path) -> Result<&'static File<'static>, Error> {
+        if path.as_os_str().is_empty() {
+            return Err(Error::NotFound);
+        }
+        self.dir.get_file(path).ok_or(Error::NotFound)
+    }
+
+    fn subdir(&self, path: &Path) -> Option<&'static Dir<'static>> {
+        if path.as_os_str().is_empty() {
+            Some(self.dir)
+        } else {
+            self.dir.get_dir(path)
+        }
+    }
+
+    fn modified(&self, file: &File) -> SystemTime {
+        file.metadata()
+            .map(|metadata| metadata.modified())
+            .unwrap_or(self.default_modified)
+    }
+}
+
+impl FileSystem for IncludeDirFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.file(path).is_ok()
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        match self.file(path) {
+            Ok(file) => Ok(self.modified(file)),
+            Err(_) if self.is_dir(path) => Ok(self.default_modified),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        Ok(self.file(path)?.contents().len() as u64)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        let mut reader = Cursor::new(self.file(path)?.contents());
+        reader.set_position(start.unwrap_or(0));
+        Ok(Box::new(reader))
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        self.file(path).ok().map(|file| file.contents())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.subdir(path).is_some()
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        let dir = self.subdir(path).ok_or(Error::NotFound)?;
+        let name = |path: &Path| {
+            path.file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+        };
+
+        let mut entries: Vec<DirEntry> = dir.dirs()
+            .map(|sub| DirEntry {
+                name: name(sub.path()),
+                is_dir: true,
+                size: 0,
+                modified: Some(self.default_modified),
+            })
+            .chain(dir.files().map(|file| DirEntry {
+                name: name(file.path()),
+                is_dir: false,
+                size: file.contents().len() as u64,
+                modified: Some(self.modified(file)),
+            }))
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.etags.get(path.to_str()?).cloned()
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        if let Ok(file) = self.file(path) {
+            return Ok(Metadata {
+                size: file.contents().len() as u64,
+                modified: Some(self.modified(file)),
+                is_file: true,
+                is_dir: false,
+            });
+        }
+
+        if self.is_dir(path) {
+            Ok(Metadata {
+                size: 0,
+                modified: Some(self.default_modified),
+                is_file: false,
+                is_dir: true,
+            })
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+}
+
+/// Hashes the contents of all files in the directory and its subdirectories.
+fn collect_etags(dir: &Dir, etags: &mut HashMap<String, String>) {
+    for file in dir.files() {
+        if let Some(path) = file.path().to_str() {
+            let mut hasher = DefaultHasher::new();
+            file.contents().hash(&mut hasher);
+            etags.insert(path.replace('\\', "/"), format!("\"{:016x}\"", hasher.finish()));
+        }
+    }
+    for sub in dir.dirs() {
+        collect_etags(sub, etags);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IncludeDirFileSystem;
+    use fs::FileSystem;
+    use include_dir;
+    use include_dir::{include_dir, Dir};
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    static ASSETS: Dir = include_dir!("$CARGO_MANIFEST_DIR/testdata");
+
+    #[test]
+    fn test_include_dir_file_system() {
+        let fs = IncludeDirFileSystem::new(&ASSETS);
+
+        assert_eq!(fs.as_bytes(Path::new("hello.txt")), Some(&b"Hello World!"[..]));
+        assert_eq!(fs.size(Path::new("inner/other.txt")).unwrap(), 6);
+        assert!(!fs.is_file(Path::new("")));
+        assert!(!fs.is_file(Path::new("missing.txt")));
+        assert!(fs.last_modified(Path::new("hello.txt")).unwrap() > UNIX_EPOCH);
+        assert!(fs.last_modified(Path::new("inner")).unwrap() > UNIX_EPOCH);
+
+        assert!(fs.is_dir(Path::new("")));
+        assert!(fs.metadata(Path::new("inner")).unwrap().is_dir);
+        let entries = fs.read_dir(Path::new("")).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["hello.txt", "inner", "yyy.txt", "zzz.txt"]);
+        assert!(entries[1].is_dir);
+
+        assert!(fs.etag(Path::new("inner/other.txt")).unwrap().starts_with('"'));
+        assert_ne!(fs.etag(Path::new("hello.txt")), fs.etag(Path::new("yyy.txt")));
+        assert_eq!(fs.etag(Path::new("inner")), None);
+
+        let built = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        let fs = IncludeDirFileSystem::new(&ASSETS).default_modified(built);
+        assert_eq!(fs.last_modified(Path::new("inner")).unwrap(), built);
+    }
+}
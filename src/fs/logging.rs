@@ -0,0 +1,245 @@
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use log::Level;
+use std::fmt::Display;
+use std::io::Read;
+use std::path::Path;
+use std::time::{Instant, SystemTime};
+use Error;
+
+/// The target of `log` records written by the LoggingFileSystem.
+const LOG_TARGET: &str = "rocket_static_fs::fs";
+
+/// Logs every call to another FileSystem.
+///
+/// Each call is logged with the operation, the path, the result and how long it took, with the
+/// target `rocket_static_fs::fs` and level `debug` by default. Useful to see what the server
+/// asks of a misbehaving backend.
+///
+/// # Example
+///
+/// ```
+/// extern crate log;
+/// extern crate rocket_static_fs;
+///
+/// use rocket_static_fs::fs::{LocalFileSystem, LoggingFileSystem};
+///
+/// fn main() {
+///     let fs = LoggingFileSystem::new(LocalFileSystem::new("static"))
+///         .name("assets")
+///         .level(log::Level::Info);
+/// }
+/// ```
+pub struct LoggingFileSystem<T> {
+    inner: T,
+    name: String,
+    level: Level,
+}
+
+impl<T: FileSystem> LoggingFileSystem<T> {
+    pub fn new(inner: T) -> Self {
+        LoggingFileSystem {
+            inner,
+            name: "fs".to_string(),
+            level: Level::Debug,
+        }
+    }
+
+    /// Sets the name logged with every call, to tell several FileSystems apart.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Sets the level of the log records, defaults to `debug`.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Calls the inner FileSystem and logs the call with the result as described by `describe`.
+    fn call<R, F, D>(&self, operation: &str, path: &Path, call: F, describe: D) -> R
+    where
+        F: FnOnce(&T) -> R,
+        D: FnOnce(&R) -> String,
+    {
+        if !log_enabled!(target: LOG_TARGET, self.level) {
+            return call(&self.inner);
+        }
+
+        let start = Instant::now();
+        let result = call(&self.inner);
+        let duration = start.elapsed();
+        let duration_ms =
+            duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1e6;
+        log!(
+            target: LOG_TARGET,
+            self.level,
+            "{} {} {:?} -> {} ({:.3} ms)",
+            self.name,
+            operation,
+            path,
+            describe(&result),
+            duration_ms
+        );
+        result
+    }
+}
+
+/// Describes a result by its value.
+fn value<T: Display>(result: &Result<T, Error>) -> String {
+    match *result {
+        Ok(ref value) => value.to_string(),
+        Err(ref err) => format!("error: {}", err),
+    }
+}
+
+/// Describes a result without its value.
+fn ok<T>(result: &Result<T, Error>) -> String {
+    match *result {
+        Ok(_) => "ok".to_string(),
+        Err(ref err) => format!("error: {}", err),
+    }
+}
+
+impl<T: FileSystem> FileSystem for LoggingFileSystem<T> {
+    fn is_file(&self, path: &Path) -> bool {
+        self.call("is_file", path, |fs| fs.is_file(path), |r| r.to_string())
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        self.call(
+            "last_modified",
+            path,
+            |fs| fs.last_modified(path),
+            |r| match *r {
+                Ok(ref modified) => format!("{:?}", modified),
+                Err(ref err) => format!("error: {}", err),
+            },
+        )
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        self.call("size", path, |fs| fs.size(path), value)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        let operation = match start {
+            Some(start) => format!("open@{}", start),
+            None => "open".to_string(),
+        };
+        self.call(&operation, path, |fs| fs.open(path, start), ok)
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.call("path_valid", path, |fs| fs.path_valid(path), |r| r.to_string())
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        self.call("open_seek", path, |fs| fs.open_seek(path), ok)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.call("is_dir", path, |fs| fs.is_dir(path), |r| r.to_string())
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        self.call(
+            "read_dir",
+            path,
+            |fs| fs.read_dir(path),
+            |r| match *r {
+                Ok(ref entries) => format!("{} entries", entries.len()),
+                Err(ref err) => format!("error: {}", err),
+            },
+        )
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.call("etag", path, |fs| fs.etag(path), |r| format!("{:?}", r))
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.call(
+            "content_type",
+            path,
+            |fs| fs.content_type(path),
+            |r| format!("{:?}", r),
+        )
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        self.call(
+            &format!("open_encoded({})", encoding),
+            path,
+            |fs| fs.open_encoded(path, encoding),
+            |r| if r.is_some() { "some" } else { "none" }.to_string(),
+        )
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        self.call(
+            "as_bytes",
+            path,
+            |fs| fs.as_bytes(path),
+            |r| if r.is_some() { "some" } else { "none" }.to_string(),
+        )
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        self.call(
+            "changed",
+            path.unwrap_or_else(|| Path::new("*")),
+            |fs| fs.changed(path),
+            |_| "ok".to_string(),
+        )
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        self.call(
+            "metadata",
+            path,
+            |fs| fs.metadata(path),
+            |r| match *r {
+                Ok(ref meta) => format!("{:?}", meta),
+                Err(ref err) => format!("error: {}", err),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ok, value, LoggingFileSystem};
+    use fs::{FileSystem, InMemoryFileSystem};
+    use log::Level;
+    use std::io::Read;
+    use std::path::Path;
+    use Error;
+
+    #[test]
+    fn test_logging_file_system() {
+        let mut files = InMemoryFileSystem::new();
+        files.insert("index.html", "<h1>Hello</h1>");
+        let fs = LoggingFileSystem::new(files)
+            .name("assets")
+            .level(Level::Info);
+
+        assert!(fs.is_file(Path::new("index.html")));
+        assert_eq!(fs.size(Path::new("index.html")).unwrap(), 14);
+        let mut s = String::new();
+        fs.open(Path::new("index.html"), Some(4))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "Hello</h1>");
+        assert!(fs.metadata(Path::new("missing.html")).is_err());
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(value(&Ok::<u64, Error>(14)), "14");
+        assert_eq!(ok(&Ok::<u64, Error>(14)), "ok");
+        let err = Err::<u64, Error>(Error::NotFound);
+        assert_eq!(value(&err), format!("error: {}", Error::NotFound));
+    }
+}
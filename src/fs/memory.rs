@@ -0,0 +1,270 @@
+use fs::embedded::collect_dirs;
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+use Error;
+
+/// Provides a FileSystem holding all files in memory.
+///
+/// Useful for tests, assets generated at startup and tiny sites. Directories are derived from
+/// the paths of the files.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::InMemoryFileSystem;
+///
+/// let mut fs = InMemoryFileSystem::new();
+/// fs.insert("index.html", "<h1>Hello</h1>");
+/// fs.insert("js/app.mjs", "console.log('hello');");
+/// fs.set_mime_type("js/app.mjs", "text/javascript");
+/// ```
+pub struct InMemoryFileSystem {
+    files: HashMap<String, MemoryFile>,
+    /// Maps the path of every directory to the names of its children, the root is "".
+    dirs: HashMap<String, BTreeSet<String>>,
+}
+
+struct MemoryFile {
+    data: Arc<[u8]>,
+    modified: SystemTime,
+    etag: String,
+    mime: Option<String>,
+}
+
+impl InMemoryFileSystem {
+    pub fn new() -> Self {
+        InMemoryFileSystem {
+            files: HashMap::new(),
+            dirs: collect_dirs(&[]),
+        }
+    }
+
+    /// Adds a file, replacing any file at the same path.
+    ///
+    /// The path is relative to the root, e.g. `css/app.css`. The modification date is set to
+    /// now.
+    pub fn insert<P, D>(&mut self, path: P, data: D)
+    where
+        P: AsRef<str>,
+        D: Into<Vec<u8>>,
+    {
+        let data: Vec<u8> = data.into();
+        let mut hasher = DefaultHasher::new();
+        data.hash(&mut hasher);
+
+        let file = MemoryFile {
+            data: Arc::from(data),
+            modified: SystemTime::now(),
+            etag: format!("\"{:016x}\"", hasher.finish()),
+            mime: None,
+        };
+        self.files.insert(normalize(path.as_ref()), file);
+        self.dirs = collect_dirs(self.files.keys());
+    }
+
+    /// Sets the modification date of a file, returns false if there's no such file.
+    pub fn set_modified(&mut self, path: &str, modified: SystemTime) -> bool {
+        match self.files.get_mut(&normalize(path)) {
+            Some(file) => {
+                file.modified = modified;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sets the MIME type of a file, returns false if there's no such file.
+    ///
+    /// Files without a MIME type are served with the one guessed from their extension.
+    pub fn set_mime_type(&mut self, path: &str, mime: &str) -> bool {
+        match self.files.get_mut(&normalize(path)) {
+            Some(file) => {
+                file.mime = Some(mime.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn file(&self, path: &Path) -> Result<&MemoryFile, Error> {
+        path.to_str()
+            .and_then(|path| self.files.get(path))
+            .ok_or(Error::NotFound)
+    }
+}
+
+impl Default for InMemoryFileSystem {
+    fn default() -> Self {
+        InMemoryFileSystem::new()
+    }
+}
+
+impl<S: AsRef<str>, D: Into<Vec<u8>>> From<HashMap<S, D>> for InMemoryFileSystem {
+    fn from(files: HashMap<S, D>) -> Self {
+        let mut fs = InMemoryFileSystem::new();
+        for (path, data) in files {
+            fs.insert(path, data);
+        }
+        fs
+    }
+}
+
+impl FileSystem for InMemoryFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.file(path).is_ok()
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        Ok(self.file(path)?.modified)
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        Ok(self.file(path)?.data.len() as u64)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        let mut reader = Cursor::new(self.file(path)?.data.clone());
+        if let Some(start) = start {
+            reader.seek(SeekFrom::Start(start))?;
+        }
+        Ok(Box::new(reader))
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        Ok(Box::new(Cursor::new(self.file(path)?.data.clone())))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.to_str()
+            .map_or(false, |path| self.dirs.contains_key(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        let dir = path.to_str().ok_or(Error::NotFound)?;
+        let children = self.dirs.get(dir).ok_or(Error::NotFound)?;
+
+        let entries = children
+            .iter()
+            .map(|name| {
+                let child = if dir.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{}", dir, name)
+                };
+                match self.files.get(&child) {
+                    Some(file) => DirEntry {
+                        name: name.clone(),
+                        is_dir: false,
+                        size: file.data.len() as u64,
+                        modified: Some(file.modified),
+                    },
+                    None => DirEntry {
+                        name: name.clone(),
+                        is_dir: true,
+                        size: 0,
+                        modified: None,
+                    },
+                }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.file(path).ok().map(|file| file.etag.clone())
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.file(path).ok().and_then(|file| file.mime.clone())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        if let Ok(file) = self.file(path) {
+            return Ok(Metadata {
+                size: file.data.len() as u64,
+                modified: Some(file.modified),
+                is_file: true,
+                is_dir: false,
+            });
+        }
+
+        if self.is_dir(path) {
+            Ok(Metadata {
+                size: 0,
+                modified: None,
+                is_file: false,
+                is_dir: true,
+            })
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+}
+
+/// Strips leading slashes, so `/index.html` and `index.html` are the same file.
+fn normalize(path: &str) -> String {
+    path.trim_left_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InMemoryFileSystem;
+    use fs::FileSystem;
+    use std::collections::HashMap;
+    use std::io::Read;
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_in_memory_file_system() {
+        let mut files = HashMap::new();
+        files.insert("index.html", "<h1>Hello</h1>");
+        files.insert("/js/app.mjs", "hello");
+        let mut fs = InMemoryFileSystem::from(files);
+
+        assert!(fs.is_file(Path::new("index.html")));
+        assert!(fs.is_file(Path::new("js/app.mjs")));
+        assert!(fs.is_dir(Path::new("")));
+        assert!(fs.is_dir(Path::new("js")));
+        assert!(!fs.is_file(Path::new("js")));
+        assert_eq!(fs.size(Path::new("js/app.mjs")).unwrap(), 5);
+
+        let mut s = String::new();
+        fs.open(Path::new("js/app.mjs"), Some(2))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "llo");
+
+        let names: Vec<String> = fs.read_dir(Path::new(""))
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["index.html", "js"]);
+
+        let modified = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        assert!(fs.set_modified("js/app.mjs", modified));
+        assert!(fs.set_mime_type("js/app.mjs", "text/javascript"));
+        assert!(!fs.set_mime_type("missing.txt", "text/plain"));
+        assert_eq!(fs.last_modified(Path::new("js/app.mjs")).unwrap(), modified);
+        assert_eq!(
+            fs.content_type(Path::new("js/app.mjs")),
+            Some("text/javascript".to_string())
+        );
+
+        let etag = fs.etag(Path::new("index.html")).unwrap();
+        fs.insert("index.html", "<h1>Changed</h1>");
+        assert_ne!(fs.etag(Path::new("index.html")), Some(etag));
+    }
+}
@@ -0,0 +1,156 @@
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use metrics::{self, Metrics};
+use std::io::Read;
+use std::path::Path;
+use std::time::{Instant, SystemTime};
+use Error;
+
+/// Records the calls to another FileSystem in the metrics of the crate.
+///
+/// Every call is counted and timed, labeled with the name of the backend and the operation,
+/// see `Metrics`. Compare the latency of the backend with the duration of requests to tell
+/// slow backends from server overhead. Requires the `metrics` feature.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{LocalFileSystem, MetricsFileSystem};
+/// use rocket_static_fs::{Metrics, StaticFileServer};
+///
+/// let metrics = Metrics::new();
+/// let fs = MetricsFileSystem::new(LocalFileSystem::new("static"), "local", &metrics);
+/// let server = StaticFileServer::builder(fs).metrics(metrics).build();
+/// ```
+pub struct MetricsFileSystem<T> {
+    inner: T,
+    backend: String,
+    metrics: Metrics,
+}
+
+impl<T: FileSystem> MetricsFileSystem<T> {
+    /// Records calls to `inner` in `metrics`, labeled with `backend`.
+    pub fn new(inner: T, backend: &str, metrics: &Metrics) -> Self {
+        MetricsFileSystem {
+            inner,
+            backend: backend.to_string(),
+            metrics: metrics.clone(),
+        }
+    }
+
+    /// Calls the inner FileSystem and records the call with the result label of `result`.
+    fn call<R, F>(&self, operation: &str, call: F, result: fn(&R) -> &'static str) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        let start = Instant::now();
+        let value = call(&self.inner);
+        metrics::record_backend_call(
+            &self.metrics,
+            &self.backend,
+            operation,
+            result(&value),
+            start.elapsed(),
+        );
+        value
+    }
+}
+
+fn outcome<T>(result: &Result<T, Error>) -> &'static str {
+    match *result {
+        Ok(_) => "ok",
+        Err(Error::NotFound) => "not_found",
+        Err(_) => "error",
+    }
+}
+
+fn ok<T>(_: &T) -> &'static str {
+    "ok"
+}
+
+impl<T: FileSystem> FileSystem for MetricsFileSystem<T> {
+    fn is_file(&self, path: &Path) -> bool {
+        self.call("is_file", |fs| fs.is_file(path), ok)
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        self.call("last_modified", |fs| fs.last_modified(path), outcome)
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        self.call("size", |fs| fs.size(path), outcome)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        self.call("open", |fs| fs.open(path, start), outcome)
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.call("path_valid", |fs| fs.path_valid(path), ok)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        self.call("open_seek", |fs| fs.open_seek(path), outcome)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.call("is_dir", |fs| fs.is_dir(path), ok)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        self.call("read_dir", |fs| fs.read_dir(path), outcome)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.call("etag", |fs| fs.etag(path), ok)
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.call("content_type", |fs| fs.content_type(path), ok)
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        self.call("open_encoded", |fs| fs.open_encoded(path, encoding), ok)
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        self.call("as_bytes", |fs| fs.as_bytes(path), ok)
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        self.inner.changed(path)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        self.call("metadata", |fs| fs.metadata(path), outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MetricsFileSystem;
+    use fs::{FileSystem, InMemoryFileSystem};
+    use std::path::Path;
+    use Metrics;
+
+    #[test]
+    fn test_metrics_file_system() {
+        let mut files = InMemoryFileSystem::new();
+        files.insert("index.html", "<h1>Hello</h1>");
+        let metrics = Metrics::new();
+        let fs = MetricsFileSystem::new(files, "memory", &metrics);
+
+        assert_eq!(fs.size(Path::new("index.html")).unwrap(), 14);
+        assert!(fs.metadata(Path::new("missing.html")).is_err());
+
+        let text = metrics.render();
+        assert!(text.contains(
+            r#"static_fs_backend_calls_total{backend="memory",operation="size",result="ok"} 1"#
+        ));
+        assert!(text.contains(
+            r#"static_fs_backend_calls_total{backend="memory",operation="metadata",result="not_found"} 1"#
+        ));
+        assert!(text.contains(
+            r#"static_fs_backend_duration_seconds_count{backend="memory",operation="size"} 1"#
+        ));
+    }
+}
@@ -1,27 +1,310 @@
 //! Includes the FileSystem trait and built-in implementations.
+//!
+//! The FileSystems fetching files over the network, `S3FileSystem`, `GcsFileSystem`,
+//! `AzureBlobFileSystem` and `HttpFileSystem`, make a request for every lookup. You probably
+//! want to put a `CachingFileSystem` or `StatCacheFileSystem` in front of them.
 
-use std::error::Error;
-use std::fs::File;
+use Error;
+use std::fs::{self, File};
 use std::io::SeekFrom;
 use std::io::{Read, Seek};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::SystemTime;
 
+// Comes first so the backends can use its macro
+#[cfg(any(feature = "s3", feature = "gcs", feature = "azure", feature = "http"))]
+#[macro_use]
+mod remote;
+
+#[cfg(feature = "azure")]
+mod azure;
+mod cache;
+mod chain;
 mod embedded;
+#[cfg(feature = "encryption")]
+mod encrypted;
+mod filtered;
+#[cfg(feature = "gcs")]
+mod gcs;
+#[cfg(feature = "git")]
+mod git;
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "include_dir")]
+mod include_dir;
+mod logging;
+mod memory;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod mount;
+mod overlay;
+#[cfg(any(feature = "tar", feature = "zip"))]
+mod reader;
+#[cfg(feature = "rust-embed")]
+mod rust_embed;
+#[cfg(feature = "s3")]
+mod s3;
+#[cfg(feature = "sftp")]
+mod sftp;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod stat_cache;
+#[cfg(feature = "tar")]
+mod tar;
+pub mod testing;
+#[cfg(any(feature = "gcs", feature = "azure"))]
+mod token;
+#[cfg(feature = "watch")]
+mod watch;
+#[cfg(feature = "zip")]
+mod zip;
 
+#[cfg(feature = "azure")]
+pub use self::azure::AzureBlobFileSystem;
+pub use self::cache::{CachingFileSystem, HotCache};
+pub use self::chain::ChainedFileSystem;
+pub use self::embedded::create_generated_source_from_dir;
+pub use self::embedded::create_package_for_build_script;
 pub use self::embedded::create_package_from_dir;
+pub use self::embedded::create_package_from_dir_filtered;
+pub use self::embedded::create_package_from_dir_with;
+pub use self::embedded::write_generated_source;
 pub use self::embedded::write_package;
-pub use self::embedded::EmbeddedFileSystem;
+pub use self::embedded::write_package_streaming;
+pub use self::embedded::write_package_with;
+pub use self::embedded::{
+    EmbeddedFileSystem, Package, PackageCompression, PackageEntry, PackageOptions,
+};
+pub use self::embedded::{GeneratedFile, GeneratedFileSystem};
+pub use self::embedded::{PackageChanges, Packager};
+#[cfg(feature = "encryption")]
+pub use self::encrypted::{encrypt_file, EncryptedFileSystem};
+pub use self::filtered::GlobFilteredFileSystem;
+#[cfg(feature = "gcs")]
+pub use self::gcs::GcsFileSystem;
+#[cfg(feature = "git")]
+pub use self::git::GitFileSystem;
+#[cfg(feature = "http")]
+pub use self::http::HttpFileSystem;
+#[cfg(feature = "include_dir")]
+pub use self::include_dir::IncludeDirFileSystem;
+pub use self::logging::LoggingFileSystem;
+pub use self::memory::InMemoryFileSystem;
+#[cfg(feature = "metrics")]
+pub use self::metrics::MetricsFileSystem;
+pub use self::mount::{EmptyFileSystem, MountedFileSystem};
+pub use self::overlay::OverlayFileSystem;
+#[cfg(feature = "rust-embed")]
+pub use self::rust_embed::RustEmbedFileSystem;
+#[cfg(feature = "s3")]
+pub use self::s3::S3FileSystem;
+#[cfg(feature = "sftp")]
+pub use self::sftp::SftpFileSystem;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteFileSystem;
+pub use self::stat_cache::StatCacheFileSystem;
+#[cfg(feature = "tar")]
+pub use self::tar::TarFileSystem;
+#[cfg(feature = "watch")]
+pub use self::watch::WatchingFileSystem;
+#[cfg(feature = "zip")]
+pub use self::zip::ZipFileSystem;
+
+/// A reader which can seek as well, as returned by `FileSystem::open_seek`.
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// An entry of a directory as returned by `FileSystem::read_dir`.
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    /// The file name of the entry, without any leading directories.
+    pub name: String,
+    pub is_dir: bool,
+    /// The size in bytes, 0 for directories.
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Metadata of a path as returned by `FileSystem::metadata`.
+#[derive(Debug, Clone)]
+pub struct Metadata {
+    /// The size in bytes, 0 for directories.
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub is_file: bool,
+    pub is_dir: bool,
+}
 
 /// Implement this trait to provide a filesystem to serve from.
+///
+/// The trait is object safe, so FileSystems can be chosen at runtime and stored as
+/// `Box<FileSystem + Send + Sync>`. Paths are always relative to the root of the FileSystem.
 pub trait FileSystem {
-    fn is_file<P: AsRef<Path>>(&self, path: P) -> bool;
-    fn last_modified<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime, Box<Error>>;
-    fn size<P: AsRef<Path>>(&self, path: P) -> Result<u64, Box<Error>>;
-    fn open<P: AsRef<Path>>(&self, path: P, start: Option<u64>) -> Result<Box<Read>, Box<Error>>;
-    fn path_valid<P: AsRef<Path>>(&self, path: P) -> bool;
+    fn is_file(&self, path: &Path) -> bool;
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error>;
+    fn size(&self, path: &Path) -> Result<u64, Error>;
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error>;
+    fn path_valid(&self, path: &Path) -> bool;
+
+    /// Opens a file for reading with the ability to seek.
+    ///
+    /// The server uses this to position readers for range requests itself. Backends which
+    /// can't seek efficiently don't need to implement it, `open` is used with the start of the
+    /// range instead.
+    fn open_seek(&self, _path: &Path) -> Result<Box<ReadSeek>, Error> {
+        Err(Error::backend("seeking not supported"))
+    }
+
+    /// Returns true if the path is a directory.
+    ///
+    /// The default implementation knows no directories at all.
+    fn is_dir(&self, _path: &Path) -> bool {
+        false
+    }
+
+    /// Lists the entries of a directory.
+    ///
+    /// The default implementation doesn't support listing directories.
+    fn read_dir(&self, _path: &Path) -> Result<Vec<DirEntry>, Error> {
+        Err(Error::backend("directory listing not supported"))
+    }
+
+    /// Returns a strong validator for the file, e.g. a content hash, if the backend knows one.
+    ///
+    /// The ETag has to include the quotes, e.g. `"d41d8cd98f00b204"`. If `None` is returned,
+    /// the server makes up a weak ETag from size and modification date of the file.
+    fn etag(&self, _path: &Path) -> Option<String> {
+        None
+    }
+
+    /// Returns the MIME type of the file, if the backend knows it.
+    ///
+    /// If `None` is returned, the server guesses it from the extension of the file.
+    fn content_type(&self, _path: &Path) -> Option<String> {
+        None
+    }
+
+    /// Opens the file encoded with the given content coding, e.g. `gzip`, if the backend holds
+    /// it that way already.
+    ///
+    /// The server prefers this over compressing the file itself. The reader has to yield the
+    /// complete encoded file. If `None` is returned, the server compresses the file on the fly.
+    fn open_encoded(&self, _path: &Path, _encoding: &str) -> Option<Box<Read>> {
+        None
+    }
+
+    /// Returns the complete file if the backend holds it in memory for the whole run of the
+    /// program, e.g. embedded into the binary.
+    ///
+    /// The server sends such files as sized bodies right from the slice, without copying them
+    /// and with a Content-Length. If `None` is returned, the file is opened and streamed.
+    fn as_bytes(&self, _path: &Path) -> Option<&'static [u8]> {
+        None
+    }
+
+    /// Tells the FileSystem that the path changed, including everything below it, or that
+    /// anything may have changed if the path is `None`.
+    ///
+    /// FileSystems caching anything about the paths drop it, FileSystems wrapping others pass
+    /// it on. The default implementation does nothing. See `WatchingFileSystem` for calling
+    /// this whenever files change on disk.
+    fn changed(&self, _path: Option<&Path>) {}
+
+    /// Returns size, modification date and type of a path in one call.
+    ///
+    /// The default implementation is built on the other methods. Override it if your backend
+    /// can provide all of it at once, e.g. with a single stat call or HTTP request.
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        if self.is_file(path) {
+            Ok(Metadata {
+                size: self.size(path)?,
+                modified: Some(self.last_modified(path)?),
+                is_file: true,
+                is_dir: false,
+            })
+        } else if self.is_dir(path) {
+            Ok(Metadata {
+                size: 0,
+                modified: self.last_modified(path).ok(),
+                is_file: false,
+                is_dir: true,
+            })
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+}
+
+/// Forwards all methods of FileSystem to the FileSystem a smart pointer points to.
+macro_rules! forward_file_system {
+    ($($ty:ty),*) => {
+        $(
+            impl<'a, T: FileSystem + ?Sized> FileSystem for $ty {
+                fn is_file(&self, path: &Path) -> bool {
+                    (**self).is_file(path)
+                }
+
+                fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+                    (**self).last_modified(path)
+                }
+
+                fn size(&self, path: &Path) -> Result<u64, Error> {
+                    (**self).size(path)
+                }
+
+                fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+                    (**self).open(path, start)
+                }
+
+                fn path_valid(&self, path: &Path) -> bool {
+                    (**self).path_valid(path)
+                }
+
+                fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+                    (**self).open_seek(path)
+                }
+
+                fn is_dir(&self, path: &Path) -> bool {
+                    (**self).is_dir(path)
+                }
+
+                fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+                    (**self).read_dir(path)
+                }
+
+                fn etag(&self, path: &Path) -> Option<String> {
+                    (**self).etag(path)
+                }
+
+                fn content_type(&self, path: &Path) -> Option<String> {
+                    (**self).content_type(path)
+                }
+
+                fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+                    (**self).open_encoded(path, encoding)
+                }
+
+                fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+                    (**self).as_bytes(path)
+                }
+
+                fn changed(&self, path: Option<&Path>) {
+                    (**self).changed(path)
+                }
+
+                fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+                    (**self).metadata(path)
+                }
+            }
+        )*
+    };
 }
 
+// Allows sharing one FileSystem, e.g. with its caches, between the fairing and other users
+forward_file_system!(&'a T, Box<T>, Arc<T>);
+
 /// Implements the FileSystem trait to handle a local directory.
 pub struct LocalFileSystem {
     path: PathBuf,
@@ -36,21 +319,21 @@ impl LocalFileSystem {
 }
 
 impl FileSystem for LocalFileSystem {
-    fn is_file<P: AsRef<Path>>(&self, path: P) -> bool {
+    fn is_file(&self, path: &Path) -> bool {
         self.path.join(path).is_file()
     }
 
-    fn last_modified<P: AsRef<Path>>(&self, path: P) -> Result<SystemTime, Box<Error>> {
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
         let modified = self.path.join(path).metadata()?.modified()?;
         Ok(modified)
     }
 
-    fn size<P: AsRef<Path>>(&self, path: P) -> Result<u64, Box<Error>> {
+    fn size(&self, path: &Path) -> Result<u64, Error> {
         let len = self.path.join(path).metadata()?.len();
         Ok(len)
     }
 
-    fn open<P: AsRef<Path>>(&self, path: P, start: Option<u64>) -> Result<Box<Read>, Box<Error>> {
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
         let mut f = File::open(self.path.join(path))?;
         if let Some(start) = start {
             f.seek(SeekFrom::Start(start))?;
@@ -58,8 +341,167 @@ impl FileSystem for LocalFileSystem {
         Ok(Box::new(f))
     }
 
-    fn path_valid<P: AsRef<Path>>(&self, path: P) -> bool {
+    fn path_valid(&self, path: &Path) -> bool {
         let path = self.path.join(path);
         path.starts_with(&self.path)
     }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        Ok(Box::new(File::open(self.path.join(path))?))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.path.join(path).is_dir()
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        let meta = self.path.join(path).metadata()?;
+        Ok(Metadata {
+            size: if meta.is_dir() { 0 } else { meta.len() },
+            modified: meta.modified().ok(),
+            is_file: meta.is_file(),
+            is_dir: meta.is_dir(),
+        })
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(self.path.join(path))? {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            entries.push(DirEntry {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                is_dir: meta.is_dir(),
+                size: if meta.is_dir() { 0 } else { meta.len() },
+                modified: meta.modified().ok(),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileSystem, LocalFileSystem};
+    use std::io::{self, Read};
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use Error;
+
+    /// Implements only the required methods, so the defaults of the trait are used.
+    struct SingleFile;
+
+    impl FileSystem for SingleFile {
+        fn is_file(&self, path: &Path) -> bool {
+            path == Path::new("index.html")
+        }
+
+        fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+            if self.is_file(path) {
+                Ok(UNIX_EPOCH)
+            } else {
+                Err(Error::NotFound)
+            }
+        }
+
+        fn size(&self, path: &Path) -> Result<u64, Error> {
+            if self.is_file(path) {
+                Ok(5)
+            } else {
+                Err(Error::NotFound)
+            }
+        }
+
+        fn open(&self, path: &Path, _start: Option<u64>) -> Result<Box<Read>, Error> {
+            if self.is_file(path) {
+                Ok(Box::new(io::Cursor::new("hello")))
+            } else {
+                Err(Error::NotFound)
+            }
+        }
+
+        fn path_valid(&self, _path: &Path) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_metadata() {
+        let meta = SingleFile.metadata(Path::new("index.html")).unwrap();
+        assert!(meta.is_file);
+        assert!(!meta.is_dir);
+        assert_eq!(meta.size, 5);
+        assert_eq!(meta.modified, Some(UNIX_EPOCH));
+        assert!(SingleFile.metadata(Path::new("missing.html")).is_err());
+
+        let fs = LocalFileSystem::new("testdata");
+        let meta = fs.metadata(Path::new("hello.txt")).unwrap();
+        assert!(meta.is_file);
+        assert_eq!(meta.size, 12);
+        assert!(meta.modified.is_some());
+        let meta = fs.metadata(Path::new("inner")).unwrap();
+        assert!(meta.is_dir);
+        assert_eq!(meta.size, 0);
+        assert!(fs.metadata(Path::new("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn test_read_dir() {
+        assert!(!SingleFile.is_dir(Path::new("")));
+        assert!(SingleFile.read_dir(Path::new("")).is_err());
+
+        let fs = LocalFileSystem::new("testdata");
+        assert!(fs.is_dir(Path::new("inner")));
+        assert!(!fs.is_dir(Path::new("hello.txt")));
+
+        let mut entries = fs.read_dir(Path::new("")).unwrap();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["hello.txt", "inner", "yyy.txt", "zzz.txt"]);
+        assert!(!entries[0].is_dir);
+        assert_eq!(entries[0].size, 12);
+        assert!(entries[1].is_dir);
+        assert_eq!(entries[1].size, 0);
+
+        let entries = fs.read_dir(Path::new("inner")).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "other.txt");
+        assert!(fs.read_dir(Path::new("missing")).is_err());
+    }
+
+    #[test]
+    fn test_object_safety() {
+        let backends: Vec<(Box<FileSystem + Send + Sync>, &str)> = vec![
+            (Box::new(SingleFile), "index.html"),
+            (Box::new(LocalFileSystem::new("testdata")), "hello.txt"),
+        ];
+
+        for &(ref fs, path) in &backends {
+            let path = Path::new(path);
+            let mut s = String::new();
+            fs.open(path, None)
+                .unwrap()
+                .read_to_string(&mut s)
+                .unwrap();
+            assert_eq!(s.len() as u64, fs.size(path).unwrap());
+            assert!(fs.metadata(path).unwrap().is_file);
+        }
+    }
+
+    #[test]
+    fn test_smart_pointers() {
+        fn entries<F: FileSystem>(fs: F) -> usize {
+            assert!(fs.is_dir(Path::new("inner")));
+            fs.read_dir(Path::new("inner")).unwrap().len()
+        }
+
+        let fs = LocalFileSystem::new("testdata");
+        assert_eq!(entries(&fs), 1);
+        assert_eq!(entries(Box::new(LocalFileSystem::new("testdata"))), 1);
+
+        let fs = Arc::new(fs);
+        assert_eq!(entries(fs.clone()), 1);
+        assert_eq!(entries(&fs), 1);
+    }
 }
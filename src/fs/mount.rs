@@ -0,0 +1,268 @@
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use Error;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
+
+/// A FileSystem which contains nothing at all.
+///
+/// Useful as the innermost FileSystem of a `MountedFileSystem`.
+pub struct EmptyFileSystem;
+
+impl FileSystem for EmptyFileSystem {
+    fn is_file(&self, _path: &Path) -> bool {
+        false
+    }
+
+    fn last_modified(&self, _path: &Path) -> Result<SystemTime, Error> {
+        Err(Error::NotFound)
+    }
+
+    fn size(&self, _path: &Path) -> Result<u64, Error> {
+        Err(Error::NotFound)
+    }
+
+    fn open(&self, _path: &Path, _start: Option<u64>) -> Result<Box<Read>, Error> {
+        Err(Error::NotFound)
+    }
+
+    fn path_valid(&self, _path: &Path) -> bool {
+        true
+    }
+}
+
+/// Routes paths below a prefix to one FileSystem and all other paths to another one.
+///
+/// This way several FileSystems can be served by one `StaticFileServer`, sharing all of its
+/// configuration.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{LocalFileSystem, MountedFileSystem};
+///
+/// // Serves `uploads/*` from /var/uploads and `assets/*` from the local assets directory
+/// let fs = MountedFileSystem::new("assets", LocalFileSystem::new("assets"))
+///     .mount("uploads", LocalFileSystem::new("/var/uploads"));
+/// ```
+pub struct MountedFileSystem<T, R> {
+    prefix: PathBuf,
+    fs: T,
+    rest: R,
+}
+
+impl<T: FileSystem> MountedFileSystem<T, EmptyFileSystem> {
+    /// Mounts the FileSystem at the given prefix, e.g. `assets` or `static/images`.
+    pub fn new<P: AsRef<Path>>(prefix: P, fs: T) -> Self {
+        MountedFileSystem {
+            prefix: prefix.as_ref().to_owned(),
+            fs,
+            rest: EmptyFileSystem,
+        }
+    }
+}
+
+impl<T: FileSystem, R: FileSystem> MountedFileSystem<T, R> {
+    /// Mounts another FileSystem at the given prefix.
+    ///
+    /// The FileSystem mounted last takes precedence for overlapping prefixes.
+    pub fn mount<P, U>(self, prefix: P, fs: U) -> MountedFileSystem<U, Self>
+    where
+        P: AsRef<Path>,
+        U: FileSystem,
+    {
+        MountedFileSystem {
+            prefix: prefix.as_ref().to_owned(),
+            fs,
+            rest: self,
+        }
+    }
+
+    /// Returns the path inside of the mounted FileSystem if the path is below the prefix.
+    fn inner<'a>(&self, path: &'a Path) -> Option<&'a Path> {
+        path.strip_prefix(&self.prefix).ok()
+    }
+
+    /// Returns the next component of the prefix if the path is a parent of the prefix.
+    fn child_of_parent(&self, path: &Path) -> Option<String> {
+        let rest = self.prefix.strip_prefix(path).ok()?;
+        match rest.components().next() {
+            Some(Component::Normal(name)) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FileSystem, R: FileSystem> FileSystem for MountedFileSystem<T, R> {
+    fn is_file(&self, path: &Path) -> bool {
+        match self.inner(path) {
+            Some(inner) => self.fs.is_file(inner),
+            None => self.rest.is_file(path),
+        }
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        match self.inner(path) {
+            Some(inner) => self.fs.last_modified(inner),
+            None => self.rest.last_modified(path),
+        }
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        match self.inner(path) {
+            Some(inner) => self.fs.size(inner),
+            None => self.rest.size(path),
+        }
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        match self.inner(path) {
+            Some(inner) => self.fs.open(inner, start),
+            None => self.rest.open(path, start),
+        }
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        match self.inner(path) {
+            Some(inner) => self.fs.path_valid(inner),
+            None => self.rest.path_valid(path),
+        }
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        match self.inner(path) {
+            Some(inner) => self.fs.open_seek(inner),
+            None => self.rest.open_seek(path),
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        match self.inner(path) {
+            Some(inner) => self.fs.metadata(inner),
+            None if self.child_of_parent(path).is_some() => Ok(Metadata {
+                size: 0,
+                modified: None,
+                is_file: false,
+                is_dir: true,
+            }),
+            None => self.rest.metadata(path),
+        }
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        match self.inner(path) {
+            Some(inner) => self.fs.etag(inner),
+            None => self.rest.etag(path),
+        }
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        match self.inner(path) {
+            Some(inner) => self.fs.content_type(inner),
+            None => self.rest.content_type(path),
+        }
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        match self.inner(path) {
+            Some(inner) => self.fs.open_encoded(inner, encoding),
+            None => self.rest.open_encoded(path, encoding),
+        }
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        match self.inner(path) {
+            Some(inner) => self.fs.as_bytes(inner),
+            None => self.rest.as_bytes(path),
+        }
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        match path {
+            Some(path) => match self.inner(path) {
+                Some(inner) => self.fs.changed(Some(inner)),
+                None => {
+                    if self.prefix.starts_with(path) {
+                        self.fs.changed(None);
+                    }
+                    self.rest.changed(Some(path));
+                }
+            },
+            None => {
+                self.fs.changed(None);
+                self.rest.changed(None);
+            }
+        }
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        match self.inner(path) {
+            Some(inner) => self.fs.is_dir(inner),
+            None => self.child_of_parent(path).is_some() || self.rest.is_dir(path),
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        if let Some(inner) = self.inner(path) {
+            return self.fs.read_dir(inner);
+        }
+
+        // Parents of the prefix list the next component of the prefix as directory
+        match self.child_of_parent(path) {
+            Some(name) => {
+                let mut entries = self.rest.read_dir(path).unwrap_or_default();
+                entries.retain(|entry| entry.name != name);
+                entries.push(DirEntry {
+                    name,
+                    is_dir: true,
+                    size: 0,
+                    modified: None,
+                });
+                Ok(entries)
+            }
+            None => self.rest.read_dir(path),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MountedFileSystem;
+    use fs::{FileSystem, LocalFileSystem};
+    use std::io::Read;
+    use std::path::Path;
+
+    #[test]
+    fn test_mounted_file_system() {
+        let fs = MountedFileSystem::new("assets", LocalFileSystem::new("testdata"))
+            .mount("static/uploads", LocalFileSystem::new("testdata/inner"))
+            .mount("assets/images", LocalFileSystem::new("testdata/inner"));
+
+        let mut s = String::new();
+        fs.open(Path::new("static/uploads/other.txt"), None)
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "Other!");
+        assert!(fs.is_file(Path::new("assets/hello.txt")));
+        assert!(fs.is_file(Path::new("assets/images/other.txt")));
+        assert!(!fs.is_file(Path::new("assets/images/hello.txt")));
+        assert!(!fs.is_file(Path::new("hello.txt")));
+        assert!(!fs.is_file(Path::new("uploads/other.txt")));
+        assert!(fs.open(Path::new("index.html"), None).is_err());
+
+        // Parents of a prefix are directories listing the next component
+        assert!(fs.is_dir(Path::new("static")));
+        let names: Vec<String> = fs.read_dir(Path::new(""))
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert!(names.contains(&"assets".to_string()));
+        assert!(names.contains(&"static".to_string()));
+
+        let uploads = fs.read_dir(Path::new("static/uploads")).unwrap();
+        assert_eq!(uploads.len(), 1);
+        assert_eq!(uploads[0].name, "other.txt");
+    }
+}
@@ -0,0 +1,196 @@
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::Path;
+use std::time::SystemTime;
+use Error;
+
+/// Merges any number of FileSystems into one, upper layers taking precedence.
+///
+/// A file is served from the uppermost layer having it. Directories are merged, listing them
+/// shows the entries of all layers, an entry of an upper layer hiding entries of the same name
+/// below it. Unlike a `ChainedFileSystem`, the layers are chosen at runtime.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{LocalFileSystem, OverlayFileSystem};
+///
+/// // The assets of a customer override the ones of the base theme
+/// let fs = OverlayFileSystem::new(LocalFileSystem::new("themes/base"))
+///     .overlay(LocalFileSystem::new("customers/acme"));
+/// ```
+pub struct OverlayFileSystem {
+    /// The layers, the uppermost one first.
+    layers: Vec<Box<FileSystem + Send + Sync>>,
+}
+
+impl OverlayFileSystem {
+    /// Creates an overlay with a single, lowest layer.
+    pub fn new<T: FileSystem + Send + Sync + 'static>(base: T) -> Self {
+        OverlayFileSystem {
+            layers: vec![Box::new(base)],
+        }
+    }
+
+    /// Adds a layer on top of all other layers.
+    pub fn overlay<T: FileSystem + Send + Sync + 'static>(mut self, fs: T) -> Self {
+        self.layers.insert(0, Box::new(fs));
+        self
+    }
+
+    /// Returns the uppermost layer having a file at the path.
+    fn file_layer(&self, path: &Path) -> Result<&(FileSystem + Send + Sync), Error> {
+        self.layers
+            .iter()
+            .map(|layer| &**layer)
+            .find(|layer| layer.is_file(path))
+            .ok_or(Error::NotFound)
+    }
+}
+
+impl FileSystem for OverlayFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.file_layer(path).is_ok()
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        match self.file_layer(path) {
+            Ok(layer) => layer.last_modified(path),
+            Err(_) => self
+                .layers
+                .iter()
+                .filter_map(|layer| layer.last_modified(path).ok())
+                .next()
+                .ok_or(Error::NotFound),
+        }
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        self.file_layer(path)?.size(path)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        self.file_layer(path)?.open(path, start)
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.layers.iter().any(|layer| layer.path_valid(path))
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        self.file_layer(path)?.open_seek(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.layers.iter().any(|layer| layer.is_dir(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        let mut entries = BTreeMap::new();
+        let mut found = false;
+
+        for layer in &self.layers {
+            if !layer.is_dir(path) {
+                continue;
+            }
+            found = true;
+            for entry in layer.read_dir(path)? {
+                if !entries.contains_key(&entry.name) {
+                    entries.insert(entry.name.clone(), entry);
+                }
+            }
+        }
+
+        if found {
+            Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.file_layer(path).ok().and_then(|layer| layer.etag(path))
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.file_layer(path)
+            .ok()
+            .and_then(|layer| layer.content_type(path))
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        self.file_layer(path)
+            .ok()
+            .and_then(|layer| layer.open_encoded(path, encoding))
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        self.file_layer(path)
+            .ok()
+            .and_then(|layer| layer.as_bytes(path))
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        for layer in &self.layers {
+            layer.changed(path);
+        }
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        let mut dir = None;
+        for layer in &self.layers {
+            match layer.metadata(path) {
+                Ok(ref meta) if meta.is_file => return Ok(meta.clone()),
+                Ok(meta) => {
+                    if dir.is_none() {
+                        dir = Some(meta);
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+        dir.ok_or(Error::NotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OverlayFileSystem;
+    use fs::{FileSystem, InMemoryFileSystem};
+    use std::io::Read;
+    use std::path::Path;
+
+    #[test]
+    fn test_overlay_file_system() {
+        let mut base = InMemoryFileSystem::new();
+        base.insert("index.html", "base");
+        base.insert("css/base.css", "base");
+        base.insert("css/theme.css", "base");
+        let mut customer = InMemoryFileSystem::new();
+        customer.insert("css/theme.css", "customer");
+        customer.insert("logo.png", "customer");
+        let fs = OverlayFileSystem::new(base).overlay(customer);
+
+        let mut s = String::new();
+        fs.open(Path::new("css/theme.css"), None)
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "customer");
+        assert!(fs.is_file(Path::new("index.html")));
+        assert!(fs.is_file(Path::new("logo.png")));
+        assert!(fs.metadata(Path::new("css")).unwrap().is_dir);
+
+        let names: Vec<String> = fs.read_dir(Path::new(""))
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["css", "index.html", "logo.png"]);
+
+        let css = fs.read_dir(Path::new("css")).unwrap();
+        assert_eq!(css.len(), 2);
+        assert_eq!(css[1].size, 8);
+    }
+}
@@ -0,0 +1,54 @@
+//! Readers shared by the archive backends.
+
+use std::cmp;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// Restricts a reader to the data of one entry of the archive.
+pub struct EntryReader<R> {
+    inner: R,
+    offset: u64,
+    size: u64,
+    pos: u64,
+}
+
+impl<R: Read + Seek> EntryReader<R> {
+    pub fn new(mut inner: R, offset: u64, size: u64) -> io::Result<Self> {
+        inner.seek(SeekFrom::Start(offset))?;
+        Ok(EntryReader {
+            inner,
+            offset,
+            size,
+            pos: 0,
+        })
+    }
+}
+
+impl<R: Read> Read for EntryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.size.saturating_sub(self.pos);
+        let len = cmp::min(buf.len() as u64, remaining) as usize;
+        let read = self.inner.read(&mut buf[..len])?;
+        self.pos += read as u64;
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for EntryReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => pos as i64,
+            SeekFrom::End(delta) => self.size as i64 + delta,
+            SeekFrom::Current(delta) => self.pos as i64 + delta,
+        };
+        if pos < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "seek before the start of the entry",
+            ));
+        }
+
+        self.pos = pos as u64;
+        self.inner.seek(SeekFrom::Start(self.offset + self.pos))?;
+        Ok(self.pos)
+    }
+}
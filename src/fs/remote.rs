@@ -0,0 +1,188 @@
+//! Helpers shared by the backends fetching files over HTTP.
+
+use chrono::DateTime;
+use fs::Metadata;
+use path::percent_encode;
+use reqwest::header::{HeaderMap, CONTENT_LENGTH, CONTENT_TYPE, ETAG, LAST_MODIFIED};
+use reqwest::{Method, Response, StatusCode};
+use std::io::{self, Read};
+use std::path::{Component, Path};
+use std::time::SystemTime;
+use Error;
+
+/// Implements FileSystem for a backend with `ObjectStore`, looking up files with HEAD
+/// requests. Listings aren't supported.
+#[cfg(any(feature = "s3", feature = "gcs", feature = "azure"))]
+macro_rules! object_file_system {
+    ($ty:ty) => {
+        impl ::fs::FileSystem for $ty {
+            fn is_file(&self, path: &::std::path::Path) -> bool {
+                ::fs::remote::ObjectStore::head(self, path).is_ok()
+            }
+
+            fn last_modified(
+                &self,
+                path: &::std::path::Path,
+            ) -> Result<::std::time::SystemTime, ::Error> {
+                Ok(::fs::remote::ObjectStore::head(self, path)?.modified)
+            }
+
+            fn size(&self, path: &::std::path::Path) -> Result<u64, ::Error> {
+                Ok(::fs::remote::ObjectStore::head(self, path)?.size)
+            }
+
+            fn open(
+                &self,
+                path: &::std::path::Path,
+                start: Option<u64>,
+            ) -> Result<Box<::std::io::Read>, ::Error> {
+                let method = ::reqwest::Method::GET;
+                let response = ::fs::remote::ObjectStore::send(self, method, path, start)?;
+                ::fs::remote::body(response, start)
+            }
+
+            fn path_valid(&self, path: &::std::path::Path) -> bool {
+                ::fs::remote::path_valid(path)
+            }
+
+            fn etag(&self, path: &::std::path::Path) -> Option<String> {
+                ::fs::remote::ObjectStore::head(self, path).ok().and_then(|file| file.etag)
+            }
+
+            fn metadata(&self, path: &::std::path::Path) -> Result<::fs::Metadata, ::Error> {
+                Ok(::fs::remote::ObjectStore::head(self, path)?.metadata())
+            }
+        }
+    };
+}
+
+/// A backend fetching files with HTTP requests.
+pub trait ObjectStore {
+    /// Sends a request for the file at `path`, from `start` on if given.
+    fn send(&self, method: Method, path: &Path, start: Option<u64>) -> Result<Response, Error>;
+
+    /// Fetches the metadata of the file at `path`.
+    fn head(&self, path: &Path) -> Result<RemoteFile, Error> {
+        let response = self.send(Method::HEAD, path, None)?;
+        RemoteFile::from_headers(response.headers())
+    }
+}
+
+/// What the headers of a response tell about a remote file.
+pub struct RemoteFile {
+    pub size: u64,
+    pub modified: SystemTime,
+    pub etag: Option<String>,
+    pub content_type: Option<String>,
+}
+
+impl RemoteFile {
+    /// Reads size, modification date, ETag and MIME type from the headers of a response to a
+    /// HEAD request.
+    pub fn from_headers(headers: &HeaderMap) -> Result<Self, Error> {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+
+        let size = header(CONTENT_LENGTH)
+            .and_then(|length| length.parse().ok())
+            .ok_or_else(|| Error::backend("upstream sent no Content-Length"))?;
+        let modified = header(LAST_MODIFIED)
+            .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+            .ok_or_else(|| Error::backend("upstream sent no valid Last-Modified"))?;
+
+        Ok(RemoteFile {
+            size,
+            modified: SystemTime::from(modified),
+            etag: header(ETAG).map(|etag| etag.to_string()),
+            content_type: header(CONTENT_TYPE).map(|mime| mime.to_string()),
+        })
+    }
+
+    pub fn metadata(&self) -> Metadata {
+        Metadata {
+            size: self.size,
+            modified: Some(self.modified),
+            is_file: true,
+            is_dir: false,
+        }
+    }
+}
+
+/// Maps failed requests and error responses to errors.
+pub fn check(result: ::reqwest::Result<Response>) -> Result<Response, Error> {
+    let response = result.map_err(|err| Error::backend(err.to_string()))?;
+    match response.status() {
+        status if status.is_success() => Ok(response),
+        StatusCode::NOT_FOUND => Err(Error::NotFound),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(Error::PermissionDenied),
+        status => Err(Error::backend(format!("upstream responded with {}", status))),
+    }
+}
+
+/// Returns the value of a Range header requesting the file from `start` on.
+pub fn range(start: u64) -> String {
+    format!("bytes={}-", start)
+}
+
+/// Turns the response to a GET request into a reader starting at `start`.
+///
+/// Upstreams ignoring the Range header send the whole file, the start is skipped then.
+pub fn body(mut response: Response, start: Option<u64>) -> Result<Box<Read>, Error> {
+    if let Some(start) = start {
+        if response.status() != StatusCode::PARTIAL_CONTENT {
+            io::copy(&mut (&mut response).take(start), &mut io::sink())?;
+        }
+    }
+    Ok(Box::new(response))
+}
+
+/// Turns a key prefix into one which can be put in front of paths, e.g. `/public/` into
+/// `public/`. An empty prefix stays empty.
+pub fn normalize_prefix(prefix: &str) -> String {
+    let prefix = prefix.trim_matches('/');
+    if prefix.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", prefix)
+    }
+}
+
+/// Returns the URL of the file at `path` below `base` and the normalized `prefix`.
+///
+/// The root is no file, neither are paths which aren't valid UTF-8.
+pub fn url(base: &str, prefix: &str, path: &Path) -> Result<String, Error> {
+    let path = path.to_str().ok_or(Error::NotFound)?;
+    if path.is_empty() {
+        return Err(Error::NotFound);
+    }
+    Ok(format!("{}/{}", base, percent_encode(&format!("{}{}", prefix, path))))
+}
+
+/// Returns false for paths which could climb out of the served location.
+pub fn path_valid(path: &Path) -> bool {
+    path.components().all(|c| match c {
+        Component::Normal(_) => true,
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_prefix, path_valid, range, url};
+    use std::path::Path;
+
+    #[test]
+    fn test_helpers() {
+        assert_eq!(range(100), "bytes=100-");
+        assert_eq!(normalize_prefix(""), "");
+        assert_eq!(normalize_prefix("/"), "");
+        assert_eq!(normalize_prefix("/public/"), "public/");
+        assert_eq!(
+            url("https://host/bucket", "public/", Path::new("my file.txt")).unwrap(),
+            "https://host/bucket/public/my%20file.txt"
+        );
+        assert!(url("https://host/bucket", "public/", Path::new("")).is_err());
+        assert!(path_valid(Path::new("")));
+        assert!(path_valid(Path::new("css/app.css")));
+        assert!(!path_valid(Path::new("../secret")));
+    }
+}
@@ -0,0 +1,218 @@
+use fs::embedded::collect_dirs;
+use fs::{DirEntry, FileSystem, Metadata};
+use rust_embed::RustEmbed;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io::{Cursor, Read};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::time::SystemTime;
+use Error;
+
+/// Provides a FileSystem serving the assets of a type deriving `RustEmbed`.
+///
+/// rust-embed knows no modification dates, so all files are dated to the creation of the
+/// FileSystem, usually the start of the server. ETags are hashes of the contents and are
+/// computed once on creation, which reads all files in debug builds where rust-embed serves
+/// them from disk. Requires the `rust-embed` feature.
+///
+/// # Example
+///
+/// ```
+/// #[macro_use]
+/// extern crate rust_embed;
+/// extern crate rocket_static_fs;
+///
+/// use rocket_static_fs::fs::RustEmbedFileSystem;
+///
+/// #[derive(RustEmbed)]
+/// #[folder = "testdata/"]
+/// struct Assets;
+///
+/// fn main() {
+///     let fs = RustEmbedFileSystem::<Assets>::new();
+/// }
+/// ```
+pub struct RustEmbedFileSystem<E> {
+    files: HashMap<String, EmbeddedAsset>,
+    /// Maps the path of every directory to the names of its children, the root is "".
+    dirs: HashMap<String, BTreeSet<String>>,
+    created: SystemTime,
+    embed: PhantomData<fn() -> E>,
+}
+
+struct EmbeddedAsset {
+    size: u64,
+    etag: String,
+}
+
+impl<E: RustEmbed> RustEmbedFileSystem<E> {
+    pub fn new() -> Self {
+        let mut files = HashMap::new();
+        for path in E::iter() {
+            if let Some(data) = E::get(&path) {
+                let mut hasher = DefaultHasher::new();
+                data.hash(&mut hasher);
+                files.insert(
+                    path.replace('\\', "/"),
+                    EmbeddedAsset {
+                        size: data.len() as u64,
+                        etag: format!("\"{:016x}\"", hasher.finish()),
+                    },
+                );
+            }
+        }
+
+        RustEmbedFileSystem {
+            dirs: collect_dirs(files.keys()),
+            files,
+            created: SystemTime::now(),
+            embed: PhantomData,
+        }
+    }
+
+    fn asset(&self, path: &Path) -> Result<(&str, &EmbeddedAsset), Error> {
+        let path = path.to_str().ok_or(Error::NotFound)?;
+        self.files
+            .get(path)
+            .map(|asset| (path, asset))
+            .ok_or(Error::NotFound)
+    }
+}
+
+impl<E: RustEmbed> Default for RustEmbedFileSystem<E> {
+    fn default() -> Self {
+        RustEmbedFileSystem::new()
+    }
+}
+
+impl<E: RustEmbed> FileSystem for RustEmbedFileSystem<E> {
+    fn is_file(&self, path: &Path) -> bool {
+        self.asset(path).is_ok()
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        if self.is_file(path) || self.is_dir(path) {
+            Ok(self.created)
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        Ok(self.asset(path)?.1.size)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        let (path, _) = self.asset(path)?;
+        let data = E::get(path).ok_or(Error::NotFound)?;
+        let mut reader = Cursor::new(data);
+        reader.set_position(start.unwrap_or(0));
+        Ok(Box::new(reader))
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.to_str()
+            .map_or(false, |path| self.dirs.contains_key(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        let dir = path.to_str().ok_or(Error::NotFound)?;
+        let children = self.dirs.get(dir).ok_or(Error::NotFound)?;
+
+        let entries = children
+            .iter()
+            .map(|name| {
+                let child = if dir.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{}", dir, name)
+                };
+                match self.files.get(&child) {
+                    Some(asset) => DirEntry {
+                        name: name.clone(),
+                        is_dir: false,
+                        size: asset.size,
+                        modified: Some(self.created),
+                    },
+                    None => DirEntry {
+                        name: name.clone(),
+                        is_dir: true,
+                        size: 0,
+                        modified: Some(self.created),
+                    },
+                }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.asset(path).ok().map(|(_, asset)| asset.etag.clone())
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        if let Ok((_, asset)) = self.asset(path) {
+            return Ok(Metadata {
+                size: asset.size,
+                modified: Some(self.created),
+                is_file: true,
+                is_dir: false,
+            });
+        }
+
+        if self.is_dir(path) {
+            Ok(Metadata {
+                size: 0,
+                modified: Some(self.created),
+                is_file: false,
+                is_dir: true,
+            })
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RustEmbedFileSystem;
+    use fs::FileSystem;
+    use rust_embed;
+    use rust_embed::RustEmbed;
+    use std::io::Read;
+    use std::path::Path;
+
+    #[derive(RustEmbed)]
+    #[folder = "testdata/"]
+    struct Assets;
+
+    #[test]
+    fn test_rust_embed_file_system() {
+        let fs = RustEmbedFileSystem::<Assets>::new();
+
+        let mut s = String::new();
+        fs.open(Path::new("hello.txt"), Some(6))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "World!");
+        assert_eq!(fs.size(Path::new("hello.txt")).unwrap(), 12);
+        assert!(fs.etag(Path::new("hello.txt")).unwrap().starts_with('"'));
+        assert_ne!(fs.etag(Path::new("hello.txt")), fs.etag(Path::new("yyy.txt")));
+        assert!(fs.is_file(Path::new("inner/other.txt")));
+        assert!(!fs.is_file(Path::new("missing.txt")));
+
+        assert!(fs.is_dir(Path::new("inner")));
+        assert!(fs.metadata(Path::new("inner")).unwrap().is_dir);
+        let entries = fs.read_dir(Path::new("")).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["hello.txt", "inner", "yyy.txt", "zzz.txt"]);
+        assert!(entries[1].is_dir);
+    }
+}
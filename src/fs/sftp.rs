@@ -0,0 +1,410 @@
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use ssh2::{self, CheckResult, ErrorCode, FileStat, KnownHostFileKind, Session, Sftp};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use Error;
+
+/// SFTP status codes, see draft-ietf-secsh-filexfer-02.
+const FX_NO_SUCH_FILE: i32 = 2;
+const FX_PERMISSION_DENIED: i32 = 3;
+const FX_NO_SUCH_PATH: i32 = 10;
+
+/// Provides a FileSystem serving the files of a directory on an SFTP server.
+///
+/// Connections are opened on first use and kept in a pool for the following requests, up to
+/// `max_idle` of them are kept around. Connections failing at the SSH level are dropped instead
+/// of being put back. Ranges are read by seeking the remote file, so only the requested bytes
+/// are transferred.
+///
+/// The host key of the server is checked against a known_hosts file, connecting fails if none
+/// is set with `known_hosts` and `accept_any_host_key` isn't called either.
+///
+/// Every lookup is a round trip to the server, so you probably want to put a cache in front of
+/// it. Requires the `sftp` feature.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::SftpFileSystem;
+///
+/// let fs = SftpFileSystem::new("legacy.example.com", "www")
+///     .private_key("/etc/rocket/id_ed25519", None)
+///     .known_hosts("/etc/rocket/known_hosts")
+///     .root("/var/www/htdocs");
+/// ```
+pub struct SftpFileSystem {
+    pool: Arc<Pool>,
+    root: PathBuf,
+}
+
+struct Pool {
+    host: String,
+    port: u16,
+    username: String,
+    auth: Auth,
+    known_hosts: Option<PathBuf>,
+    accept_any_host_key: bool,
+    timeout: Option<Duration>,
+    max_idle: usize,
+    idle: Mutex<Vec<Sftp>>,
+}
+
+enum Auth {
+    Agent,
+    Password(String),
+    PrivateKey(PathBuf, Option<String>),
+}
+
+impl SftpFileSystem {
+    /// Serves the home directory of `username` on `host`, port 22, authenticating with the
+    /// SSH agent.
+    pub fn new(host: &str, username: &str) -> Self {
+        SftpFileSystem {
+            pool: Arc::new(Pool {
+                host: host.to_string(),
+                port: 22,
+                username: username.to_string(),
+                auth: Auth::Agent,
+                known_hosts: None,
+                accept_any_host_key: false,
+                timeout: None,
+                max_idle: 4,
+                idle: Mutex::new(Vec::new()),
+            }),
+            root: PathBuf::new(),
+        }
+    }
+
+    /// Connects to another port than 22.
+    pub fn port(mut self, port: u16) -> Self {
+        self.pool_mut().port = port;
+        self
+    }
+
+    /// Authenticates with a password.
+    pub fn password(mut self, password: &str) -> Self {
+        self.pool_mut().auth = Auth::Password(password.to_string());
+        self
+    }
+
+    /// Authenticates with the private key file at `path`, decrypted with `passphrase` if given.
+    pub fn private_key<P: AsRef<Path>>(mut self, path: P, passphrase: Option<&str>) -> Self {
+        self.pool_mut().auth = Auth::PrivateKey(
+            path.as_ref().to_path_buf(),
+            passphrase.map(|p| p.to_string()),
+        );
+        self
+    }
+
+    /// Only connects to the server if its host key is listed in the OpenSSH known_hosts file
+    /// at `path`.
+    pub fn known_hosts<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.pool_mut().known_hosts = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Connects to the server whatever host key it presents if no known_hosts file is set.
+    ///
+    /// Anyone able to intercept the connection can then pose as the server and serve their own
+    /// files, so only use this where the network can be trusted, e.g. in tests.
+    pub fn accept_any_host_key(mut self) -> Self {
+        self.pool_mut().accept_any_host_key = true;
+        self
+    }
+
+    /// Serves the given directory of the server instead of the home directory.
+    pub fn root<P: AsRef<Path>>(mut self, root: P) -> Self {
+        self.root = root.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the timeout of connecting and of every SFTP operation.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.pool_mut().timeout = Some(timeout);
+        self
+    }
+
+    /// Sets how many idle connections are kept open, 4 by default.
+    pub fn max_idle(mut self, max_idle: usize) -> Self {
+        self.pool_mut().max_idle = max_idle;
+        self
+    }
+
+    /// The pool isn't shared before the first connection, which needs `&self`.
+    fn pool_mut(&mut self) -> &mut Pool {
+        Arc::get_mut(&mut self.pool).expect("SftpFileSystem configured after first use")
+    }
+
+    fn remote_path(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+
+    fn stat(&self, path: &Path) -> Result<FileStat, Error> {
+        let remote = self.remote_path(path);
+        Pool::get(&self.pool)?.run(|sftp| sftp.stat(&remote))
+    }
+
+    fn open_file(&self, path: &Path) -> Result<SftpFile, Error> {
+        let remote = self.remote_path(path);
+        let mut connection = Pool::get(&self.pool)?;
+        let file = connection.run(|sftp| sftp.open(&remote))?;
+        Ok(SftpFile {
+            file,
+            _connection: connection,
+        })
+    }
+}
+
+impl Pool {
+    /// Takes an idle connection or opens a new one.
+    fn get(pool: &Arc<Pool>) -> Result<Connection, Error> {
+        let idle = pool.idle.lock().unwrap().pop();
+        let sftp = match idle {
+            Some(sftp) => sftp,
+            None => pool.connect()?,
+        };
+        Ok(Connection {
+            sftp: Some(sftp),
+            pool: pool.clone(),
+        })
+    }
+
+    fn connect(&self) -> Result<Sftp, Error> {
+        if self.known_hosts.is_none() && !self.accept_any_host_key {
+            return Err(Error::backend(format!(
+                "no known_hosts file to check the host key of {} against, set one or call \
+                 accept_any_host_key",
+                self.host
+            )));
+        }
+
+        let address = (self.host.as_str(), self.port);
+        let tcp = match self.timeout {
+            Some(timeout) => {
+                let address = address
+                    .to_socket_addrs()?
+                    .next()
+                    .ok_or_else(|| Error::backend(format!("cannot resolve {}", self.host)))?;
+                TcpStream::connect_timeout(&address, timeout)?
+            }
+            None => TcpStream::connect(address)?,
+        };
+
+        let mut session = Session::new().map_err(ssh_error)?;
+        if let Some(timeout) = self.timeout {
+            let millis = timeout.as_secs() * 1000 + u64::from(timeout.subsec_millis());
+            session.set_timeout(millis as u32);
+        }
+        session.set_tcp_stream(tcp);
+        session.handshake().map_err(ssh_error)?;
+        self.check_host_key(&session)?;
+
+        match self.auth {
+            Auth::Agent => session.userauth_agent(&self.username),
+            Auth::Password(ref password) => session.userauth_password(&self.username, password),
+            Auth::PrivateKey(ref path, ref passphrase) => session.userauth_pubkey_file(
+                &self.username,
+                None,
+                path,
+                passphrase.as_ref().map(|p| p.as_str()),
+            ),
+        }.map_err(ssh_error)?;
+
+        session.sftp().map_err(ssh_error)
+    }
+
+    fn check_host_key(&self, session: &Session) -> Result<(), Error> {
+        // Without a known_hosts file `connect` made sure any host key is accepted
+        let path = match self.known_hosts {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+
+        let mut known_hosts = session.known_hosts().map_err(ssh_error)?;
+        known_hosts
+            .read_file(path, KnownHostFileKind::OpenSSH)
+            .map_err(ssh_error)?;
+        let (key, _) = session
+            .host_key()
+            .ok_or_else(|| Error::backend("SFTP server sent no host key"))?;
+        match known_hosts.check_port(&self.host, self.port, key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::Mismatch => Err(Error::backend(format!(
+                "host key of {} does not match the known one",
+                self.host
+            ))),
+            _ => Err(Error::backend(format!("host key of {} is not known", self.host))),
+        }
+    }
+}
+
+/// A connection taken from the pool, put back when dropped unless it broke.
+struct Connection {
+    sftp: Option<Sftp>,
+    pool: Arc<Pool>,
+}
+
+impl Connection {
+    /// Runs an operation, dropping the connection if it failed at the SSH level.
+    fn run<T, F>(&mut self, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&Sftp) -> Result<T, ssh2::Error>,
+    {
+        let result = f(self.sftp.as_ref().unwrap());
+        if let Err(ref err) = result {
+            if let ErrorCode::Session(_) = err.code() {
+                self.sftp = None;
+            }
+        }
+        result.map_err(ssh_error)
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(sftp) = self.sftp.take() {
+            let mut idle = self.pool.idle.lock().unwrap();
+            if idle.len() < self.pool.max_idle {
+                idle.push(sftp);
+            }
+        }
+    }
+}
+
+/// An open remote file, keeping its connection out of the pool while it is read.
+struct SftpFile {
+    // Declared first so the file is closed before the connection goes back to the pool.
+    file: ssh2::File,
+    _connection: Connection,
+}
+
+impl Read for SftpFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+impl Seek for SftpFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.file.seek(pos)
+    }
+}
+
+fn ssh_error(err: ssh2::Error) -> Error {
+    match err.code() {
+        ErrorCode::SFTP(FX_NO_SUCH_FILE) | ErrorCode::SFTP(FX_NO_SUCH_PATH) => Error::NotFound,
+        ErrorCode::SFTP(FX_PERMISSION_DENIED) => Error::PermissionDenied,
+        _ => Error::backend(format!("SFTP: {}", err.message())),
+    }
+}
+
+fn system_time(stat: &FileStat) -> Option<SystemTime> {
+    stat.mtime.map(|seconds| UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+impl FileSystem for SftpFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.stat(path).map(|stat| stat.is_file()).unwrap_or(false)
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        system_time(&self.stat(path)?)
+            .ok_or_else(|| Error::backend("SFTP server sent no modification date"))
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        Ok(self.stat(path)?.size.unwrap_or(0))
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        let mut file = self.open_file(path)?;
+        if let Some(start) = start {
+            file.seek(SeekFrom::Start(start))?;
+        }
+        Ok(Box::new(file))
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        path.components().all(|c| match c {
+            Component::Normal(_) => true,
+            _ => false,
+        })
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        Ok(Box::new(self.open_file(path)?))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.stat(path).map(|stat| stat.is_dir()).unwrap_or(false)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        let remote = self.remote_path(path);
+        let mut entries: Vec<DirEntry> = Pool::get(&self.pool)?
+            .run(|sftp| sftp.readdir(&remote))?
+            .into_iter()
+            .filter_map(|(child, stat)| {
+                let name = child.file_name()?.to_str()?.to_string();
+                Some(DirEntry {
+                    name,
+                    is_dir: stat.is_dir(),
+                    size: if stat.is_dir() { 0 } else { stat.size.unwrap_or(0) },
+                    modified: system_time(&stat),
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        let stat = self.stat(path)?;
+        Ok(Metadata {
+            size: if stat.is_dir() { 0 } else { stat.size.unwrap_or(0) },
+            modified: system_time(&stat),
+            is_file: stat.is_file(),
+            is_dir: stat.is_dir(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SftpFileSystem;
+    use fs::FileSystem;
+    use std::path::Path;
+
+    #[test]
+    fn test_sftp_file_system_config() {
+        let fs = SftpFileSystem::new("localhost", "www")
+            .port(2222)
+            .password("secret")
+            .max_idle(0)
+            .root("/srv/www");
+
+        assert_eq!(fs.pool.port, 2222);
+        assert_eq!(fs.remote_path(Path::new("css/app.css")), Path::new("/srv/www/css/app.css"));
+        assert!(fs.path_valid(Path::new("css/app.css")));
+        assert!(!fs.path_valid(Path::new("../etc/passwd")));
+        assert!(!fs.path_valid(Path::new("/etc/passwd")));
+    }
+
+    #[test]
+    fn test_sftp_file_system_requires_known_hosts() {
+        // Fails before connecting, nothing listens on port 1
+        let fs = SftpFileSystem::new("localhost", "www").port(1);
+        let err = fs.metadata(Path::new("index.html")).unwrap_err();
+        assert!(err.to_string().contains("known_hosts"));
+
+        let fs = SftpFileSystem::new("localhost", "www")
+            .port(1)
+            .accept_any_host_key();
+        assert!(fs.pool.accept_any_host_key);
+        let err = fs.metadata(Path::new("index.html")).unwrap_err();
+        assert!(!err.to_string().contains("known_hosts"));
+    }
+}
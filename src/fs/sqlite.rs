@@ -0,0 +1,286 @@
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use rusqlite::{self, Connection, OptionalExtension, ToSql};
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use Error;
+
+/// Provides a FileSystem serving the rows of a SQLite table as files.
+///
+/// The table needs the columns `path`, `content`, `mtime` and `mime`:
+///
+/// ```sql
+/// CREATE TABLE assets (
+///     path TEXT PRIMARY KEY NOT NULL,  -- relative to the root, e.g. "img/logo.png"
+///     content BLOB NOT NULL,
+///     mtime INTEGER NOT NULL,          -- seconds since the epoch
+///     mime TEXT                        -- NULL to guess it from the extension
+/// );
+/// ```
+///
+/// Directories are derived from the paths. The table is queried on every request, so changes
+/// show up right away. Requires the `sqlite` feature.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::SqliteFileSystem;
+///
+/// let fs = SqliteFileSystem::open("uploads.db", "assets").unwrap();
+/// ```
+pub struct SqliteFileSystem {
+    connection: Mutex<Connection>,
+    table: String,
+}
+
+impl SqliteFileSystem {
+    /// Opens the database at `path` and serves the given table of it.
+    pub fn open<P: AsRef<Path>>(path: P, table: &str) -> Result<Self, Error> {
+        let connection = Connection::open(path).map_err(sqlite_error)?;
+        SqliteFileSystem::from_connection(connection, table)
+    }
+
+    /// Serves the given table of an open connection.
+    pub fn from_connection(connection: Connection, table: &str) -> Result<Self, Error> {
+        let valid = table
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+            && table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid {
+            return Err(Error::backend(format!("invalid table name: {}", table)));
+        }
+
+        Ok(SqliteFileSystem {
+            connection: Mutex::new(connection),
+            table: table.to_string(),
+        })
+    }
+
+    /// Runs a query for a single row of the file at `path`.
+    fn file<T, F>(&self, columns: &str, path: &Path, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(&rusqlite::Row) -> rusqlite::Result<T>,
+    {
+        let path = path.to_str().ok_or(Error::NotFound)?;
+        let sql = format!("SELECT {} FROM {} WHERE path = ?", columns, self.table);
+        self.connection
+            .lock()
+            .unwrap()
+            .query_row(&sql, &[path], f)
+            .optional()
+            .map_err(sqlite_error)?
+            .ok_or(Error::NotFound)
+    }
+
+    /// Returns the latest modification date of the files in a directory, if there are any.
+    fn dir_modified(&self, path: &Path) -> Result<Option<SystemTime>, Error> {
+        let (lower, upper) = dir_range(path).ok_or(Error::NotFound)?;
+        let sql = format!(
+            "SELECT max(mtime) FROM {} WHERE path >= ? AND path < ?",
+            self.table
+        );
+        let modified: Option<i64> = self.connection
+            .lock()
+            .unwrap()
+            .query_row(&sql, &[&lower, &upper], |row| row.get(0))
+            .map_err(sqlite_error)?;
+        Ok(modified.map(system_time))
+    }
+}
+
+fn sqlite_error(err: rusqlite::Error) -> Error {
+    Error::backend(err.to_string())
+}
+
+/// Returns the range of paths within a directory: all paths starting with `dir/`, which sort
+/// before `dir0`. The range lets SQLite use the index of the path column.
+fn dir_range(path: &Path) -> Option<(String, String)> {
+    match path.to_str()? {
+        "" => Some((String::new(), "\u{10ffff}".to_string())),
+        dir => Some((format!("{}/", dir), format!("{}0", dir))),
+    }
+}
+
+fn system_time(seconds: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(if seconds > 0 { seconds as u64 } else { 0 })
+}
+
+impl FileSystem for SqliteFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.file("1", path, |row| row.get::<_, i64>(0)).is_ok()
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        match self.file("mtime", path, |row| row.get(0)) {
+            Ok(seconds) => Ok(system_time(seconds)),
+            Err(Error::NotFound) => self.dir_modified(path)?.ok_or(Error::NotFound),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        let size: i64 = self.file("length(content)", path, |row| row.get(0))?;
+        Ok(size as u64)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        let content: Vec<u8> = self.file("content", path, |row| row.get(0))?;
+        let mut reader = Cursor::new(content);
+        reader.set_position(start.unwrap_or(0));
+        Ok(Box::new(reader))
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        let content: Vec<u8> = self.file("content", path, |row| row.get(0))?;
+        Ok(Box::new(Cursor::new(content)))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.to_str() == Some("") || self.dir_modified(path).ok().map_or(false, |m| m.is_some())
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        let (lower, upper) = dir_range(path).ok_or(Error::NotFound)?;
+        let sql = format!(
+            "SELECT path, length(content), mtime FROM {} WHERE path >= ? AND path < ?",
+            self.table
+        );
+
+        let mut entries: BTreeMap<String, DirEntry> = BTreeMap::new();
+        {
+            let connection = self.connection.lock().unwrap();
+            let mut statement = connection.prepare(&sql).map_err(sqlite_error)?;
+            let params: [&ToSql; 2] = [&lower, &upper];
+            let mut rows = statement.query(&params).map_err(sqlite_error)?;
+            while let Some(row) = rows.next().map_err(sqlite_error)? {
+                let child: String = row.get(0).map_err(sqlite_error)?;
+                let size: i64 = row.get(1).map_err(sqlite_error)?;
+                let modified = system_time(row.get(2).map_err(sqlite_error)?);
+
+                let rest = &child[lower.len()..];
+                match rest.find('/') {
+                    Some(end) => {
+                        let entry = entries.entry(rest[..end].to_string()).or_insert(DirEntry {
+                            name: rest[..end].to_string(),
+                            is_dir: true,
+                            size: 0,
+                            modified: Some(modified),
+                        });
+                        if entry.modified.map_or(true, |m| m < modified) {
+                            entry.modified = Some(modified);
+                        }
+                    }
+                    None => {
+                        entries.insert(
+                            rest.to_string(),
+                            DirEntry {
+                                name: rest.to_string(),
+                                is_dir: false,
+                                size: size as u64,
+                                modified: Some(modified),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+
+        if entries.is_empty() && !lower.is_empty() {
+            return Err(Error::NotFound);
+        }
+        Ok(entries.into_iter().map(|(_, entry)| entry).collect())
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.file("mime", path, |row| row.get(0)).ok().and_then(|mime| mime)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        let file = self.file("length(content), mtime", path, |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        });
+        match file {
+            Ok((size, modified)) => Ok(Metadata {
+                size: size as u64,
+                modified: Some(system_time(modified)),
+                is_file: true,
+                is_dir: false,
+            }),
+            Err(Error::NotFound) => match self.dir_modified(path)? {
+                Some(modified) => Ok(Metadata {
+                    size: 0,
+                    modified: Some(modified),
+                    is_file: false,
+                    is_dir: true,
+                }),
+                None if path.to_str() == Some("") => Ok(Metadata {
+                    size: 0,
+                    modified: None,
+                    is_file: false,
+                    is_dir: true,
+                }),
+                None => Err(Error::NotFound),
+            },
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SqliteFileSystem;
+    use fs::FileSystem;
+    use rusqlite::Connection;
+    use std::io::Read;
+    use std::path::Path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_sqlite_file_system() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE assets (path TEXT PRIMARY KEY, content BLOB, mtime INTEGER, mime TEXT);
+                 INSERT INTO assets VALUES ('index.html', X'3C68313E48656C6C6F3C2F68313E', 1500000000, NULL);
+                 INSERT INTO assets VALUES ('img/logo', X'89504E47', 1600000000, 'image/png');
+                 INSERT INTO assets VALUES ('img0', X'', 1, NULL);",
+            )
+            .unwrap();
+        let fs = SqliteFileSystem::from_connection(connection, "assets").unwrap();
+        assert!(SqliteFileSystem::from_connection(Connection::open_in_memory().unwrap(), "a; b").is_err());
+
+        assert!(fs.is_file(Path::new("index.html")));
+        assert!(fs.is_dir(Path::new("img")));
+        assert!(!fs.is_file(Path::new("img")));
+        assert!(!fs.is_dir(Path::new("index.html")));
+        assert_eq!(fs.size(Path::new("img/logo")).unwrap(), 4);
+        assert_eq!(fs.content_type(Path::new("img/logo")), Some("image/png".to_string()));
+        assert_eq!(fs.content_type(Path::new("index.html")), None);
+        assert_eq!(
+            fs.last_modified(Path::new("img")).unwrap(),
+            UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+        );
+
+        let mut s = String::new();
+        fs.open(Path::new("index.html"), Some(4))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "Hello</h1>");
+
+        let names: Vec<String> = fs.read_dir(Path::new(""))
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, vec!["img", "img0", "index.html"]);
+        assert!(fs.read_dir(Path::new("css")).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use Error;
+
+/// The number of paths cached before expired entries are dropped.
+const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+/// Caches the metadata of the paths of another FileSystem for a short time.
+///
+/// A request makes the server look up a path several times, e.g. for hidden files, index files
+/// and the response itself. With this cache that's a single call to the inner FileSystem,
+/// `is_file`, `is_dir`, `size`, `last_modified` and `metadata` are answered from the cache.
+/// Missing paths are cached as well. Contents aren't cached at all, see `CachingFileSystem`
+/// for that.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{LocalFileSystem, StatCacheFileSystem};
+/// use std::time::Duration;
+///
+/// let fs = StatCacheFileSystem::new(LocalFileSystem::new("static"), Duration::from_secs(1));
+/// ```
+pub struct StatCacheFileSystem<T> {
+    inner: T,
+    ttl: Duration,
+    max_entries: usize,
+    /// Maps paths to their metadata, None if there's nothing, and the time it was fetched.
+    stats: Mutex<HashMap<String, (Option<Metadata>, Instant)>>,
+}
+
+impl<T: FileSystem> StatCacheFileSystem<T> {
+    /// Caches metadata of the inner FileSystem for `ttl`.
+    pub fn new(inner: T, ttl: Duration) -> Self {
+        StatCacheFileSystem {
+            inner,
+            ttl,
+            max_entries: DEFAULT_MAX_ENTRIES,
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Sets the number of paths cached at most, defaults to 10000.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    fn stat(&self, path: &Path) -> Result<Metadata, Error> {
+        let key = path.to_string_lossy();
+        if let Some(&(ref metadata, fetched)) = self.stats.lock().unwrap().get(&*key) {
+            if fetched.elapsed() < self.ttl {
+                return metadata.clone().ok_or(Error::NotFound);
+            }
+        }
+
+        let metadata = match self.inner.metadata(path) {
+            Ok(metadata) => Some(metadata),
+            Err(Error::NotFound) => None,
+            Err(err) => return Err(err),
+        };
+
+        let mut stats = self.stats.lock().unwrap();
+        if stats.len() >= self.max_entries {
+            let ttl = self.ttl;
+            stats.retain(|_, &mut (_, fetched)| fetched.elapsed() < ttl);
+            if stats.len() >= self.max_entries {
+                stats.clear();
+            }
+        }
+        stats.insert(key.into_owned(), (metadata.clone(), Instant::now()));
+        metadata.ok_or(Error::NotFound)
+    }
+}
+
+impl<T: FileSystem> FileSystem for StatCacheFileSystem<T> {
+    fn is_file(&self, path: &Path) -> bool {
+        self.stat(path).map_or(false, |meta| meta.is_file)
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        match self.stat(path)?.modified {
+            Some(modified) => Ok(modified),
+            None => self.inner.last_modified(path),
+        }
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        Ok(self.stat(path)?.size)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        self.inner.open(path, start)
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.inner.path_valid(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        self.inner.open_seek(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.stat(path).map_or(false, |meta| meta.is_dir)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        self.inner.read_dir(path)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.inner.etag(path)
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.inner.content_type(path)
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        self.inner.open_encoded(path, encoding)
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        self.inner.as_bytes(path)
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        {
+            let mut stats = self.stats.lock().unwrap();
+            match path.map(|path| path.to_string_lossy()) {
+                Some(ref path) if !path.is_empty() => {
+                    let dir = format!("{}/", path);
+                    stats.retain(|key, _| key != path && !key.starts_with(&dir));
+                }
+                _ => stats.clear(),
+            }
+        }
+        self.inner.changed(path);
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        self.stat(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatCacheFileSystem;
+    use fs::{FileSystem, InMemoryFileSystem, Metadata};
+    use std::io::Read;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, SystemTime};
+    use Error;
+
+    /// Counts how often metadata is looked up.
+    struct Counting {
+        fs: InMemoryFileSystem,
+        stats: AtomicUsize,
+    }
+
+    impl FileSystem for Counting {
+        fn is_file(&self, path: &Path) -> bool {
+            self.fs.is_file(path)
+        }
+
+        fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+            self.fs.last_modified(path)
+        }
+
+        fn size(&self, path: &Path) -> Result<u64, Error> {
+            self.fs.size(path)
+        }
+
+        fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+            self.fs.open(path, start)
+        }
+
+        fn path_valid(&self, path: &Path) -> bool {
+            self.fs.path_valid(path)
+        }
+
+        fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+            self.stats.fetch_add(1, Ordering::SeqCst);
+            self.fs.metadata(path)
+        }
+    }
+
+    #[test]
+    fn test_stat_cache_file_system() {
+        let mut files = InMemoryFileSystem::new();
+        files.insert("css/app.css", "body {}");
+        let counting = Counting {
+            fs: files,
+            stats: AtomicUsize::new(0),
+        };
+
+        let fs = StatCacheFileSystem::new(&counting, Duration::from_secs(60));
+        assert!(fs.is_file(Path::new("css/app.css")));
+        assert_eq!(fs.size(Path::new("css/app.css")).unwrap(), 7);
+        assert!(fs.metadata(Path::new("css/app.css")).unwrap().is_file);
+        assert_eq!(counting.stats.load(Ordering::SeqCst), 1);
+
+        assert!(!fs.is_file(Path::new("missing.css")));
+        assert!(!fs.is_dir(Path::new("missing.css")));
+        assert!(fs.is_dir(Path::new("css")));
+        assert_eq!(counting.stats.load(Ordering::SeqCst), 3);
+
+        // Expired entries are looked up again
+        let fs = StatCacheFileSystem::new(&counting, Duration::from_secs(0));
+        fs.is_file(Path::new("css/app.css"));
+        fs.is_file(Path::new("css/app.css"));
+        assert_eq!(counting.stats.load(Ordering::SeqCst), 5);
+    }
+}
@@ -0,0 +1,269 @@
+use flate2::read::GzDecoder;
+use fs::embedded::collect_dirs;
+use fs::reader::EntryReader;
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tar::Archive;
+use Error;
+
+/// Provides a FileSystem serving the files of a tar archive.
+///
+/// The archive is indexed once on construction. Files of plain tar archives are read straight
+/// from the archive on disk, gzipped archives (`.tar.gz`, `.tgz`) are unpacked into memory
+/// since they can't be read at random positions. Requires the `tar` feature.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::TarFileSystem;
+///
+/// let fs = TarFileSystem::open("content.tar.gz").unwrap();
+/// ```
+pub struct TarFileSystem {
+    source: Source,
+    files: HashMap<String, TarEntry>,
+    /// Maps the path of every directory to the names of its children, the root is "".
+    dirs: HashMap<String, BTreeSet<String>>,
+}
+
+enum Source {
+    File(PathBuf),
+    Memory(Arc<[u8]>),
+}
+
+struct TarEntry {
+    /// The position of the data of the file in the (uncompressed) archive.
+    offset: u64,
+    size: u64,
+    modified: SystemTime,
+}
+
+impl TarFileSystem {
+    /// Indexes the tar archive at the given path, which may be gzipped.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let mut magic = [0; 2];
+        let gzipped = {
+            let mut f = File::open(path)?;
+            f.read_exact(&mut magic).is_ok() && is_gzip(&magic)
+        };
+
+        if gzipped {
+            let mut bytes = Vec::new();
+            File::open(path)?.read_to_end(&mut bytes)?;
+            return TarFileSystem::from_bytes(bytes);
+        }
+
+        let files = index(File::open(path)?)?;
+        Ok(TarFileSystem::new(Source::File(path.to_owned()), files))
+    }
+
+    /// Indexes a tar archive held in memory, which may be gzipped.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        let bytes = if is_gzip(&bytes) {
+            let mut unpacked = Vec::new();
+            GzDecoder::new(&bytes[..]).read_to_end(&mut unpacked)?;
+            unpacked
+        } else {
+            bytes
+        };
+
+        let files = index(Cursor::new(&bytes[..]))?;
+        Ok(TarFileSystem::new(Source::Memory(Arc::from(bytes)), files))
+    }
+
+    fn new(source: Source, files: HashMap<String, TarEntry>) -> Self {
+        let dirs = collect_dirs(files.keys());
+        TarFileSystem {
+            source,
+            files,
+            dirs,
+        }
+    }
+
+    fn entry(&self, path: &Path) -> Result<&TarEntry, Error> {
+        path.to_str()
+            .and_then(|path| self.files.get(path))
+            .ok_or(Error::NotFound)
+    }
+
+    /// Returns a reader for the data of the entry, positioned at its start.
+    fn reader(&self, entry: &TarEntry) -> Result<EntryReader<Box<ReadSeek>>, Error> {
+        let inner: Box<ReadSeek> = match self.source {
+            Source::File(ref path) => Box::new(File::open(path)?),
+            Source::Memory(ref bytes) => Box::new(Cursor::new(bytes.clone())),
+        };
+        EntryReader::new(inner, entry.offset, entry.size).map_err(Error::from)
+    }
+}
+
+impl FileSystem for TarFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.entry(path).is_ok()
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        Ok(self.entry(path)?.modified)
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        Ok(self.entry(path)?.size)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        let mut reader = self.reader(self.entry(path)?)?;
+        if let Some(start) = start {
+            reader.seek(SeekFrom::Start(start))?;
+        }
+        Ok(Box::new(reader))
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        Ok(Box::new(self.reader(self.entry(path)?)?))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.to_str()
+            .map_or(false, |path| self.dirs.contains_key(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        let dir = path.to_str().ok_or(Error::NotFound)?;
+        let children = self.dirs.get(dir).ok_or(Error::NotFound)?;
+
+        let entries = children
+            .iter()
+            .map(|name| {
+                let child = if dir.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{}", dir, name)
+                };
+                match self.files.get(&child) {
+                    Some(entry) => DirEntry {
+                        name: name.clone(),
+                        is_dir: false,
+                        size: entry.size,
+                        modified: Some(entry.modified),
+                    },
+                    None => DirEntry {
+                        name: name.clone(),
+                        is_dir: true,
+                        size: 0,
+                        modified: None,
+                    },
+                }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        if let Ok(entry) = self.entry(path) {
+            return Ok(Metadata {
+                size: entry.size,
+                modified: Some(entry.modified),
+                is_file: true,
+                is_dir: false,
+            });
+        }
+
+        if self.is_dir(path) {
+            Ok(Metadata {
+                size: 0,
+                modified: None,
+                is_file: false,
+                is_dir: true,
+            })
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+}
+
+/// Reads the positions of all regular files of an uncompressed tar archive.
+fn index<R: Read>(reader: R) -> io::Result<HashMap<String, TarEntry>> {
+    let mut archive = Archive::new(reader);
+    let mut files = HashMap::new();
+
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let path = path.trim_left_matches("./").trim_left_matches('/').to_string();
+        let mtime = entry.header().mtime().unwrap_or(0);
+        files.insert(
+            path,
+            TarEntry {
+                offset: entry.raw_file_position(),
+                size: entry.size(),
+                modified: UNIX_EPOCH + Duration::from_secs(mtime),
+            },
+        );
+    }
+
+    Ok(files)
+}
+
+fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&[0x1f, 0x8b])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TarFileSystem;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use fs::FileSystem;
+    use std::io::{Read, Write};
+    use std::path::Path;
+    use tar::{Builder, Header};
+
+    fn archive() -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        for &(path, data) in &[("index.html", "<h1>Hello</h1>"), ("css/app.css", "body {}")] {
+            let mut header = Header::new_gnu();
+            header.set_path(path).unwrap();
+            header.set_size(data.len() as u64);
+            header.set_mtime(1_500_000_000);
+            header.set_cksum();
+            builder.append(&header, data.as_bytes()).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn check(fs: &TarFileSystem) {
+        assert!(fs.is_file(Path::new("index.html")));
+        assert!(fs.is_file(Path::new("css/app.css")));
+        assert!(fs.is_dir(Path::new("css")));
+        assert_eq!(fs.size(Path::new("css/app.css")).unwrap(), 7);
+
+        let mut s = String::new();
+        fs.open(Path::new("index.html"), Some(4))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "Hello</h1>");
+    }
+
+    #[test]
+    fn test_tar_file_system() {
+        check(&TarFileSystem::from_bytes(archive()).unwrap());
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&archive()).unwrap();
+        check(&TarFileSystem::from_bytes(encoder.finish().unwrap()).unwrap());
+    }
+}
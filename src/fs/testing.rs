@@ -0,0 +1,410 @@
+//! Helpers for testing code which uses FileSystems, e.g. the configuration of a
+//! StaticFileServer, in memory or against a temporary directory.
+
+use filetime::{self, FileTime};
+use fs::{DirEntry, FileSystem, InMemoryFileSystem, LocalFileSystem, Metadata, ReadSeek};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use Error;
+
+/// Numbers the temporary directories of this process.
+static TEMP_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+/// A call made to a MockFileSystem.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    /// The name of the FileSystem method, e.g. `open`.
+    pub operation: &'static str,
+    /// The path relative to the root, e.g. `css/app.css`.
+    pub path: String,
+}
+
+/// A FileSystem serving scripted files and errors, which records every call made to it.
+///
+/// Files are held in memory like in an `InMemoryFileSystem`. Calls for a path can be made to
+/// fail or to take some time. Share the MockFileSystem with an `Arc` to inspect its calls
+/// after handing it to a StaticFileServer.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::testing::MockFileSystem;
+/// use rocket_static_fs::Error;
+/// use std::time::Duration;
+///
+/// let mut fs = MockFileSystem::new();
+/// fs.insert("index.html", "<h1>Hello</h1>");
+/// fs.insert("secret.txt", "");
+/// fs.fail("secret.txt", || Error::PermissionDenied);
+/// fs.delay("index.html", Duration::from_millis(50));
+/// ```
+pub struct MockFileSystem {
+    files: InMemoryFileSystem,
+    /// Creates the error calls for a path fail with.
+    errors: HashMap<String, Box<Fn() -> Error + Send + Sync>>,
+    delays: HashMap<String, Duration>,
+    delay: Option<Duration>,
+    calls: Mutex<Vec<Call>>,
+}
+
+impl MockFileSystem {
+    pub fn new() -> Self {
+        MockFileSystem {
+            files: InMemoryFileSystem::new(),
+            errors: HashMap::new(),
+            delays: HashMap::new(),
+            delay: None,
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Adds a file, replacing any file at the same path.
+    pub fn insert<P, D>(&mut self, path: P, data: D)
+    where
+        P: AsRef<str>,
+        D: Into<Vec<u8>>,
+    {
+        self.files.insert(path, data);
+    }
+
+    /// Sets the modification date of a file, returns false if there's no such file.
+    pub fn set_modified(&mut self, path: &str, modified: SystemTime) -> bool {
+        self.files.set_modified(path, modified)
+    }
+
+    /// Sets the MIME type of a file, returns false if there's no such file.
+    pub fn set_mime_type(&mut self, path: &str, mime: &str) -> bool {
+        self.files.set_mime_type(path, mime)
+    }
+
+    /// Makes all calls for a path fail with the error created by `error`.
+    ///
+    /// Calls returning a bool return false and calls returning an Option return None.
+    pub fn fail<F>(&mut self, path: &str, error: F)
+    where
+        F: Fn() -> Error + Send + Sync + 'static,
+    {
+        self.errors.insert(normalize(path), Box::new(error));
+    }
+
+    /// Makes all calls for a path take at least `delay`.
+    pub fn delay(&mut self, path: &str, delay: Duration) {
+        self.delays.insert(normalize(path), delay);
+    }
+
+    /// Makes all calls for paths without a delay of their own take at least `delay`.
+    pub fn delay_all(&mut self, delay: Duration) {
+        self.delay = Some(delay);
+    }
+
+    /// Returns the calls made so far, the first one first.
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Returns how often the operation was called for the path.
+    pub fn count(&self, operation: &str, path: &str) -> usize {
+        let path = normalize(path);
+        self.calls
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|call| call.operation == operation && call.path == path)
+            .count()
+    }
+
+    /// Forgets the calls made so far.
+    pub fn clear_calls(&self) {
+        self.calls.lock().unwrap().clear();
+    }
+
+    /// Records a call, waits for the delay of the path and returns its error, if any.
+    fn call(&self, operation: &'static str, path: &Path) -> Result<(), Error> {
+        let path = normalize(&path.to_string_lossy());
+        if let Some(delay) = self.delays.get(&path).cloned().or(self.delay) {
+            thread::sleep(delay);
+        }
+
+        let result = match self.errors.get(&path) {
+            Some(error) => Err(error()),
+            None => Ok(()),
+        };
+        self.calls.lock().unwrap().push(Call { operation, path });
+        result
+    }
+}
+
+impl Default for MockFileSystem {
+    fn default() -> Self {
+        MockFileSystem::new()
+    }
+}
+
+fn normalize(path: &str) -> String {
+    path.trim_left_matches('/').to_string()
+}
+
+impl FileSystem for MockFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.call("is_file", path).is_ok() && self.files.is_file(path)
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        self.call("last_modified", path)?;
+        self.files.last_modified(path)
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        self.call("size", path)?;
+        self.files.size(path)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        self.call("open", path)?;
+        self.files.open(path, start)
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.call("path_valid", path).is_ok() && self.files.path_valid(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        self.call("open_seek", path)?;
+        self.files.open_seek(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.call("is_dir", path).is_ok() && self.files.is_dir(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        self.call("read_dir", path)?;
+        self.files.read_dir(path)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.call("etag", path).ok()?;
+        self.files.etag(path)
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.call("content_type", path).ok()?;
+        self.files.content_type(path)
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        self.call("open_encoded", path).ok()?;
+        self.files.open_encoded(path, encoding)
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        self.call("as_bytes", path).ok()?;
+        self.files.as_bytes(path)
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        let _ = self.call("changed", path.unwrap_or_else(|| Path::new("")));
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        self.call("metadata", path)?;
+        self.files.metadata(path)
+    }
+}
+
+/// A LocalFileSystem over a temporary directory, which is deleted when this is dropped.
+///
+/// Useful to test code against real files, e.g. their modification dates. As a helper for
+/// tests it panics if the directory or its files can't be written.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::testing::TempFileSystem;
+/// use std::collections::HashMap;
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let mut files = HashMap::new();
+/// files.insert("index.html", "<h1>Hello</h1>");
+/// files.insert("css/app.css", "body {}");
+/// let mut fs = TempFileSystem::from(files);
+/// fs.set_modified("index.html", UNIX_EPOCH + Duration::from_secs(1_500_000_000));
+/// ```
+pub struct TempFileSystem {
+    dir: PathBuf,
+    fs: LocalFileSystem,
+}
+
+impl TempFileSystem {
+    /// Creates an empty temporary directory.
+    pub fn new() -> Self {
+        let created = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.subsec_nanos())
+            .unwrap_or(0);
+        let dir = env::temp_dir().join(format!(
+            "rocket_static_fs-{}-{}-{}",
+            process::id(),
+            TEMP_DIRS.fetch_add(1, Ordering::SeqCst),
+            created
+        ));
+        fs::create_dir_all(&dir).expect("creating a temporary directory");
+
+        TempFileSystem {
+            fs: LocalFileSystem::new(&dir),
+            dir,
+        }
+    }
+
+    /// Returns the temporary directory.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes a file, creating its directories and replacing any file at the same path.
+    pub fn insert<P, D>(&mut self, path: P, data: D)
+    where
+        P: AsRef<str>,
+        D: AsRef<[u8]>,
+    {
+        let path = self.dir.join(normalize(path.as_ref()));
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).expect("creating a directory");
+        }
+        fs::write(path, data).expect("writing a file");
+    }
+
+    /// Sets the modification date of a file or directory, returns false if there's no such
+    /// file or directory.
+    pub fn set_modified(&mut self, path: &str, modified: SystemTime) -> bool {
+        let path = self.dir.join(normalize(path));
+        path.exists()
+            && filetime::set_file_mtime(path, FileTime::from_system_time(modified)).is_ok()
+    }
+}
+
+impl Default for TempFileSystem {
+    fn default() -> Self {
+        TempFileSystem::new()
+    }
+}
+
+impl<S: AsRef<str>, D: AsRef<[u8]>> From<HashMap<S, D>> for TempFileSystem {
+    fn from(files: HashMap<S, D>) -> Self {
+        let mut fs = TempFileSystem::new();
+        for (path, data) in files {
+            fs.insert(path, data);
+        }
+        fs
+    }
+}
+
+impl Drop for TempFileSystem {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+impl FileSystem for TempFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.fs.is_file(path)
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        self.fs.last_modified(path)
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        self.fs.size(path)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        self.fs.open(path, start)
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.fs.path_valid(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        self.fs.open_seek(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.fs.is_dir(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        self.fs.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        self.fs.metadata(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MockFileSystem, TempFileSystem};
+    use fs::FileSystem;
+    use rocket;
+    use rocket::http::Status;
+    use rocket::local::Client;
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::{Duration, UNIX_EPOCH};
+    use {Error, StaticFileServer};
+
+    #[test]
+    fn test_mock_file_system() {
+        let mut fs = MockFileSystem::new();
+        fs.insert("index.html", "<h1>Hello</h1>");
+        fs.insert("secret.txt", "");
+        fs.fail("secret.txt", || Error::PermissionDenied);
+        let fs = Arc::new(fs);
+
+        let server = StaticFileServer::builder(fs.clone()).build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut response = client.get("/index.html").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.body_string(), Some("<h1>Hello</h1>".to_string()));
+        assert_eq!(fs.count("open", "index.html"), 1);
+
+        fs.clear_calls();
+        let response = client.get("/secret.txt").dispatch();
+        assert_ne!(response.status(), Status::Ok);
+        assert!(fs.calls().iter().any(|call| call.path == "secret.txt"));
+        assert_eq!(fs.count("open", "secret.txt"), 0);
+    }
+
+    #[test]
+    fn test_temp_file_system() {
+        let mut files = HashMap::new();
+        files.insert("index.html", "<h1>Hello</h1>");
+        files.insert("/css/app.css", "body {}");
+        let mut fs = TempFileSystem::from(files);
+        let dir = fs.path().to_owned();
+
+        assert!(fs.is_file(Path::new("index.html")));
+        assert!(fs.is_file(Path::new("css/app.css")));
+        assert!(fs.is_dir(Path::new("css")));
+
+        let modified = UNIX_EPOCH + Duration::from_secs(1_500_000_000);
+        assert!(fs.set_modified("css/app.css", modified));
+        assert!(!fs.set_modified("missing.txt", modified));
+        assert_eq!(fs.last_modified(Path::new("css/app.css")).unwrap(), modified);
+
+        drop(fs);
+        assert!(!dir.exists());
+    }
+}
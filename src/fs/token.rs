@@ -0,0 +1,80 @@
+//! OAuth access tokens for the backends of cloud storage services.
+
+use fs::remote;
+use reqwest::RequestBuilder;
+use serde_json::{self, Value};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use Error;
+
+/// Caches an OAuth access token until shortly before it expires.
+pub struct TokenCache {
+    token: Mutex<Option<(String, Instant)>>,
+}
+
+impl TokenCache {
+    pub fn new() -> Self {
+        TokenCache {
+            token: Mutex::new(None),
+        }
+    }
+
+    /// Returns the cached token, or the one returned by `fetch` if there's none or it expires
+    /// within the next minute.
+    pub fn get<F>(&self, fetch: F) -> Result<String, Error>
+    where
+        F: FnOnce() -> Result<(String, Duration), Error>,
+    {
+        let mut cached = self.token.lock().unwrap();
+        if let Some((ref token, expires)) = *cached {
+            if Instant::now() + Duration::from_secs(60) < expires {
+                return Ok(token.clone());
+            }
+        }
+
+        let (access_token, lifetime) = fetch()?;
+        *cached = Some((access_token.clone(), Instant::now() + lifetime));
+        Ok(access_token)
+    }
+}
+
+/// Sends a token request and returns the access token and its lifetime.
+///
+/// The response has to be the usual JSON object of OAuth 2.0 with `access_token` and
+/// `expires_in`, which may be a number or a string.
+pub fn fetch_token(request: RequestBuilder) -> Result<(String, Duration), Error> {
+    let response = remote::check(request.send())?;
+    let body: Value =
+        serde_json::from_reader(response).map_err(|err| Error::backend(err.to_string()))?;
+
+    let token = body["access_token"]
+        .as_str()
+        .ok_or_else(|| Error::backend("token response without access_token"))?;
+    let expires_in = match body["expires_in"] {
+        Value::Number(ref n) => n.as_u64(),
+        Value::String(ref s) => s.parse().ok(),
+        _ => None,
+    };
+    Ok((token.to_string(), Duration::from_secs(expires_in.unwrap_or(0))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TokenCache;
+    use std::time::Duration;
+
+    #[test]
+    fn test_token_cache() {
+        let cache = TokenCache::new();
+        let token = cache.get(|| Ok(("first".to_string(), Duration::from_secs(3600))));
+        assert_eq!(token.unwrap(), "first");
+        let token = cache.get(|| Ok(("second".to_string(), Duration::from_secs(3600))));
+        assert_eq!(token.unwrap(), "first");
+
+        // Tokens about to expire are replaced
+        let cache = TokenCache::new();
+        cache.get(|| Ok(("first".to_string(), Duration::from_secs(30)))).unwrap();
+        let token = cache.get(|| Ok(("second".to_string(), Duration::from_secs(3600))));
+        assert_eq!(token.unwrap(), "second");
+    }
+}
@@ -0,0 +1,312 @@
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use notify::{self, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Component, Path};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use Error;
+
+/// How long changes are collected before they're passed on.
+const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+/// How many paths the versions are counted for before they're collapsed into one.
+const MAX_VERSIONS: usize = 10_000;
+
+/// Watches the directory another FileSystem serves and tells it about every change.
+///
+/// Changes are passed on with `FileSystem::changed`, so caches like `CachingFileSystem` and
+/// `StatCacheFileSystem` wrapped by this FileSystem drop their stale entries right away. The
+/// ETags of changed files get a version appended as well, so clients don't keep a variant
+/// cached which was served before the change. Requires the `watch` feature.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::{CachingFileSystem, LocalFileSystem, WatchingFileSystem};
+///
+/// let cached = CachingFileSystem::new(LocalFileSystem::new("static"), 64 * 1024 * 1024);
+/// let fs = WatchingFileSystem::new(cached, "static").unwrap();
+/// ```
+pub struct WatchingFileSystem<T> {
+    shared: Arc<Shared<T>>,
+    /// Stops watching when dropped.
+    _watcher: Mutex<RecommendedWatcher>,
+}
+
+struct Shared<T> {
+    inner: T,
+    /// Counts the changes of paths, the path "" counts changes of everything.
+    versions: Mutex<HashMap<String, u64>>,
+}
+
+impl<T: FileSystem + Send + Sync + 'static> WatchingFileSystem<T> {
+    /// Watches `dir`, the directory the inner FileSystem serves, and all directories below it.
+    pub fn new<P: AsRef<Path>>(inner: T, dir: P) -> Result<Self, Error> {
+        WatchingFileSystem::with_delay(inner, dir, DEFAULT_DELAY)
+    }
+
+    /// Watches like `new`, but collects changes for `delay` instead of 100 ms, e.g. to pass
+    /// on a deployment as a whole.
+    pub fn with_delay<P: AsRef<Path>>(inner: T, dir: P, delay: Duration) -> Result<Self, Error> {
+        let root = dir.as_ref().canonicalize()?;
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::watcher(sender, delay).map_err(watch_error)?;
+        watcher
+            .watch(&root, RecursiveMode::Recursive)
+            .map_err(watch_error)?;
+
+        let shared = Arc::new(Shared {
+            inner,
+            versions: Mutex::new(HashMap::new()),
+        });
+
+        // Runs until the watcher is dropped along with the sender
+        let watched = shared.clone();
+        thread::spawn(move || {
+            for event in receiver {
+                match event {
+                    DebouncedEvent::Create(path)
+                    | DebouncedEvent::Write(path)
+                    | DebouncedEvent::Chmod(path)
+                    | DebouncedEvent::Remove(path) => watched.changed_on_disk(&root, &path),
+                    DebouncedEvent::Rename(from, to) => {
+                        watched.changed_on_disk(&root, &from);
+                        watched.changed_on_disk(&root, &to);
+                    }
+                    DebouncedEvent::Rescan => watched.changed(None),
+                    DebouncedEvent::Error(err, path) => {
+                        warn!("Watching {:?} failed: {}", path.as_ref().unwrap_or(&root), err);
+                        watched.changed(None);
+                    }
+                    DebouncedEvent::NoticeWrite(_) | DebouncedEvent::NoticeRemove(_) => {}
+                }
+            }
+        });
+
+        Ok(WatchingFileSystem {
+            shared,
+            _watcher: Mutex::new(watcher),
+        })
+    }
+}
+
+fn watch_error(err: notify::Error) -> Error {
+    match err {
+        notify::Error::Io(err) => Error::from(err),
+        notify::Error::PathNotFound => Error::NotFound,
+        err => Error::backend(err.to_string()),
+    }
+}
+
+impl<T: FileSystem> Shared<T> {
+    /// Passes on the change of an absolute path below the watched directory.
+    fn changed_on_disk(&self, root: &Path, path: &Path) {
+        let relative = match path.strip_prefix(root) {
+            Ok(relative) => relative,
+            Err(_) => return,
+        };
+        let names: Vec<_> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })
+            .collect();
+        self.changed(Some(Path::new(&names.join("/"))));
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        {
+            let mut versions = self.versions.lock().unwrap();
+            match path {
+                Some(path) if versions.len() < MAX_VERSIONS => {
+                    let key = path.to_string_lossy().into_owned();
+                    *versions.entry(key).or_insert(0) += 1;
+                }
+                // Everything changes to a version above any one seen before, so the map doesn't
+                // grow with every path ever changed
+                _ => {
+                    let total = versions.values().sum::<u64>() + 1;
+                    versions.clear();
+                    versions.insert(String::new(), total);
+                }
+            }
+        }
+        self.inner.changed(path);
+    }
+
+    /// Returns how often the path, one of its parents or everything changed.
+    fn version(&self, path: &Path) -> u64 {
+        let versions = self.versions.lock().unwrap();
+        if versions.is_empty() {
+            return 0;
+        }
+
+        let path = path.to_string_lossy();
+        let mut version = versions.get(&*path).cloned().unwrap_or(0);
+        for (end, _) in path.match_indices('/') {
+            version += versions.get(&path[..end]).cloned().unwrap_or(0);
+        }
+        if !path.is_empty() {
+            version += versions.get("").cloned().unwrap_or(0);
+        }
+        version
+    }
+}
+
+impl<T: FileSystem> FileSystem for WatchingFileSystem<T> {
+    fn is_file(&self, path: &Path) -> bool {
+        self.shared.inner.is_file(path)
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        self.shared.inner.last_modified(path)
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        self.shared.inner.size(path)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        self.shared.inner.open(path, start)
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.shared.inner.path_valid(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        self.shared.inner.open_seek(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.shared.inner.is_dir(path)
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        self.shared.inner.read_dir(path)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        let version = self.shared.version(path);
+        match self.shared.inner.etag(path) {
+            Some(ref etag) if version > 0 && etag.ends_with('"') => {
+                Some(format!("{}-v{}\"", &etag[..etag.len() - 1], version))
+            }
+            Some(etag) => Some(etag),
+            // Like the ETag the server makes up, but with the version
+            None if version > 0 => {
+                let metadata = self.shared.inner.metadata(path).ok()?;
+                let modified = metadata.modified?.duration_since(UNIX_EPOCH).ok()?;
+                Some(format!(
+                    "W/\"{:x}-{:x}-v{}\"",
+                    metadata.size,
+                    modified.as_secs(),
+                    version
+                ))
+            }
+            None => None,
+        }
+    }
+
+    fn content_type(&self, path: &Path) -> Option<String> {
+        self.shared.inner.content_type(path)
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        self.shared.inner.open_encoded(path, encoding)
+    }
+
+    fn as_bytes(&self, path: &Path) -> Option<&'static [u8]> {
+        self.shared.inner.as_bytes(path)
+    }
+
+    fn changed(&self, path: Option<&Path>) {
+        self.shared.changed(path)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        self.shared.inner.metadata(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Shared, WatchingFileSystem, MAX_VERSIONS};
+    use fs::testing::MockFileSystem;
+    use fs::{CachingFileSystem, FileSystem, LocalFileSystem};
+    use std::collections::HashMap;
+    use std::env;
+    use std::fs;
+    use std::io::Read;
+    use std::path::Path;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::Duration;
+
+    fn read(fs: &FileSystem, path: &str) -> String {
+        let mut s = String::new();
+        fs.open(Path::new(path), None)
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        s
+    }
+
+    #[test]
+    fn test_watching_file_system() {
+        let dir = env::temp_dir().join("rocket_static_fs_test_watch");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("css")).unwrap();
+        fs::write(dir.join("css/app.css"), "body {}").unwrap();
+
+        let cached = CachingFileSystem::new(LocalFileSystem::new(&dir), 1024 * 1024);
+        let fs = WatchingFileSystem::with_delay(cached, &dir, Duration::from_millis(10)).unwrap();
+        assert_eq!(read(&fs, "css/app.css"), "body {}");
+        assert_eq!(fs.etag(Path::new("css/app.css")), None);
+
+        fs::write(dir.join("css/app.css"), "body { margin: 0 }").unwrap();
+        for _ in 0..100 {
+            if read(&fs, "css/app.css") != "body {}" {
+                break;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(read(&fs, "css/app.css"), "body { margin: 0 }");
+        let etag = fs.etag(Path::new("css/app.css")).unwrap();
+        assert!(etag.starts_with("W/\"12-"));
+
+        fs.changed(Some(Path::new("css")));
+        assert_ne!(fs.etag(Path::new("css/app.css")).unwrap(), etag);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_bounded_versions() {
+        let shared = Shared {
+            inner: MockFileSystem::new(),
+            versions: Mutex::new(HashMap::new()),
+        };
+        for _ in 0..3 {
+            shared.changed(Some(Path::new("css/app.css")));
+        }
+        shared.changed(Some(Path::new("css")));
+        assert_eq!(shared.version(Path::new("css/app.css")), 4);
+
+        // A rescan collapses the versions without reusing one
+        shared.changed(None);
+        assert_eq!(shared.versions.lock().unwrap().len(), 1);
+        assert_eq!(shared.version(Path::new("css/app.css")), 5);
+        assert_eq!(shared.version(Path::new("index.html")), 5);
+
+        for i in 0..MAX_VERSIONS {
+            shared.changed(Some(Path::new(&format!("{}.html", i))));
+        }
+        assert_eq!(shared.versions.lock().unwrap().len(), 1);
+        assert!(shared.version(Path::new("0.html")) > 6);
+    }
+}
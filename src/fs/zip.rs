@@ -0,0 +1,331 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::read::DeflateDecoder;
+use fs::embedded::collect_dirs;
+use fs::reader::EntryReader;
+use fs::{DirEntry, FileSystem, Metadata, ReadSeek};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zip::{self, CompressionMethod, ZipArchive};
+use Error;
+
+/// Provides a FileSystem serving the files of a ZIP archive.
+///
+/// The archive is indexed once on construction, files are read straight from the archive
+/// afterwards. Stored and deflated entries are supported. Deflated entries are sent to clients
+/// accepting gzip as they are, without unpacking and compressing them again. Requires the `zip`
+/// feature.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::fs::ZipFileSystem;
+///
+/// let fs = ZipFileSystem::open("content.zip").unwrap();
+/// ```
+pub struct ZipFileSystem {
+    source: Source,
+    files: HashMap<String, ZipEntry>,
+    /// Maps the path of every directory to the names of its children, the root is "".
+    dirs: HashMap<String, BTreeSet<String>>,
+}
+
+enum Source {
+    File(PathBuf),
+    Memory(Arc<[u8]>),
+}
+
+struct ZipEntry {
+    /// The position of the (possibly compressed) data of the file in the archive.
+    offset: u64,
+    compressed_size: u64,
+    size: u64,
+    deflated: bool,
+    crc32: u32,
+    modified: SystemTime,
+}
+
+impl ZipFileSystem {
+    /// Indexes the ZIP archive at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let files = index(File::open(path)?)?;
+        Ok(ZipFileSystem::new(Source::File(path.to_owned()), files))
+    }
+
+    /// Indexes a ZIP archive held in memory.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        let files = index(Cursor::new(&bytes[..]))?;
+        Ok(ZipFileSystem::new(Source::Memory(Arc::from(bytes)), files))
+    }
+
+    fn new(source: Source, files: HashMap<String, ZipEntry>) -> Self {
+        let dirs = collect_dirs(files.keys());
+        ZipFileSystem {
+            source,
+            files,
+            dirs,
+        }
+    }
+
+    fn entry(&self, path: &Path) -> Result<&ZipEntry, Error> {
+        path.to_str()
+            .and_then(|path| self.files.get(path))
+            .ok_or(Error::NotFound)
+    }
+
+    /// Returns a reader for the raw data of the entry, positioned at its start.
+    fn raw(&self, entry: &ZipEntry) -> Result<EntryReader<Box<ReadSeek>>, Error> {
+        let inner: Box<ReadSeek> = match self.source {
+            Source::File(ref path) => Box::new(File::open(path)?),
+            Source::Memory(ref bytes) => Box::new(Cursor::new(bytes.clone())),
+        };
+        EntryReader::new(inner, entry.offset, entry.compressed_size).map_err(Error::from)
+    }
+}
+
+impl FileSystem for ZipFileSystem {
+    fn is_file(&self, path: &Path) -> bool {
+        self.entry(path).is_ok()
+    }
+
+    fn last_modified(&self, path: &Path) -> Result<SystemTime, Error> {
+        Ok(self.entry(path)?.modified)
+    }
+
+    fn size(&self, path: &Path) -> Result<u64, Error> {
+        Ok(self.entry(path)?.size)
+    }
+
+    fn open(&self, path: &Path, start: Option<u64>) -> Result<Box<Read>, Error> {
+        let entry = self.entry(path)?;
+        let mut raw = self.raw(entry)?;
+        if !entry.deflated {
+            if let Some(start) = start {
+                raw.seek(SeekFrom::Start(start))?;
+            }
+            return Ok(Box::new(raw));
+        }
+
+        // Deflated data can't be read at random positions, so we skip to the start
+        let mut reader = DeflateDecoder::new(raw);
+        if let Some(start) = start {
+            io::copy(&mut (&mut reader).take(start), &mut io::sink())?;
+        }
+        Ok(Box::new(reader))
+    }
+
+    fn path_valid(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    fn open_seek(&self, path: &Path) -> Result<Box<ReadSeek>, Error> {
+        let entry = self.entry(path)?;
+        if entry.deflated {
+            return Err(Error::backend("deflated entries can't be seeked"));
+        }
+        Ok(Box::new(self.raw(entry)?))
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.to_str()
+            .map_or(false, |path| self.dirs.contains_key(path))
+    }
+
+    fn read_dir(&self, path: &Path) -> Result<Vec<DirEntry>, Error> {
+        let dir = path.to_str().ok_or(Error::NotFound)?;
+        let children = self.dirs.get(dir).ok_or(Error::NotFound)?;
+
+        let entries = children
+            .iter()
+            .map(|name| {
+                let child = if dir.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{}", dir, name)
+                };
+                match self.files.get(&child) {
+                    Some(entry) => DirEntry {
+                        name: name.clone(),
+                        is_dir: false,
+                        size: entry.size,
+                        modified: Some(entry.modified),
+                    },
+                    None => DirEntry {
+                        name: name.clone(),
+                        is_dir: true,
+                        size: 0,
+                        modified: None,
+                    },
+                }
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    fn etag(&self, path: &Path) -> Option<String> {
+        self.entry(path)
+            .ok()
+            .map(|entry| format!("\"{:08x}-{:x}\"", entry.crc32, entry.size))
+    }
+
+    fn open_encoded(&self, path: &Path, encoding: &str) -> Option<Box<Read>> {
+        let entry = match self.entry(path) {
+            Ok(entry) if entry.deflated && encoding == "gzip" => entry,
+            _ => return None,
+        };
+        let raw = self.raw(entry).ok()?;
+
+        // A gzip stream is the raw deflate stream framed by a header and a trailer holding the
+        // CRC-32 and size of the data, which the archive knows already
+        let mut trailer = Vec::with_capacity(8);
+        trailer.write_u32::<LittleEndian>(entry.crc32).ok()?;
+        trailer.write_u32::<LittleEndian>(entry.size as u32).ok()?;
+        Some(Box::new(
+            Cursor::new(GZIP_HEADER).chain(raw).chain(Cursor::new(trailer)),
+        ))
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Metadata, Error> {
+        if let Ok(entry) = self.entry(path) {
+            return Ok(Metadata {
+                size: entry.size,
+                modified: Some(entry.modified),
+                is_file: true,
+                is_dir: false,
+            });
+        }
+
+        if self.is_dir(path) {
+            Ok(Metadata {
+                size: 0,
+                modified: None,
+                is_file: false,
+                is_dir: true,
+            })
+        } else {
+            Err(Error::NotFound)
+        }
+    }
+}
+
+/// A gzip header without file name or modification date, announcing deflated data.
+const GZIP_HEADER: &[u8] = &[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+
+/// Reads the positions of all stored and deflated files of a ZIP archive.
+fn index<R: Read + Seek>(reader: R) -> Result<HashMap<String, ZipEntry>, Error> {
+    let mut archive = ZipArchive::new(reader).map_err(|err| Error::backend(err.to_string()))?;
+    let mut files = HashMap::new();
+
+    for i in 0..archive.len() {
+        let file = archive
+            .by_index(i)
+            .map_err(|err| Error::backend(err.to_string()))?;
+        if file.name().ends_with('/') {
+            continue;
+        }
+
+        let deflated = match file.compression() {
+            CompressionMethod::Stored => false,
+            CompressionMethod::Deflated => true,
+            method => {
+                warn!(
+                    "skipping {} in ZIP archive: unsupported compression {:?}",
+                    file.name(),
+                    method
+                );
+                continue;
+            }
+        };
+
+        let path = file.name().trim_left_matches("./").trim_left_matches('/').to_string();
+        files.insert(
+            path,
+            ZipEntry {
+                offset: file.data_start(),
+                compressed_size: file.compressed_size(),
+                size: file.size(),
+                deflated,
+                crc32: file.crc32(),
+                modified: system_time(&file.last_modified()),
+            },
+        );
+    }
+
+    Ok(files)
+}
+
+/// Converts the timestamp of an entry, which has no time zone, taking it as UTC.
+fn system_time(time: &zip::DateTime) -> SystemTime {
+    let date = NaiveDate::from_ymd_opt(
+        i32::from(time.year()),
+        u32::from(time.month()),
+        u32::from(time.day()),
+    );
+    let time = date.and_then(|date| {
+        date.and_hms_opt(
+            u32::from(time.hour()),
+            u32::from(time.minute()),
+            u32::from(time.second()),
+        )
+    });
+    time.map_or(UNIX_EPOCH, |time| {
+        SystemTime::from(DateTime::<Utc>::from_utc(time, Utc))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ZipFileSystem;
+    use flate2::read::GzDecoder;
+    use fs::FileSystem;
+    use std::io::{Cursor, Read, Write};
+    use std::path::Path;
+    use zip::write::FileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+
+    fn archive() -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        writer.start_file("index.html", stored).unwrap();
+        writer.write_all(b"<h1>Hello</h1>").unwrap();
+
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        writer.start_file("css/app.css", deflated).unwrap();
+        writer.write_all(b"body { color: red; }").unwrap();
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_zip_file_system() {
+        let fs = ZipFileSystem::from_bytes(archive()).unwrap();
+        assert!(fs.is_file(Path::new("index.html")));
+        assert!(fs.is_file(Path::new("css/app.css")));
+        assert!(fs.is_dir(Path::new("css")));
+        assert_eq!(fs.size(Path::new("css/app.css")).unwrap(), 20);
+
+        let mut s = String::new();
+        fs.open(Path::new("index.html"), Some(4))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "Hello</h1>");
+
+        let mut s = String::new();
+        fs.open(Path::new("css/app.css"), Some(5))
+            .unwrap()
+            .read_to_string(&mut s)
+            .unwrap();
+        assert_eq!(s, "{ color: red; }");
+
+        assert!(fs.open_encoded(Path::new("index.html"), "gzip").is_none());
+        let encoded = fs.open_encoded(Path::new("css/app.css"), "gzip").unwrap();
+        let mut s = String::new();
+        GzDecoder::new(encoded).read_to_string(&mut s).unwrap();
+        assert_eq!(s, "body { color: red; }");
+    }
+}
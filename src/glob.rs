@@ -0,0 +1,105 @@
+//! Minimal glob patterns for matching request and FileSystem paths.
+
+use regex::{self, Regex};
+use std::fmt;
+
+/// A compiled glob pattern.
+///
+/// Supports `*` (anything but a slash), `**` (anything, including slashes), `?` (a single
+/// character but a slash) and `{a,b}` (one of the alternatives). Patterns are matched against
+/// whole paths without a leading slash, e.g. `**/*.key` or `internal/**`.
+#[derive(Clone)]
+pub struct Glob {
+    pattern: String,
+    regex: Regex,
+}
+
+impl Glob {
+    /// Compiles the given pattern.
+    pub fn new(pattern: &str) -> Result<Glob, regex::Error> {
+        let trimmed = pattern.trim_left_matches('/');
+        let mut re = String::from("^");
+        let mut chars = trimmed.chars().peekable();
+        let mut in_alternatives = false;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '*' => {
+                    if chars.peek() == Some(&'*') {
+                        chars.next();
+                        if chars.peek() == Some(&'/') {
+                            chars.next();
+                            re.push_str("(?:.*/)?");
+                        } else {
+                            re.push_str(".*");
+                        }
+                    } else {
+                        re.push_str("[^/]*");
+                    }
+                }
+                '?' => re.push_str("[^/]"),
+                '{' if !in_alternatives => {
+                    in_alternatives = true;
+                    re.push_str("(?:");
+                }
+                '}' if in_alternatives => {
+                    in_alternatives = false;
+                    re.push(')');
+                }
+                ',' if in_alternatives => re.push('|'),
+                _ => re.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        re.push('$');
+
+        Ok(Glob {
+            pattern: pattern.to_string(),
+            regex: Regex::new(&re)?,
+        })
+    }
+
+    /// Returns true if the path matches the pattern.
+    pub fn is_match(&self, path: &str) -> bool {
+        self.regex.is_match(path.trim_left_matches('/'))
+    }
+
+    /// Returns the pattern the glob was compiled from.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+}
+
+impl fmt::Debug for Glob {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Glob({:?})", self.pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob() {
+        let glob = Glob::new("**/*.key").unwrap();
+        assert!(glob.is_match("server.key"));
+        assert!(glob.is_match("certs/server.key"));
+        assert!(glob.is_match("/certs/nested/server.key"));
+        assert!(!glob.is_match("server.keys"));
+
+        let glob = Glob::new("internal/**").unwrap();
+        assert!(glob.is_match("internal/a"));
+        assert!(glob.is_match("internal/a/b.txt"));
+        assert!(!glob.is_match("public/internal/a"));
+
+        let glob = Glob::new("*.{js,css}").unwrap();
+        assert!(glob.is_match("app.js"));
+        assert!(glob.is_match("app.css"));
+        assert!(!glob.is_match("js/app.js"));
+        assert!(!glob.is_match("app.json"));
+
+        let glob = Glob::new("file?.txt").unwrap();
+        assert!(glob.is_match("file1.txt"));
+        assert!(!glob.is_match("file10.txt"));
+    }
+}
@@ -0,0 +1,142 @@
+//! Serving files from a route instead of a fairing.
+
+use fs::FileSystem;
+use rocket::handler::{Handler, Outcome};
+use rocket::http::{Method, Status};
+use rocket::{Data, Request, Response, Route};
+use std::sync::Arc;
+use {normalize_prefix, StaticFileServer};
+
+/// The default rank of the routes, so they're tried after most routes of the application.
+const DEFAULT_RANK: isize = 10;
+
+/// A handler serving files of a StaticFileServer from the path it's mounted at.
+///
+/// Unlike the fairing, which only gets to see requests no route handled, the routes take part
+/// in the regular routing of Rocket. Requests for which there's nothing to serve are forwarded
+/// to the next matching route. The prefixes and file mappings of the server aren't used, files
+/// are looked up relative to the mount point.
+///
+/// # Example
+///
+/// ```
+/// extern crate rocket;
+/// extern crate rocket_static_fs;
+///
+/// use rocket_static_fs::{fs, StaticFileServer, StaticFiles};
+///
+/// fn main() {
+///     let server = StaticFileServer::new(fs::LocalFileSystem::new("static"), "/").unwrap();
+///     rocket::ignite().mount("/assets", StaticFiles::new(server).rank(5));
+/// }
+/// ```
+pub struct StaticFiles<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    server: Arc<StaticFileServer<T>>,
+    rank: isize,
+}
+
+impl<T> StaticFiles<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    pub fn new(server: StaticFileServer<T>) -> Self {
+        StaticFiles {
+            server: Arc::new(server),
+            rank: DEFAULT_RANK,
+        }
+    }
+
+    /// Sets the rank of the routes, defaults to 10.
+    ///
+    /// Routes with a lower rank are tried first.
+    pub fn rank(mut self, rank: isize) -> Self {
+        self.rank = rank;
+        self
+    }
+}
+
+impl<T> Clone for StaticFiles<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    fn clone(&self) -> Self {
+        StaticFiles {
+            server: self.server.clone(),
+            rank: self.rank,
+        }
+    }
+}
+
+impl<T: 'static> Handler for StaticFiles<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    fn handle<'r>(&self, request: &'r Request, data: Data) -> Outcome<'r> {
+        let base = match request.route() {
+            Some(route) => normalize_prefix(route.base.path()),
+            None => return Outcome::Forward(data),
+        };
+
+        let _span = trace_span!(
+            "static_fs.request",
+            method = %request.method(),
+            uri = %request.uri()
+        );
+
+        // Start out like the fairing does, with nothing found
+        let mut response = Response::new();
+        response.set_status(Status::NotFound);
+        self.server.handle(request, &mut response, &base);
+
+        if response.status() == Status::NotFound && response.body().is_none() {
+            Outcome::Forward(data)
+        } else {
+            self.server.record(request, &response, &base);
+            Outcome::Success(response)
+        }
+    }
+}
+
+impl<T: 'static> Into<Vec<Route>> for StaticFiles<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    fn into(self) -> Vec<Route> {
+        vec![
+            Route::ranked(self.rank, Method::Get, "/", self.clone()),
+            Route::ranked(self.rank, Method::Get, "/<path..>", self),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StaticFiles;
+    use fs::LocalFileSystem;
+    use rocket;
+    use rocket::http::Status;
+    use rocket::local::Client;
+    use StaticFileServer;
+
+    #[test]
+    fn test_routes() {
+        let server = StaticFileServer::new(LocalFileSystem::new("testdata"), "/").unwrap();
+        let rocket = rocket::ignite().mount("/files", StaticFiles::new(server));
+        let client = Client::new(rocket).expect("valid rocket");
+
+        let mut resp = client.get("/files/inner/other.txt").dispatch();
+        assert_eq!(resp.status(), Status::Ok);
+        assert!(resp.body_bytes().is_some());
+
+        let resp = client.get("/files/inner").dispatch();
+        assert_eq!(resp.status(), Status::MovedPermanently);
+        assert_eq!(resp.headers().get_one("Location"), Some("/files/inner/"));
+
+        // Nothing to serve, so the request is forwarded and ends up with Rocket's 404
+        let resp = client.get("/files/missing.txt").dispatch();
+        assert_eq!(resp.status(), Status::NotFound);
+    }
+}
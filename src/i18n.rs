@@ -0,0 +1,136 @@
+//! Negotiation of localized file variants via the `Accept-Language` header.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+/// The most languages of a header considered, each of them costs a lookup per request.
+const MAX_LANGUAGES: usize = 8;
+
+/// How localized variants of a file are named.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageScheme {
+    /// The language is inserted before the extension, e.g. `docs/index.de.html`.
+    Suffix,
+    /// The language is a top level directory, e.g. `de/docs/index.html`.
+    Directory,
+}
+
+impl LanguageScheme {
+    /// Returns the path of the variant of the file for the given language.
+    pub fn localize(&self, path: &str, language: &str) -> String {
+        match *self {
+            LanguageScheme::Suffix => {
+                let file_start = path.rfind('/').map_or(0, |i| i + 1);
+                match path[file_start..].rfind('.') {
+                    Some(dot) if dot > 0 => {
+                        let dot = file_start + dot;
+                        format!("{}.{}{}", &path[..dot], language, &path[dot..])
+                    }
+                    _ => format!("{}.{}", path, language),
+                }
+            }
+            LanguageScheme::Directory => format!("{}/{}", language, path),
+        }
+    }
+}
+
+/// Parses an `Accept-Language` header into language tags ordered by preference.
+///
+/// Tags are lowercased. For tags with a region like `de-ch`, the primary language `de` is
+/// added as less preferred fallback. The wildcard `*`, tags with `q=0` and anything which
+/// isn't a well-formed tag are skipped, as tags end up in file paths. Only the 8 most
+/// preferred tags are considered.
+pub fn parse_accept_language(header: &str) -> Vec<String> {
+    let mut weighted: Vec<(String, f32)> = header
+        .split(',')
+        .filter_map(|part| {
+            let mut parts = part.split(';');
+            let tag = parts.next()?.trim().to_lowercase();
+            let quality = parts
+                .filter_map(|param| {
+                    let param = param.trim();
+                    if param.starts_with("q=") {
+                        param[2..].parse().ok()
+                    } else {
+                        None
+                    }
+                })
+                .next()
+                .unwrap_or(1.0);
+            if !is_valid_tag(&tag) || quality <= 0.0 {
+                None
+            } else {
+                Some((tag, quality))
+            }
+        })
+        .collect();
+
+    // A stable sort keeps the order of the header for equal qualities
+    weighted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+    weighted.truncate(MAX_LANGUAGES);
+
+    let mut seen = HashSet::new();
+    let mut languages: Vec<String> = Vec::new();
+    for &(ref tag, _) in &weighted {
+        if seen.insert(tag.as_str()) {
+            languages.push(tag.clone());
+        }
+    }
+    for &(ref tag, _) in &weighted {
+        if let Some(i) = tag.find('-') {
+            if seen.insert(&tag[..i]) {
+                languages.push(tag[..i].to_string());
+            }
+        }
+    }
+    languages
+}
+
+/// Returns true for tags like `de` or `zh-hant-tw`: alphanumeric subtags of up to 8
+/// characters, separated by dashes.
+fn is_valid_tag(tag: &str) -> bool {
+    tag.split('-').all(|subtag| {
+        !subtag.is_empty()
+            && subtag.len() <= 8
+            && subtag.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localize() {
+        let scheme = LanguageScheme::Suffix;
+        assert_eq!(scheme.localize("index.html", "de"), "index.de.html");
+        assert_eq!(scheme.localize("docs/index.html", "de"), "docs/index.de.html");
+        assert_eq!(scheme.localize("v1.0/README", "de"), "v1.0/README.de");
+        assert_eq!(scheme.localize(".htaccess", "de"), ".htaccess.de");
+
+        let scheme = LanguageScheme::Directory;
+        assert_eq!(scheme.localize("docs/index.html", "de"), "de/docs/index.html");
+    }
+
+    #[test]
+    fn test_parse_accept_language() {
+        assert_eq!(
+            parse_accept_language("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7, *;q=0.5"),
+            vec!["fr-ch", "fr", "en", "de"]
+        );
+        assert_eq!(
+            parse_accept_language("en;q=0.5, de-AT"),
+            vec!["de-at", "en", "de"]
+        );
+        assert_eq!(parse_accept_language("en, de;q=0"), vec!["en"]);
+        assert_eq!(
+            parse_accept_language("../secret, de/x, de-, toolongtag, en"),
+            vec!["en"]
+        );
+
+        let many: Vec<String> = (0..1000).map(|i| format!("x{}", i)).collect();
+        let languages = parse_accept_language(&many.join(", "));
+        assert_eq!(languages.len(), 8);
+        assert_eq!(languages[7], "x7");
+    }
+}
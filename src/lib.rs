@@ -6,9 +6,9 @@
 //! This example works for sharing the src folder of your app.
 //!
 //! ```
-//! #![feature(plugin)]
-//! #![plugin(rocket_codegen)]
+//! #![feature(proc_macro_hygiene, decl_macro)]
 //!
+//! #[macro_use]
 //! extern crate rocket;
 //! extern crate rocket_static_fs;
 //!
@@ -29,60 +29,136 @@
 //! ```
 
 extern crate chrono;
+extern crate filetime;
 extern crate flate2;
 extern crate mime_guess;
 extern crate regex;
 extern crate rocket;
 #[macro_use]
 extern crate lazy_static;
+#[macro_use]
+extern crate log;
 extern crate byteorder;
+#[macro_use]
+extern crate serde_json;
+extern crate sha2;
 extern crate walkdir;
+#[cfg(feature = "metrics")]
+extern crate prometheus;
+#[cfg(feature = "encryption")]
+extern crate aes_gcm;
+#[cfg(feature = "sniff")]
+extern crate infer;
+#[cfg(feature = "brotli")]
+extern crate brotli;
+#[cfg(feature = "git")]
+extern crate git2;
+#[cfg(any(feature = "s3", feature = "encryption"))]
+extern crate hex;
+#[cfg(any(feature = "s3", feature = "encryption"))]
+extern crate hmac;
+#[cfg(feature = "include_dir")]
+extern crate include_dir;
+#[cfg(feature = "gcs")]
+extern crate jsonwebtoken;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+#[cfg(feature = "watch")]
+extern crate notify;
+#[cfg(any(feature = "s3", feature = "gcs", feature = "azure", feature = "http"))]
+extern crate reqwest;
+#[cfg(feature = "sqlite")]
+extern crate rusqlite;
+#[cfg(feature = "sftp")]
+extern crate ssh2;
+#[cfg(feature = "rust-embed")]
+extern crate rust_embed;
+#[cfg(feature = "tar")]
+extern crate tar;
+#[cfg(feature = "tracing")]
+extern crate tracing;
+#[cfg(feature = "zip")]
+extern crate zip;
+
+#[macro_use]
+mod trace;
 
+mod access_log;
+mod builder;
+mod config;
+mod error;
+mod events;
 pub mod fs;
+mod glob;
+mod handler;
+mod i18n;
+pub mod listing;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod netlify;
+mod path;
+mod responder;
+mod rewrite;
+mod serve;
+mod vhost;
 
-use chrono::prelude::*;
-use flate2::read::GzEncoder;
-use flate2::Compression;
+use builder::Options;
 use fs::FileSystem;
-use mime_guess::get_mime_type;
 use regex::Regex;
 use rocket::fairing::{Fairing, Info, Kind};
-use rocket::http::Header;
 use rocket::http::Method;
 use rocket::http::Status;
+use rocket::http::uri::Uri;
 use rocket::{Request, Response};
+use std::cmp;
 use std::error::Error as StdError;
 use std::fmt;
-use std::io::Read;
+use std::io::{Cursor, Read};
 use std::path::Path;
 use std::str::FromStr;
 
+pub use access_log::{AccessLog, AccessLogFormat};
+pub use builder::StaticFileServerBuilder;
+pub use error::Error;
+pub use events::{ErrorEvent, ServeEvent};
+pub use glob::Glob;
+pub use handler::StaticFiles;
+pub use i18n::LanguageScheme;
+#[cfg(feature = "metrics")]
+pub use metrics::Metrics;
+pub use responder::StaticFile;
+pub use rewrite::RewriteRule;
+pub use serve::serve_file;
+pub use vhost::VirtualHosts;
+
 lazy_static! {
     static ref RANGE_HEADER_REGEX: Regex = Regex::new(r#"(.*?)=(\d+)-(\d+)"#).unwrap();
+    static ref OPEN_RANGE_HEADER_REGEX: Regex = Regex::new(r#"^(.*?)=(\d*)-(\d*)$"#).unwrap();
 }
 
 const LAST_MODIFIED_DATE_FORMAT: &str = "%a, %d %b %Y %H:%M:%S GMT";
 
+/// The error returned when a `Range` header can't be handled.
 #[derive(Debug)]
-struct Error {
+struct RangeError {
     description: String,
 }
 
-impl Error {
+impl RangeError {
     fn new(description: &str) -> Self {
-        Error {
+        RangeError {
             description: description.to_string(),
         }
     }
 }
 
-impl StdError for Error {
+impl StdError for RangeError {
     fn description(&self) -> &str {
         &self.description
     }
 }
 
-impl fmt::Display for Error {
+impl fmt::Display for RangeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         f.write_str(&self.description)
     }
@@ -98,6 +174,38 @@ struct Range {
 }
 
 impl Range {
+    /// Parses a `Range` header for a file of the given size.
+    ///
+    /// Besides `bytes=5-10`, open ended (`bytes=5-`) and suffix ranges (`bytes=-5`) are
+    /// supported. Ends beyond the end of the file are clamped to the end of the file.
+    fn parse_for_size(s: &str, size: u64) -> Result<Self, Box<StdError>> {
+        let invalid = || -> Box<StdError> { Box::new(RangeError::new("invalid range header")) };
+        let matches = OPEN_RANGE_HEADER_REGEX.captures(s.trim()).ok_or_else(invalid)?;
+
+        let (start, end): (u64, u64) = match (&matches[2], &matches[3]) {
+            ("", "") => return Err(invalid()),
+            ("", suffix) => {
+                let suffix: u64 = suffix.parse()?;
+                if suffix == 0 {
+                    return Err(invalid());
+                }
+                (size.saturating_sub(suffix), size.saturating_sub(1))
+            }
+            (start, "") => (start.parse()?, size.saturating_sub(1)),
+            (start, end) => (start.parse()?, end.parse()?),
+        };
+
+        if end < start {
+            return Err(invalid());
+        }
+
+        Ok(Range {
+            typ: matches[1].to_string(),
+            start,
+            end: cmp::min(end, size.saturating_sub(1)),
+        })
+    }
+
     fn len(&self) -> u64 {
         self.end - self.start + 1
     }
@@ -119,183 +227,586 @@ impl FromStr for Range {
                     end,
                 })
             }
-            None => Err(Box::new(Error::new("invalid range header"))),
+            None => Err(Box::new(RangeError::new("invalid range header"))),
         }
     }
 }
 
 /// StaticFileServer is your fairing for the static file server.
+///
+/// Use `StaticFileServer::builder` to configure it.
 pub struct StaticFileServer<T>
 where
     T: FileSystem + Sized + Send + Sync,
 {
     fs: T,
-    prefix: String,
+    options: Options,
 }
 
 impl<T> StaticFileServer<T>
 where
     T: FileSystem + Sized + Send + Sync,
 {
-    /// Constructs a new StaticFileServer fairing.
-    ///
-    /// `path` is local directory to serve from.
-    /// `prefix` is the prefix the serve from.
+    /// Constructs a new StaticFileServer fairing with the default options.
     ///
-    /// You can set a prefix of /assets and only requests to /assets/* will be served.
+    /// `prefix` is the prefix to serve from, e.g. with a prefix of /assets only requests to
+    /// /assets/* will be served. Use `builder` to set any other options.
     pub fn new(fs: T, prefix: &str) -> Result<Self, Box<StdError>> {
-        let mut prefix = prefix.to_string();
-        if !prefix.ends_with('/') {
-            prefix.push_str("/");
+        Ok(Self::builder(fs).prefix(prefix).build())
+    }
+
+    /// Returns a builder to configure a new StaticFileServer fairing.
+    pub fn builder(fs: T) -> StaticFileServerBuilder<T> {
+        StaticFileServerBuilder::new(fs)
+    }
+
+    /// Returns the fingerprinted path of the file at `path` as listed in the manifest given to
+    /// `fingerprint_manifest`, e.g. `css/app.1a2b3c4d5e6f7a8b.css` for `css/app.css`.
+    ///
+    /// Useful to render links in templates before the server is attached.
+    pub fn fingerprinted(&self, path: &str) -> Option<&str> {
+        self.options
+            .fingerprints
+            .get(path.trim_left_matches('/'))
+            .map(|path| path.as_str())
+    }
+
+    /// Returns true if files with the extension of the path may be served.
+    fn is_allowed_extension(&self, path: &str) -> bool {
+        match self.options.allowed_extensions {
+            Some(ref allowed) => Path::new(path)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| allowed.contains(&ext.to_lowercase())),
+            None => true,
         }
+    }
 
-        Ok(StaticFileServer { fs, prefix })
+    /// Returns true if the path must be treated as if it doesn't exist.
+    fn is_hidden(&self, path: &str) -> bool {
+        (self.options.hide_dotfiles && path::has_hidden_component(path))
+            || (self.options.netlify_files && (path == "_redirects" || path == "_headers"))
+            || self.options.excludes.iter().any(|glob| glob.is_match(path))
     }
-}
 
-impl<T: 'static> Fairing for StaticFileServer<T>
-where
-    T: FileSystem + Sized + Send + Sync,
-{
-    fn info(&self) -> Info {
-        Info {
-            name: "static_file_server",
-            kind: Kind::Response,
+    /// Like `is_hidden` for directories, which are hidden by globs matching their contents too,
+    /// e.g. `internal/**` hides `internal`.
+    fn is_hidden_dir(&self, path: &str) -> bool {
+        let contents = format!("{}/", path);
+        self.is_hidden(path) || self.options.excludes.iter().any(|glob| glob.is_match(&contents))
+    }
+
+    /// The status used for paths we refuse to serve.
+    fn deny_status(&self) -> Status {
+        if self.options.deny_with_not_found {
+            Status::NotFound
+        } else {
+            Status::Forbidden
         }
     }
 
-    fn on_response(&self, request: &Request, response: &mut Response) {
-        // Only handle requests which aren't otherwise handled.
-        if response.status() != Status::NotFound {
-            return;
+    /// Applies the configured deny status to an error status of the FileSystem.
+    fn error_status(&self, status: Status) -> Status {
+        if status == Status::Forbidden {
+            self.deny_status()
+        } else {
+            status
         }
+    }
 
-        // Only handle requests which include our prefix
-        let uri = request.uri().as_str();
-        if !((request.method() == Method::Get || request.method() == Method::Head)
-            && uri.starts_with(&self.prefix))
-        {
+    /// Applies the first matching rule of the `_redirects` file.
+    ///
+    /// Rewrites change `req_path`, returns true if the response was already handled.
+    fn apply_redirects(
+        &self,
+        request: &Request,
+        response: &mut Response,
+        prefix: &str,
+        req_path: &mut String,
+    ) -> bool {
+        let request_path = format!("/{}", req_path);
+        let exists = self.fs.is_file(Path::new(req_path.as_str()));
+        for rule in &self.options.redirects {
+            if exists && !rule.force {
+                continue;
+            }
+
+            let target = match rule.target(&request_path) {
+                Some(target) => target,
+                None => continue,
+            };
+
+            if !rule.is_rewrite() {
+                let location = if target.starts_with('/') {
+                    format!("{}{}", prefix, &target[1..])
+                } else {
+                    target
+                };
+                let status = Status::from_code(rule.status).unwrap_or(Status::MovedPermanently);
+                redirect(request, response, status, location);
+                return true;
+            }
+
+            // Rewrites to other hosts aren't supported, we're no proxy
+            if !target.starts_with('/') {
+                continue;
+            }
+
+            *req_path = match path::normalize(&target) {
+                Some(p) => p,
+                None => {
+                    self.fail(request, response, self.deny_status());
+                    return true;
+                }
+            };
+
+            if rule.status == 404 {
+                self.serve(request, response, req_path, Status::NotFound);
+                return true;
+            }
+            return false;
+        }
+        false
+    }
+
+    /// Serves the file at `req_path` with the given status.
+    ///
+    /// Conditional and range requests are only honored for `200 OK` responses.
+    fn serve(&self, request: &Request, response: &mut Response, req_path: &str, status: Status) {
+        // Whatever path we ended up with, hidden files and files with extensions which aren't
+        // allowed are never served
+        let denied = self.is_hidden(req_path) || !self.is_allowed_extension(req_path);
+        if status == Status::Ok && denied {
+            self.fail(request, response, Status::NotFound);
             return;
         }
 
-        // Strip out the prefix to get the normal file path
-        let mut req_path = uri.replacen(&self.prefix, "", 1);
+        trace_event!(path = req_path, status = status.code, "resolved file");
 
-        // Fail on paths outside of the given path
-        if !self.fs.path_valid(&req_path) {
-            response.set_status(Status::Forbidden);
-            return;
+        // Serve the variant of the file in the language the client prefers, if there is one
+        let localized;
+        let req_path = match self.localize(request, response, req_path) {
+            Some(path) => {
+                localized = path;
+                localized.as_str()
+            }
+            None => req_path,
         };
 
-        // Fail if it is no file
-        // TODO: Support directory listing
-        if !self.fs.is_file(&req_path) {
-            //response.set_status(Status::NotFound);
-            req_path = "index.html".to_string();
-            //return;
+        let served = match self.options.hot_cache {
+            Some(ref cache) => serve::serve_with_status(
+                &cache.cached(&self.fs),
+                request,
+                response,
+                req_path,
+                status,
+                &self.options.serve,
+            ),
+            None => serve::serve_with_status(
+                &self.fs,
+                request,
+                response,
+                req_path,
+                status,
+                &self.options.serve,
+            ),
         };
+        match served {
+            Ok(bytes) => {
+                events::set_body_bytes(request, bytes);
+                if status != Status::Ok {
+                    return;
+                }
 
-        // Let's set the mime type here, this can't possibly go wrong anymore *cough*.
-        {
-            let file_extension = Path::new(&req_path).extension().unwrap().to_str().unwrap();
-            let mime = get_mime_type(file_extension).to_string();
-            response.set_raw_header("Content-Type", mime);
-        };
+                if !response.headers().contains("Cache-Control") {
+                    if let Some(cache_control) = self.cache_control(req_path) {
+                        response.set_raw_header("Cache-Control", cache_control);
+                    }
+                }
 
-        // Get the file modification date and the If-Modified-Since header value
-        let modified = self.fs.last_modified(&req_path).expect("no modified since");
-        let modified: DateTime<Utc> = DateTime::from(modified);
-        let if_modified_since = request.headers().get("If-Modified-Since").next();
-
-        // Only on a GET request: If the If-Modified-Since header and the modified time of the file are the same, we
-        // respond with a 304 here
-        if request.method() == Method::Get {
-            if let Some(time) = if_modified_since {
-                if let Ok(time) = Utc.datetime_from_str(&time, LAST_MODIFIED_DATE_FORMAT) {
-                    let duration: chrono::Duration = time.signed_duration_since(modified);
-                    if duration.num_seconds() == 0 {
-                        response.set_status(Status::NotModified);
-                        return;
-                    };
-                };
+                if let Some(ref on_serve) = self.options.on_serve {
+                    on_serve(&ServeEvent {
+                        uri: request.uri().path(),
+                        path: req_path,
+                        status: response.status(),
+                        bytes,
+                        duration: events::started(request).elapsed(),
+                    });
+                }
+            }
+            Err(error_status) => {
+                trace_event!(status = error_status.code, "serving failed");
+                let error_status = self.error_status(error_status);
+                self.fail_serving(request, response, status, error_status);
+            }
+        }
+    }
+
+    /// Returns the `Cache-Control` header for the file at `path`, if any.
+    fn cache_control(&self, path: &str) -> Option<String> {
+        if self.options.fingerprinted.contains(path) {
+            Some("public, max-age=31536000, immutable".to_string())
+        } else if self.options.manifest.as_ref().map(|m| m.as_str()) == Some(path) {
+            Some("no-cache".to_string())
+        } else {
+            self.options.cache_max_age.map(|max_age| format!("max-age={}", max_age))
+        }
+    }
+
+    /// Returns the path of the localized variant of the file preferred by the client.
+    ///
+    /// Sets the `Content-Language` header if a variant is found.
+    fn localize(&self, request: &Request, response: &mut Response, path: &str) -> Option<String> {
+        let scheme = self.options.language_scheme?;
+        response.set_raw_header("Vary", "Accept-Language");
+
+        let header = request.headers().get_one("Accept-Language")?;
+        for language in i18n::parse_accept_language(header) {
+            let localized = match path::normalize(&scheme.localize(path, &language)) {
+                Some(localized) => localized,
+                None => continue,
             };
+            if !self.is_hidden(&localized)
+                && self.fs.path_valid(Path::new(&localized))
+                && self.fs.is_file(Path::new(&localized))
+            {
+                response.set_raw_header("Content-Language", language);
+                return Some(localized);
+            }
+        }
+        None
+    }
+
+    /// Responds with the given error status, serving its error page if there is one.
+    fn fail(&self, request: &Request, response: &mut Response, status: Status) {
+        if let Some(ref on_error) = self.options.on_error {
+            on_error(&ErrorEvent {
+                uri: request.uri().path(),
+                status,
+                duration: events::started(request).elapsed(),
+            });
         }
 
-        let size = match self.fs.size(&req_path) {
-            Ok(s) => s,
-            Err(_) => {
-                response.set_status(Status::Forbidden);
-                return;
+        match self.options.error_pages.get(&status.code) {
+            Some(page) if self.fs.is_file(Path::new(page)) => {
+                self.serve(request, response, page, status)
             }
-        };
+            _ => response.set_status(status),
+        }
+    }
 
-        // In case someone heads the file, we inform him about the content length and
-        // that we support byte ranges.
-        if request.method() == Method::Head {
-            response.set_header(Header::new("Accept-Ranges", "bytes"));
-            response.set_header(Header::new("Content-Length", format!("{}", size)));
-            response.set_status(Status::Ok);
-            return;
+    /// Fails while serving a file with the given status.
+    ///
+    /// Error pages are only served if we weren't serving an error page already.
+    fn fail_serving(
+        &self,
+        request: &Request,
+        response: &mut Response,
+        serving: Status,
+        status: Status,
+    ) {
+        if serving == Status::Ok {
+            self.fail(request, response, status);
+        } else {
+            response.set_status(serving);
+        }
+    }
+
+    /// Records a request handled by us in the access log and the metrics.
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn record(&self, request: &Request, response: &Response, prefix: &str) {
+        if let Some(ref log) = self.options.access_log {
+            access_log::record(log, request, response);
         }
 
-        // Let's parse the range header if it exists
-        let range_header = request.headers().get_one("Range").unwrap_or("");
+        #[cfg(feature = "metrics")]
+        {
+            if let Some(ref metrics) = self.options.metrics {
+                metrics::record(metrics, request, response, prefix);
+            }
+        }
+    }
+
+    /// Handles a request to a path below the given prefix, which has to end with a slash.
+    ///
+    /// This is shared by the fairing and the route handler. The response is expected to have a
+    /// 404 status, which is kept if there's nothing to serve.
+    fn handle(&self, request: &Request, response: &mut Response, prefix: &str) {
+        events::started(request);
 
-        // If we get a multipart range request, we more or less fail gracefully here for the moment.
-        // We simply set the range here to an error and send the complete file cause of that.
-        // TODO: Support multipart ranges
-        let range: Result<Range, Box<StdError>> = if range_header.contains(',') {
-            Err(Box::new(Error::new("multipart ranges not supported")))
+        let uri = request.uri().path();
+        let rel_path = if uri.starts_with(prefix) {
+            &uri[prefix.len()..]
         } else {
-            range_header.parse::<Range>()
+            if let Some(status) = self.options.directory_redirect {
+                redirect(request, response, status, prefix.to_string());
+                return;
+            }
+            ""
         };
 
-        // Set the start byte for the request
-        let start = match range {
-            Ok(ref range) => range.start,
-            Err(_) => 0,
+        // Strip out the prefix and decode what's left. Anything which doesn't look like a sane
+        // path is rejected before it gets anywhere near the FileSystem.
+        let req_path = match Uri::percent_decode(rel_path.as_bytes()) {
+            Ok(ref p) if path::is_sane(p) => p.to_string(),
+            _ => {
+                self.fail(request, response, Status::BadRequest);
+                return;
+            }
         };
 
-        // Otherwise we try to send the file, which should work since that size above should have
-        // worked as well.
-        match self.fs.open(&req_path, Some(start)) {
-            Ok(mut f) => {
-                response.set_header(Header::new("Accept-Ranges", "bytes"));
-                response.set_status(Status::Ok);
-                response.set_raw_header(
-                    "Last-Modified",
-                    modified.format(LAST_MODIFIED_DATE_FORMAT).to_string(),
-                );
+        // Normalize the path to get the normal file path. Paths escaping the root are treated
+        // just like paths the FileSystem considers invalid.
+        let mut req_path = match path::normalize(&req_path) {
+            Some(p) => p,
+            None => {
+                self.fail(request, response, self.deny_status());
+                return;
+            }
+        };
 
-                // If we got a range header, we set the corresponding headers here and
-                // set f to a limit reader so it will stop when it reached the range len.
-                if let Ok(ref range) = range {
-                    f = Box::new(f.take(range.len()));
-                    response.set_header(Header::new("Content-Length", format!("{}", range.len())));
-                    response.set_header(Header::new(
-                        "Content-Range",
-                        format!("{}={}-{}/{}", range.typ, range.start, range.end, size),
-                    ));
-                    response.set_status(Status::PartialContent);
+        // Set the headers of all matching rules of the _headers file
+        if !self.options.header_rules.is_empty() {
+            let request_path = format!("/{}", req_path);
+            for rule in self.options.header_rules.iter().filter(|r| r.matches(&request_path)) {
+                for &(ref name, ref value) in &rule.headers {
+                    response.adjoin_raw_header(name.clone(), value.clone());
                 }
+            }
+        }
 
-                // In case the client accepts encodings, we handle these
-                // TODO: Support more encodings
-                if let Some(encodings) = request.headers().get_one("Accept-Encoding") {
-                    if encodings.contains("gzip") {
-                        let mut encoder = GzEncoder::new(f, Compression::default());
-                        response.set_raw_header("Content-Encoding", "gzip");
-                        response.set_streamed_body(encoder);
-                        return;
-                    };
-                };
+        // Apply the rules of the _redirects file
+        if !self.options.redirects.is_empty()
+            && self.apply_redirects(request, response, prefix, &mut req_path)
+        {
+            return;
+        }
+
+        // Apply the rewrite rules, the result has to stay inside of the root as well
+        if !self.options.rewrites.is_empty() {
+            req_path = match path::normalize(&rewrite::rewrite(&self.options.rewrites, &req_path)) {
+                Some(p) => p,
+                None => {
+                    self.fail(request, response, self.deny_status());
+                    return;
+                }
+            };
+        }
+
+        // Pretend hidden files don't exist
+        if self.is_hidden(&req_path) {
+            self.fail(request, response, Status::NotFound);
+            return;
+        }
+
+        // Fail on paths outside of the given path
+        if !self.fs.path_valid(Path::new(&req_path)) {
+            self.fail(request, response, self.deny_status());
+            return;
+        };
+
+        // With clean URLs, `about.html` is served for `about` and optionally requests to
+        // `about.html` are redirected to `about`
+        if self.options.clean_urls {
+            if let Some(status) = self.options.clean_urls_redirect {
+                let is_index = req_path == "index.html" || req_path.ends_with("/index.html");
+                let is_html = req_path.ends_with(".html");
+                if !is_index && is_html && self.fs.is_file(Path::new(&req_path)) {
+                    let clean = &req_path[..req_path.len() - ".html".len()];
+                    let location = format!("{}{}", prefix, path::percent_encode(clean));
+                    redirect(request, response, status, location);
+                    return;
+                }
+            }
+
+            let html = format!("{}.html", req_path);
+            if !req_path.is_empty()
+                && !self.fs.is_file(Path::new(&req_path))
+                && self.fs.is_file(Path::new(&html))
+            {
+                req_path = html;
+            }
+        }
+
+        // Hidden directories don't exist either, neither for the redirect nor for listings
+        if !req_path.is_empty()
+            && self.is_hidden_dir(&req_path)
+            && self.fs.is_dir(Path::new(&req_path))
+        {
+            self.fail(request, response, Status::NotFound);
+            return;
+        }
+
+        // Redirect directories to their slash terminated URI, so relative links in index files
+        // and listings resolve correctly
+        if let Some(status) = self.options.directory_redirect {
+            if !req_path.is_empty() && !uri.ends_with('/') && self.fs.is_dir(Path::new(&req_path)) {
+                let location = format!("{}{}/", prefix, path::percent_encode(&req_path));
+                redirect(request, response, status, location);
+                return;
+            }
+        }
+
+        // With try_files configured, the first candidate which exists is served. This replaces
+        // the lookup of index files and the fallback to the root index file.
+        if !self.options.try_files.is_empty() {
+            let candidate = self.options.try_files
+                .iter()
+                .filter_map(|template| path::normalize(&template.replace("$path", &req_path)))
+                .filter(|c| !self.is_hidden(c))
+                .find(|c| self.fs.path_valid(Path::new(c)) && self.fs.is_file(Path::new(c)));
+            if let Some(candidate) = candidate {
+                self.serve(request, response, &candidate, Status::Ok);
+                return;
+            }
+        }
 
-                response.set_streamed_body(f);
+        // Serve the first index file found for directories which have one
+        if self.options.try_files.is_empty() && !self.fs.is_file(Path::new(&req_path)) {
+            let index = self.options.index_files
+                .iter()
+                .map(|name| path::join(&req_path, name))
+                .find(|index| self.fs.is_file(Path::new(index)));
+            if let Some(index) = index {
+                req_path = index;
             }
-            Err(_) => {
-                // TODO: What else could go wrong here? IMO it can be just no permissions
-                response.set_status(Status::Forbidden);
+        }
+
+        // Render a listing if the path is a directory and we're allowed to do so
+        if self.options.directory_listing && self.fs.is_dir(Path::new(&req_path)) {
+            match self.fs.read_dir(Path::new(&req_path)) {
+                Ok(mut entries) => {
+                    entries.retain(|e| {
+                        let path = path::join(&req_path, &e.name);
+                        if e.is_dir {
+                            !self.is_hidden_dir(&path)
+                        } else {
+                            !self.is_hidden(&path) && self.is_allowed_extension(&e.name)
+                        }
+                    });
+                    listing::sort_entries(&mut entries);
+
+                    let mut base = prefix.to_string();
+                    if !req_path.is_empty() {
+                        base.push_str(&req_path);
+                        base.push('/');
+                    }
+
+                    let (content_type, body) =
+                        self.options.listing_renderer.render(request, &base, &entries);
+                    response.set_status(Status::Ok);
+                    response.set_header(content_type);
+                    response.set_raw_header("Vary", "Accept");
+                    response.set_sized_body(Cursor::new(body));
+                }
+                Err(err) => {
+                    let error_status = self.error_status(err.status());
+                    self.fail(request, response, error_status);
+                }
             }
+            return;
+        }
+
+        // Serve the file, falling back to the not found page or the root index file for
+        // everything which doesn't exist
+        if self.options.try_files.is_empty() && self.fs.is_file(Path::new(&req_path)) {
+            self.serve(request, response, &req_path, Status::Ok);
+        } else if self.options.try_files.is_empty()
+            && !self.options.error_pages.contains_key(&404)
+            && self.fs.is_file(Path::new("index.html"))
+        {
+            self.serve(request, response, "index.html", Status::Ok);
+        } else {
+            self.fail(request, response, Status::NotFound);
+        }
+    }
+}
+
+/// Returns true if an If-None-Match header value matches the ETag.
+///
+/// Uses the weak comparison, as required for If-None-Match.
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_left_matches("W/");
+    if_none_match
+        .split(',')
+        .map(|tag| tag.trim())
+        .any(|tag| tag == "*" || tag.trim_left_matches("W/") == etag)
+}
+
+/// Makes sure the prefix ends with a slash.
+fn normalize_prefix(prefix: &str) -> String {
+    let mut prefix = prefix.to_string();
+    if !prefix.ends_with('/') {
+        prefix.push_str("/");
+    }
+    prefix
+}
+
+/// Redirects to the given location, preserving the query string of the request.
+fn redirect(request: &Request, response: &mut Response, status: Status, mut location: String) {
+    if let Some(query) = request.uri().query() {
+        location.push(if location.contains('?') { '&' } else { '?' });
+        location.push_str(query);
+    }
+    response.set_status(status);
+    response.set_raw_header("Location", location);
+}
+
+/// Reads the whole file at the given path into a string.
+fn read_to_string<T: FileSystem>(fs: &T, path: &str) -> Result<String, Box<StdError>> {
+    let mut s = String::new();
+    fs.open(Path::new(path), None)?.read_to_string(&mut s)?;
+    Ok(s)
+}
+
+impl<T: 'static> Fairing for StaticFileServer<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    fn info(&self) -> Info {
+        Info {
+            name: "static_file_server",
+            kind: Kind::Response,
+        }
+    }
+
+    fn on_response(&self, request: &Request, response: &mut Response) {
+        // Only handle requests which aren't otherwise handled.
+        if response.status() != Status::NotFound {
+            return;
+        }
+
+        if !(request.method() == Method::Get || request.method() == Method::Head) {
+            return;
+        }
+
+        events::started(request);
+        let _span = trace_span!(
+            "static_fs.request",
+            method = %request.method(),
+            uri = %request.uri()
+        );
+
+        // Files mapped to exact URIs are served no matter whether they're inside of our prefix
+        let uri = request.uri().path();
+        if let Some(file) = self.options.file_mappings.get(uri) {
+            match path::normalize(file) {
+                Some(file) => self.serve(request, response, &file, Status::Ok),
+                None => self.fail(request, response, Status::NotFound),
+            }
+            self.record(request, response, uri);
+            return;
+        }
+
+        // Only handle requests which include one of our prefixes, the longest one wins. Requests
+        // to a prefix without its trailing slash are treated like requests to the root directory.
+        let prefix = self.options.prefixes
+            .iter()
+            .filter(|p| uri.starts_with(p.as_str()) || uri == &p[..p.len() - 1])
+            .max_by_key(|p| p.len());
+        if let Some(prefix) = prefix {
+            self.handle(request, response, prefix);
+            self.record(request, response, prefix);
         }
     }
 }
@@ -304,12 +815,59 @@ where
 mod tests {
     #[allow(unused)]
     use super::fs::EmbeddedFileSystem;
+    use super::fs::{GeneratedFile, GeneratedFileSystem, HotCache};
+    use super::fs::InMemoryFileSystem;
     use super::fs::LocalFileSystem;
-    use super::Range;
+    use super::fs::testing::{MockFileSystem, TempFileSystem};
+    use super::{Glob, LanguageScheme, Range};
     use super::StaticFileServer;
+    use super::etag_matches;
     use rocket;
     use rocket::http::{Header, Status};
     use rocket::local::Client;
+    use rocket::response::Body;
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    /// Numbers the temporary directories of the tests.
+    static TEMP_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+    /// A temporary directory to serve files from, deleted when this is dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = env::temp_dir().join(format!(
+                "rocket_static_fs-test-{}-{}",
+                process::id(),
+                TEMP_DIRS.fetch_add(1, Ordering::SeqCst)
+            ));
+            fs::create_dir_all(&dir).expect("creating a temporary directory");
+            TempDir(dir)
+        }
+
+        fn path(&self) -> &Path {
+            &self.0
+        }
+
+        /// Writes a file, creating its directories.
+        fn insert(&mut self, path: &str, data: &str) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).expect("creating a directory");
+            fs::write(path, data).expect("writing a file");
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
 
     #[test]
     fn test_with_local_filesystem() {
@@ -350,6 +908,42 @@ mod tests {
         assert_eq!(body.len(), 6);
     }
 
+    #[test]
+    fn test_error_pages() {
+        let fs = LocalFileSystem::new("testdata");
+        let server = StaticFileServer::builder(fs)
+            .prefix("/test")
+            .hide_dotfiles(true)
+            .not_found_page("hello.txt")
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut resp = client.get("/test/.env").dispatch();
+        assert_eq!(resp.status(), Status::NotFound);
+        assert_eq!(resp.body_string(), Some("Hello World!".to_string()));
+
+        let mut resp = client.get("/test/does-not-exist.txt").dispatch();
+        assert_eq!(resp.status(), Status::NotFound);
+        assert_eq!(resp.body_string(), Some("Hello World!".to_string()));
+    }
+
+    #[test]
+    fn test_hidden_dotfiles() {
+        let mut temp = TempDir::new();
+        temp.insert(".git/config", "[core]");
+        let hidden = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .hide_dotfiles(true)
+            .build();
+        let client = Client::new(rocket::ignite().attach(hidden)).expect("valid rocket");
+        assert_eq!(client.get("/.git/config").dispatch().status(), Status::NotFound);
+
+        let shown = StaticFileServer::builder(LocalFileSystem::new(temp.path())).build();
+        let client = Client::new(rocket::ignite().attach(shown)).expect("valid rocket");
+        let mut resp = client.get("/.git/config").dispatch();
+        assert_eq!(resp.status(), Status::Ok);
+        assert_eq!(resp.body_string(), Some("[core]".to_string()));
+    }
+
     #[test]
     #[cfg(feature = "test_embedded")]
     fn test_with_embedded_filesystem() {
@@ -399,6 +993,585 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cache_max_age() {
+        let server = StaticFileServer::builder(LocalFileSystem::new("testdata"))
+            .prefix("/test")
+            .cache_max_age(3600)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let resp = client.get("/test/hello.txt").dispatch();
+        assert_eq!(resp.status(), Status::Ok);
+        assert_eq!(resp.headers().get_one("Cache-Control"), Some("max-age=3600"));
+
+        let resp = client.get("/test/does-not-exist.txt").dispatch();
+        assert_eq!(resp.headers().get_one("Cache-Control"), None);
+    }
+
+    #[test]
+    fn test_package_mime_types() {
+        use super::fs::{write_package_with, PackageOptions};
+
+        let mut temp = TempDir::new();
+        temp.insert("notes.txt", "notes");
+        temp.insert("app.wasm", "wasm");
+        temp.insert("data.unknown", "data");
+        let mut bytes = Vec::new();
+        write_package_with(
+            temp.path(),
+            &["notes.txt", "app.wasm", "data.unknown"],
+            &PackageOptions::new()
+                .mime_type("txt", "text/markdown")
+                .mime_type("wasm", "application/wasm"),
+            &mut bytes,
+        ).unwrap();
+        let fs = EmbeddedFileSystem::from_bytes(Box::leak(bytes.into_boxed_slice())).unwrap();
+
+        // The types chosen when packaging take precedence over the ones of the server
+        let server = StaticFileServer::builder(fs)
+            .mime_type("txt", "text/plain")
+            .default_mime_type("text/x-default")
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let resp = client.get("/notes.txt").dispatch();
+        assert_eq!(resp.status(), Status::Ok);
+        assert_eq!(resp.headers().get_one("Content-Type"), Some("text/markdown"));
+        let resp = client.get("/app.wasm").dispatch();
+        assert_eq!(resp.headers().get_one("Content-Type"), Some("application/wasm"));
+        let resp = client.get("/data.unknown").dispatch();
+        assert_eq!(resp.headers().get_one("Content-Type"), Some("text/x-default"));
+    }
+
+    #[test]
+    fn test_fingerprint_manifest() {
+        let mut fs = InMemoryFileSystem::new();
+        fs.insert("manifest.json", r#"{"css/app.css": "css/app.0123456789abcdef.css"}"#);
+        fs.insert("css/app.0123456789abcdef.css", "body {}");
+        fs.insert("index.html", "<h1>Hello</h1>");
+        let server = StaticFileServer::builder(fs)
+            .cache_max_age(60)
+            .fingerprint_manifest("manifest.json")
+            .build();
+        assert_eq!(
+            server.fingerprinted("/css/app.css"),
+            Some("css/app.0123456789abcdef.css")
+        );
+        assert_eq!(server.fingerprinted("index.html"), None);
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let resp = client.get("/css/app.0123456789abcdef.css").dispatch();
+        assert_eq!(
+            resp.headers().get_one("Cache-Control"),
+            Some("public, max-age=31536000, immutable")
+        );
+        let resp = client.get("/manifest.json").dispatch();
+        assert_eq!(resp.headers().get_one("Cache-Control"), Some("no-cache"));
+        let resp = client.get("/index.html").dispatch();
+        assert_eq!(resp.headers().get_one("Cache-Control"), Some("max-age=60"));
+    }
+
+    #[test]
+    fn test_sized_body() {
+        static FILES: &[GeneratedFile] = &[GeneratedFile {
+            path: "hello.txt",
+            data: b"Hello World!",
+            modified: 1_500_000_000,
+            mime: None,
+            etag: "\"1\"",
+        }];
+        let server = StaticFileServer::builder(GeneratedFileSystem::new(FILES)).build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut resp = client.get("/hello.txt").dispatch();
+        match resp.body() {
+            Some(Body::Sized(_, size)) => assert_eq!(size, 12),
+            _ => panic!("expected a sized body"),
+        }
+        assert_eq!(resp.body_string(), Some("Hello World!".to_string()));
+
+        let mut resp = client
+            .get("/hello.txt")
+            .header(Header::new("Range", "bytes=6-"))
+            .dispatch();
+        assert_eq!(resp.status(), Status::PartialContent);
+        assert_eq!(resp.body_string(), Some("World!".to_string()));
+
+        // Compressed bodies are still streamed
+        let resp = client
+            .get("/hello.txt")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .dispatch();
+        assert_eq!(resp.headers().get_one("Content-Encoding"), Some("gzip"));
+    }
+
+    #[test]
+    fn test_hot_cache_revalidation() {
+        let mut temp = TempFileSystem::new();
+        temp.insert("index.html", "old");
+        temp.set_modified("index.html", UNIX_EPOCH + Duration::from_secs(1_500_000_000));
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .hot_cache(HotCache::new(4096))
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut resp = client.get("/index.html").dispatch();
+        assert_eq!(resp.body_string(), Some("old".to_string()));
+
+        temp.insert("index.html", "changed");
+        temp.set_modified("index.html", UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+        let mut resp = client.get("/index.html").dispatch();
+        assert_eq!(resp.body_string(), Some("changed".to_string()));
+    }
+
+    #[test]
+    fn test_hot_cache() {
+        let mut fs = MockFileSystem::new();
+        fs.insert("index.html", "<h1>Hello</h1>");
+        fs.insert("large.bin", vec![0u8; 100]);
+        let fs = Arc::new(fs);
+        let cache = HotCache::new(4096).max_file_size(50);
+        let server = StaticFileServer::builder(fs.clone())
+            .hot_cache(cache.clone())
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        for _ in 0..3 {
+            let mut resp = client.get("/index.html").dispatch();
+            assert_eq!(resp.body_string(), Some("<h1>Hello</h1>".to_string()));
+            let mut resp = client.get("/large.bin").dispatch();
+            assert_eq!(resp.body_bytes().map(|body| body.len()), Some(100));
+        }
+        assert_eq!(fs.count("open", "index.html"), 1);
+        assert_eq!(fs.count("open", "large.bin"), 3);
+
+        cache.invalidate("/index.html");
+        client.get("/index.html").dispatch();
+        assert_eq!(fs.count("open", "index.html"), 2);
+    }
+
+    #[test]
+    fn test_compression_disabled() {
+        let server = StaticFileServer::builder(LocalFileSystem::new("testdata"))
+            .prefix("/test")
+            .compression(false)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut resp = client
+            .get("/test/hello.txt")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .dispatch();
+        assert_eq!(resp.headers().get_one("Content-Encoding"), None);
+        assert_eq!(resp.body_string(), Some("Hello World!".to_string()));
+    }
+
+    #[test]
+    fn test_etag_of_compressed_body() {
+        let server = StaticFileServer::builder(LocalFileSystem::new("testdata"))
+            .prefix("/test")
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let resp = client.get("/test/hello.txt").dispatch();
+        let etag = resp.headers().get_one("ETag").unwrap().to_string();
+        let resp = client
+            .get("/test/hello.txt")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .dispatch();
+        assert_eq!(resp.headers().get_one("Content-Encoding"), Some("gzip"));
+        let gzip_etag = resp.headers().get_one("ETag").unwrap().to_string();
+        assert_ne!(gzip_etag, etag);
+
+        let resp = client
+            .get("/test/hello.txt")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .header(Header::new("If-None-Match", gzip_etag))
+            .dispatch();
+        assert_eq!(resp.status(), Status::NotModified);
+    }
+
+    #[test]
+    fn test_ranges_disabled() {
+        let server = StaticFileServer::builder(LocalFileSystem::new("testdata"))
+            .prefix("/test")
+            .ranges(false)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut resp = client
+            .get("/test/hello.txt")
+            .header(Header::new("Range", "bytes=0-4"))
+            .dispatch();
+        assert_eq!(resp.status(), Status::Ok);
+        assert_eq!(resp.headers().get_one("Accept-Ranges"), Some("none"));
+        assert_eq!(resp.body_string(), Some("Hello World!".to_string()));
+    }
+
+    #[test]
+    fn test_listing_special_characters() {
+        let mut temp = TempDir::new();
+        temp.insert("a #b/c?d.txt", "hello");
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .directory_listing(true)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut resp = client.get("/a%20%23b/").dispatch();
+        assert_eq!(resp.status(), Status::Ok);
+        let body = resp.body_string().unwrap();
+        assert!(body.contains("<a href=\"/a%20%23b/c%3Fd.txt\">c?d.txt</a>"));
+
+        let mut resp = client.get("/a%20%23b/c%3Fd.txt").dispatch();
+        assert_eq!(resp.body_string(), Some("hello".to_string()));
+    }
+
+    #[test]
+    fn test_index_file() {
+        let mut temp = TempDir::new();
+        temp.insert("index.html", "root");
+        temp.insert("docs/index.html", "docs");
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path())).build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut resp = client.get("/").dispatch();
+        assert_eq!(resp.body_string(), Some("root".to_string()));
+
+        let mut resp = client.get("/docs/").dispatch();
+        assert_eq!(resp.status(), Status::Ok);
+        assert_eq!(resp.headers().get_one("Content-Type"), Some("text/html"));
+        assert_eq!(resp.body_string(), Some("docs".to_string()));
+    }
+
+    #[test]
+    fn test_index_files() {
+        let mut temp = TempDir::new();
+        temp.insert("docs/index.html", "html");
+        temp.insert("docs/default.htm", "htm");
+        temp.insert("blog/index.html", "blog");
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .index_files(vec!["default.htm", "index.html"])
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut resp = client.get("/docs/").dispatch();
+        assert_eq!(resp.body_string(), Some("htm".to_string()));
+        let mut resp = client.get("/blog/").dispatch();
+        assert_eq!(resp.body_string(), Some("blog".to_string()));
+    }
+
+    #[test]
+    fn test_directory_redirect() {
+        let mut temp = TempDir::new();
+        temp.insert("docs/index.html", "docs");
+        temp.insert("my docs/index.html", "my docs");
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path())).build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let resp = client.get("/docs?page=2").dispatch();
+        assert_eq!(resp.status(), Status::MovedPermanently);
+        assert_eq!(resp.headers().get_one("Location"), Some("/docs/?page=2"));
+        let resp = client.get("/my%20docs").dispatch();
+        assert_eq!(resp.headers().get_one("Location"), Some("/my%20docs/"));
+
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .directory_redirect(None)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+        let mut resp = client.get("/docs").dispatch();
+        assert_eq!(resp.headers().get_one("Location"), None);
+        assert_eq!(resp.body_string(), Some("docs".to_string()));
+    }
+
+    #[test]
+    fn test_bare_prefix() {
+        let mut temp = TempDir::new();
+        temp.insert("index.html", "assets");
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .prefix("/assets")
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let resp = client.get("/assets").dispatch();
+        assert_eq!(resp.status(), Status::MovedPermanently);
+        assert_eq!(resp.headers().get_one("Location"), Some("/assets/"));
+        let resp = client.get("/assetsx").dispatch();
+        assert_eq!(resp.status(), Status::NotFound);
+
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .prefix("/assets")
+            .directory_redirect(None)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+        let mut resp = client.get("/assets").dispatch();
+        assert_eq!(resp.body_string(), Some("assets".to_string()));
+    }
+
+    #[test]
+    fn test_not_found_page() {
+        let mut temp = TempDir::new();
+        temp.insert("index.html", "app");
+        temp.insert("404.html", "not found");
+
+        // Without a not found page, the root index file is served, e.g. for single page apps
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path())).build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+        let mut resp = client.get("/users/1").dispatch();
+        assert_eq!(resp.status(), Status::Ok);
+        assert_eq!(resp.body_string(), Some("app".to_string()));
+
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .not_found_page("404.html")
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+        let mut resp = client.get("/users/1").dispatch();
+        assert_eq!(resp.status(), Status::NotFound);
+        assert_eq!(resp.body_string(), Some("not found".to_string()));
+    }
+
+    #[test]
+    fn test_try_files() {
+        let mut temp = TempDir::new();
+        temp.insert("about.html", "about");
+        temp.insert("docs/index.html", "docs");
+        temp.insert("index.html", "app");
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .try_files(vec!["$path", "$path.html", "$path/index.html", "index.html"])
+            .directory_redirect(None)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        for &(uri, body) in &[("/about", "about"), ("/docs", "docs"), ("/users/1", "app")] {
+            let mut resp = client.get(uri).dispatch();
+            assert_eq!(resp.status(), Status::Ok);
+            assert_eq!(resp.body_string(), Some(body.to_string()));
+        }
+
+        let empty = TempDir::new();
+        let server = StaticFileServer::builder(LocalFileSystem::new(empty.path()))
+            .try_files(vec!["$path", "$path.html"])
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+        assert_eq!(client.get("/about").dispatch().status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_clean_urls() {
+        let mut temp = TempDir::new();
+        temp.insert("about.html", "about");
+        temp.insert("docs/index.html", "docs");
+
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .clean_urls(true)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+        let mut resp = client.get("/about").dispatch();
+        assert_eq!(resp.body_string(), Some("about".to_string()));
+        assert_eq!(client.get("/about.html").dispatch().status(), Status::Ok);
+
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .clean_urls(true)
+            .clean_urls_redirect(Some(Status::MovedPermanently))
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+        let resp = client.get("/about.html").dispatch();
+        assert_eq!(resp.status(), Status::MovedPermanently);
+        assert_eq!(resp.headers().get_one("Location"), Some("/about"));
+        let mut resp = client.get("/docs/index.html").dispatch();
+        assert_eq!(resp.body_string(), Some("docs".to_string()));
+    }
+
+    #[test]
+    fn test_excluded_directory() {
+        let mut temp = TempDir::new();
+        temp.insert("internal/secret.txt", "secret");
+        temp.insert("public.txt", "public");
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .exclude(Glob::new("internal/**").unwrap())
+            .directory_listing(true)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        for path in &["/internal", "/internal/", "/internal/secret.txt"] {
+            let resp = client.get(*path).dispatch();
+            assert_eq!(resp.status(), Status::NotFound);
+        }
+
+        let mut resp = client.get("/").dispatch();
+        let listing = resp.body_string().unwrap();
+        assert!(listing.contains("public.txt"));
+        assert!(!listing.contains("internal"));
+    }
+
+    #[test]
+    fn test_allowed_extensions() {
+        let mut temp = TempDir::new();
+        temp.insert("app.JS", "js");
+        temp.insert("index.html", "index");
+        temp.insert("backup.sql", "secret");
+        temp.insert("docs/readme.md", "readme");
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .allowed_extensions(vec!["html", "js"])
+            .directory_listing(true)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        assert_eq!(client.get("/app.JS").dispatch().status(), Status::Ok);
+        assert_eq!(client.get("/backup.sql").dispatch().status(), Status::NotFound);
+        let mut resp = client.get("/docs/").dispatch();
+        assert!(!resp.body_string().unwrap().contains("readme.md"));
+    }
+
+    #[test]
+    fn test_multiple_prefixes() {
+        let mut temp = TempDir::new();
+        temp.insert("app.js", "app");
+        temp.insert("v2/app.js", "v2");
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .prefix("/static")
+            .prefix("/assets")
+            .prefix("/assets/v1")
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut resp = client.get("/static/app.js").dispatch();
+        assert_eq!(resp.body_string(), Some("app".to_string()));
+        let mut resp = client.get("/assets/v2/app.js").dispatch();
+        assert_eq!(resp.body_string(), Some("v2".to_string()));
+        // The longest matching prefix wins
+        let mut resp = client.get("/assets/v1/app.js").dispatch();
+        assert_eq!(resp.body_string(), Some("app".to_string()));
+        assert_eq!(client.get("/app.js").dispatch().status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_localized_traversal() {
+        let mut temp = TempDir::new();
+        temp.insert("public/index.html", "public");
+        temp.insert("secret/index.html", "secret");
+        temp.insert("secret/only.html", "secret");
+        let fs = LocalFileSystem::new(temp.path().join("public"));
+        let server = StaticFileServer::builder(fs)
+            .localized(LanguageScheme::Directory)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut resp = client
+            .get("/index.html")
+            .header(Header::new("Accept-Language", "../secret"))
+            .dispatch();
+        assert_eq!(resp.status(), Status::Ok);
+        assert_eq!(resp.headers().get_one("Content-Language"), None);
+        assert_eq!(resp.body_string(), Some("public".to_string()));
+
+        let resp = client
+            .get("/only.html")
+            .header(Header::new("Accept-Language", "../secret"))
+            .dispatch();
+        assert_eq!(resp.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_netlify_files() {
+        let mut temp = TempDir::new();
+        temp.insert("_redirects", "/home / 301\n");
+        temp.insert("_headers", "/*\n  X-Frame-Options: DENY\n");
+        temp.insert("hello.html", "<h1>Hello</h1>");
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .directory_listing(true)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let resp = client.get("/home").dispatch();
+        assert_eq!(resp.status(), Status::MovedPermanently);
+        assert_eq!(resp.headers().get_one("Location"), Some("/"));
+
+        let resp = client.get("/hello.html").dispatch();
+        assert_eq!(resp.headers().get_one("X-Frame-Options"), Some("DENY"));
+
+        let mut resp = client.get("/").dispatch();
+        let listing = resp.body_string().unwrap();
+        assert!(listing.contains("hello.html"));
+        assert!(!listing.contains("_redirects") && !listing.contains("_headers"));
+
+        for path in &["/_redirects", "/_headers"] {
+            let resp = client.get(*path).dispatch();
+            assert_eq!(resp.status(), Status::NotFound);
+        }
+
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .netlify_files(false)
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+        assert_eq!(client.get("/home").dispatch().status(), Status::NotFound);
+        assert_eq!(client.get("/_redirects").dispatch().status(), Status::Ok);
+    }
+
+    #[test]
+    fn test_invalid_file_mapping() {
+        let mut temp = TempDir::new();
+        temp.insert("icons/favicon.ico", "icon");
+        let server = StaticFileServer::builder(LocalFileSystem::new(temp.path()))
+            .prefix("/assets")
+            .map_file("/favicon.ico", "icons/favicon.ico")
+            .map_file("/secret", "../secret.txt")
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        let mut resp = client.get("/favicon.ico").dispatch();
+        assert_eq!(resp.body_string(), Some("icon".to_string()));
+        let resp = client.get("/secret").dispatch();
+        assert_eq!(resp.status(), Status::NotFound);
+    }
+
+    #[test]
+    fn test_callbacks() {
+        let served = Arc::new(Mutex::new(Vec::new()));
+        let failed = Arc::new(Mutex::new(Vec::new()));
+        let (served_clone, failed_clone) = (served.clone(), failed.clone());
+
+        let server = StaticFileServer::builder(LocalFileSystem::new("testdata"))
+            .prefix("/test")
+            .on_serve(move |e| served_clone.lock().unwrap().push((e.path.to_string(), e.bytes)))
+            .on_error(move |e| failed_clone.lock().unwrap().push((e.uri.to_string(), e.status)))
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        client.get("/test/hello.txt").dispatch();
+        client.get("/test/does-not-exist.txt").dispatch();
+
+        assert_eq!(*served.lock().unwrap(), vec![("hello.txt".to_string(), 12)]);
+        assert_eq!(
+            *failed.lock().unwrap(),
+            vec![("/test/does-not-exist.txt".to_string(), Status::NotFound)]
+        );
+    }
+
+    #[test]
+    fn test_etag_matches() {
+        assert!(etag_matches("\"abc\"", "\"abc\""));
+        assert!(etag_matches("W/\"abc\"", "\"abc\""));
+        assert!(etag_matches("\"xyz\", W/\"abc\"", "W/\"abc\""));
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(!etag_matches("\"xyz\"", "\"abc\""));
+    }
+
+    #[test]
+    fn test_parse_range_header_for_size() {
+        let range = Range::parse_for_size("bytes=5-", 100).unwrap();
+        assert_eq!((range.start, range.end), (5, 99));
+
+        let range = Range::parse_for_size("bytes=-10", 100).unwrap();
+        assert_eq!((range.start, range.end), (90, 99));
+
+        let range = Range::parse_for_size("bytes=10-1000", 100).unwrap();
+        assert_eq!((range.start, range.end), (10, 99));
+
+        assert!(Range::parse_for_size("bytes=10-5", 100).is_err());
+        assert!(Range::parse_for_size("bytes=-", 100).is_err());
+    }
+
     #[test]
     fn test_parse_range_header() {
         let range: Range = "bytes=0-1023"
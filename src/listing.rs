@@ -0,0 +1,190 @@
+//! Renders directory listings.
+//!
+//! Implement `ListingRenderer` to customize how listings look, e.g. to apply your own theme.
+
+use chrono::prelude::*;
+use fs::DirEntry;
+use path;
+use rocket::http::ContentType;
+use rocket::Request;
+use serde_json;
+use std::fmt::Write;
+
+const LISTING_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Implement this trait to render directory listings yourself.
+pub trait ListingRenderer: Send + Sync {
+    /// Renders the listing of a directory.
+    ///
+    /// `base` is the decoded path of the URI the directory is served at, ending with a slash,
+    /// so it has to be percent-encoded for links. `entries` are already sorted and filtered.
+    /// Returns the content type and body of the response.
+    fn render(
+        &self,
+        request: &Request,
+        base: &str,
+        entries: &[DirEntry],
+    ) -> (ContentType, Vec<u8>);
+}
+
+/// The built-in renderer.
+///
+/// Renders JSON for clients preferring `application/json` and HTML for everyone else.
+pub struct DefaultListingRenderer;
+
+impl ListingRenderer for DefaultListingRenderer {
+    fn render(
+        &self,
+        request: &Request,
+        base: &str,
+        entries: &[DirEntry],
+    ) -> (ContentType, Vec<u8>) {
+        let wants_json = request
+            .accept()
+            .map_or(false, |accept| accept.preferred().media_type().is_json());
+        if wants_json {
+            (ContentType::JSON, render_json(entries).into_bytes())
+        } else {
+            (ContentType::HTML, render_html(base, entries).into_bytes())
+        }
+    }
+}
+
+/// Sorts entries the way they're presented: directories first, then by name.
+pub fn sort_entries(entries: &mut Vec<DirEntry>) {
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+}
+
+/// Renders an HTML index of the given entries.
+///
+/// `base` is the decoded path of the URI the listed directory is served at, ending with a
+/// slash. It is percent-encoded to build the links to the entries.
+pub fn render_html(base: &str, entries: &[DirEntry]) -> String {
+    let title = escape_html(base);
+    let mut html = String::new();
+    write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Index of {0}</title>\n\
+         </head>\n<body>\n<h1>Index of {0}</h1>\n<table>\n\
+         <tr><th>Name</th><th>Size</th><th>Last modified</th></tr>\n",
+        title
+    ).unwrap();
+
+    for entry in entries {
+        let mut name = entry.name.clone();
+        if entry.is_dir {
+            name.push('/');
+        }
+
+        let size = if entry.is_dir {
+            "-".to_string()
+        } else {
+            entry.size.to_string()
+        };
+
+        let modified = match entry.modified {
+            Some(modified) => DateTime::<Utc>::from(modified)
+                .format(LISTING_DATE_FORMAT)
+                .to_string(),
+            None => "-".to_string(),
+        };
+
+        write!(
+            html,
+            "<tr><td><a href=\"{}{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+            escape_html(&path::percent_encode(base)),
+            escape_html(&path::percent_encode(&name)),
+            escape_html(&name),
+            size,
+            modified
+        ).unwrap();
+    }
+
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/// Renders the entries as JSON array of objects with `name`, `size`, `mtime` and `is_dir` keys.
+///
+/// `mtime` is given in seconds since the unix epoch or `null` if unknown.
+pub fn render_json(entries: &[DirEntry]) -> String {
+    let entries: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            let mtime = entry
+                .modified
+                .map(|modified| DateTime::<Utc>::from(modified).timestamp());
+            json!({
+                "name": entry.name,
+                "size": entry.size,
+                "mtime": mtime,
+                "is_dir": entry.is_dir,
+            })
+        })
+        .collect();
+    serde_json::Value::Array(entries).to_string()
+}
+
+/// Escapes the characters which have a special meaning in HTML.
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_html() {
+        let mut entries = vec![
+            DirEntry {
+                name: "b <file>.txt".to_string(),
+                is_dir: false,
+                size: 12,
+                modified: None,
+            },
+            DirEntry {
+                name: "inner".to_string(),
+                is_dir: true,
+                size: 0,
+                modified: None,
+            },
+        ];
+        sort_entries(&mut entries);
+        assert_eq!(entries[0].name, "inner");
+
+        let html = render_html("/assets/", &entries);
+        assert!(html.contains("<a href=\"/assets/inner/\">inner/</a>"));
+        assert!(html.contains("<a href=\"/assets/b%20%3Cfile%3E.txt\">b &lt;file&gt;.txt</a>"));
+        assert!(html.contains("<td>12</td>"));
+
+        let html = render_html("/a #b/", &entries);
+        assert!(html.contains("<title>Index of /a #b/</title>"));
+        assert!(html.contains("<a href=\"/a%20%23b/inner/\">inner/</a>"));
+    }
+
+    #[test]
+    fn test_render_json() {
+        let entries = vec![DirEntry {
+            name: "hello.txt".to_string(),
+            is_dir: false,
+            size: 12,
+            modified: None,
+        }];
+        assert_eq!(
+            render_json(&entries),
+            r#"[{"is_dir":false,"mtime":null,"name":"hello.txt","size":12}]"#
+        );
+    }
+}
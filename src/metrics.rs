@@ -0,0 +1,199 @@
+//! Prometheus metrics of the requests handled by the server and of its FileSystems.
+
+use events;
+use prometheus::proto::MetricFamily;
+use prometheus::{
+    self, Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use rocket::http::Status;
+use rocket::{Request, Response};
+use std::time::Duration;
+
+/// Prometheus metrics of a StaticFileServer, see `StaticFileServerBuilder::metrics`.
+///
+/// The metrics of requests are labeled with the prefix the request was served from:
+///
+/// - `static_fs_requests_total` counts requests by `prefix` and `status`
+/// - `static_fs_bytes_total` counts the bytes of files sent, before compression
+/// - `static_fs_cache_total` counts conditional requests answered with a 304 as `hit` and
+///   files sent as `miss`
+/// - `static_fs_compressed_total` counts responses by `encoding`
+///
+/// FileSystems wrapped in a `fs::MetricsFileSystem` record their calls as well, labeled with
+/// the name of the `backend` and the `operation`:
+///
+/// - `static_fs_backend_calls_total` counts calls by `result`, which is `ok`, `not_found` or
+///   `error`
+/// - `static_fs_backend_duration_seconds` is a histogram of the latency of calls
+///
+/// Metrics can be cloned cheaply, clones share the counters.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::{fs, Metrics, StaticFileServer};
+///
+/// let metrics = Metrics::new();
+/// let server = StaticFileServer::builder(fs::LocalFileSystem::new("static"))
+///     .metrics(metrics.clone())
+///     .build();
+///
+/// // Serve this from a route of your application
+/// let text = metrics.render();
+/// ```
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    bytes: IntCounterVec,
+    cache: IntCounterVec,
+    compressed: IntCounterVec,
+    backend_calls: IntCounterVec,
+    backend_duration: HistogramVec,
+}
+
+impl Metrics {
+    /// Creates the metrics in a registry of their own.
+    pub fn new() -> Self {
+        let metrics = Metrics {
+            registry: Registry::new(),
+            requests: counter("static_fs_requests_total", "Requests handled", &["prefix", "status"]),
+            bytes: counter("static_fs_bytes_total", "Bytes of files sent", &["prefix"]),
+            cache: counter("static_fs_cache_total", "Cache hits and misses", &["prefix", "result"]),
+            compressed: counter(
+                "static_fs_compressed_total",
+                "Compressed responses",
+                &["prefix", "encoding"],
+            ),
+            backend_calls: counter(
+                "static_fs_backend_calls_total",
+                "Calls to FileSystems",
+                &["backend", "operation", "result"],
+            ),
+            backend_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "static_fs_backend_duration_seconds",
+                    "Latency of calls to FileSystems",
+                ),
+                &["backend", "operation"],
+            ).expect("valid metric"),
+        };
+        metrics
+            .register(&metrics.registry)
+            .expect("metrics are registered once");
+        metrics
+    }
+
+    /// Registers the metrics in another registry as well, e.g. the default one of the
+    /// prometheus crate, to export them along with the metrics of your application.
+    pub fn register(&self, registry: &Registry) -> prometheus::Result<()> {
+        registry.register(Box::new(self.requests.clone()))?;
+        registry.register(Box::new(self.bytes.clone()))?;
+        registry.register(Box::new(self.cache.clone()))?;
+        registry.register(Box::new(self.compressed.clone()))?;
+        registry.register(Box::new(self.backend_calls.clone()))?;
+        registry.register(Box::new(self.backend_duration.clone()))?;
+        Ok(())
+    }
+
+    /// Returns the current values of all metrics.
+    pub fn gather(&self) -> Vec<MetricFamily> {
+        self.registry.gather()
+    }
+
+    /// Returns the current values of all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.gather(), &mut buffer)
+            .expect("encoding to a vec doesn't fail");
+        String::from_utf8(buffer).expect("the text format is UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+/// Records a request handled by the server.
+pub fn record(metrics: &Metrics, request: &Request, response: &Response, prefix: &str) {
+    let status = response.status();
+    metrics
+        .requests
+        .with_label_values(&[prefix, &status.code.to_string()])
+        .inc();
+
+    if let Some(bytes) = events::body_bytes(request) {
+        metrics.bytes.with_label_values(&[prefix]).inc_by(bytes as i64);
+    }
+
+    if status == Status::NotModified {
+        metrics.cache.with_label_values(&[prefix, "hit"]).inc();
+    } else if status == Status::Ok || status == Status::PartialContent {
+        metrics.cache.with_label_values(&[prefix, "miss"]).inc();
+    }
+
+    if let Some(encoding) = response.headers().get_one("Content-Encoding") {
+        metrics
+            .compressed
+            .with_label_values(&[prefix, encoding])
+            .inc();
+    }
+}
+
+/// Records a call to a FileSystem.
+pub fn record_backend_call(
+    metrics: &Metrics,
+    backend: &str,
+    operation: &str,
+    result: &str,
+    duration: Duration,
+) {
+    metrics
+        .backend_calls
+        .with_label_values(&[backend, operation, result])
+        .inc();
+    metrics
+        .backend_duration
+        .with_label_values(&[backend, operation])
+        .observe(duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1e9);
+}
+
+fn counter(name: &str, help: &str, labels: &[&str]) -> IntCounterVec {
+    IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Metrics;
+    use fs::LocalFileSystem;
+    use rocket;
+    use rocket::http::Header;
+    use rocket::local::Client;
+    use StaticFileServer;
+
+    #[test]
+    fn test_metrics() {
+        let metrics = Metrics::new();
+        let server = StaticFileServer::builder(LocalFileSystem::new("testdata"))
+            .prefix("/test")
+            .metrics(metrics.clone())
+            .build();
+        let client = Client::new(rocket::ignite().attach(server)).expect("valid rocket");
+
+        client
+            .get("/test/hello.txt")
+            .header(Header::new("Accept-Encoding", "gzip"))
+            .dispatch();
+        client.get("/test/does-not-exist.txt").dispatch();
+
+        let text = metrics.render();
+        assert!(text.contains(r#"static_fs_requests_total{prefix="/test/",status="200"} 1"#));
+        assert!(text.contains(r#"static_fs_requests_total{prefix="/test/",status="404"} 1"#));
+        assert!(text.contains(r#"static_fs_bytes_total{prefix="/test/"} 12"#));
+        assert!(text.contains(r#"static_fs_cache_total{prefix="/test/",result="miss"} 1"#));
+        assert!(text.contains(r#"static_fs_compressed_total{encoding="gzip",prefix="/test/"} 1"#));
+    }
+}
@@ -0,0 +1,226 @@
+//! Support for Netlify-style `_redirects` and `_headers` files.
+//!
+//! See <https://www.netlify.com/docs/redirects/> and
+//! <https://www.netlify.com/docs/headers-and-basic-auth/> for the formats.
+
+use regex::{self, Regex};
+
+/// A single rule of a `_redirects` file.
+#[derive(Debug)]
+pub struct Redirect {
+    from: Regex,
+    placeholders: Vec<String>,
+    to: String,
+    /// The status of the rule, 200 rules are rewrites and not redirects.
+    pub status: u16,
+    /// Forced rules apply even if the requested file exists.
+    pub force: bool,
+}
+
+impl Redirect {
+    /// Parses a single line of a `_redirects` file.
+    ///
+    /// Returns `None` for empty lines, comments and lines which can't be parsed.
+    pub fn parse(line: &str) -> Option<Redirect> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let mut parts = line.split_whitespace();
+        let from = parts.next()?;
+        let to = parts.next()?;
+
+        let (status, force) = match parts.next() {
+            Some(status) => {
+                let force = status.ends_with('!');
+                (status.trim_right_matches('!').parse().ok()?, force)
+            }
+            None => (301, false),
+        };
+
+        let (from, placeholders) = compile_pattern(from).ok()?;
+        Some(Redirect {
+            from,
+            placeholders,
+            to: to.to_string(),
+            status,
+            force,
+        })
+    }
+
+    /// Returns the target of the rule if it matches the path.
+    ///
+    /// `path` has to start with a slash. Placeholders and splats in the target are replaced by
+    /// what they matched.
+    pub fn target(&self, path: &str) -> Option<String> {
+        let path = if path.len() > 1 {
+            path.trim_right_matches('/')
+        } else {
+            path
+        };
+
+        let captures = self.from.captures(path)?;
+        let mut target = self.to.clone();
+        if let Some(splat) = captures.name("splat") {
+            target = target.replace(":splat", splat.as_str());
+        }
+        for name in &self.placeholders {
+            if let Some(value) = captures.name(name) {
+                target = target.replace(&format!(":{}", name), value.as_str());
+            }
+        }
+        Some(target)
+    }
+
+    /// Returns true if the rule is a rewrite rather than a redirect.
+    pub fn is_rewrite(&self) -> bool {
+        self.status == 200 || self.status == 404
+    }
+}
+
+/// A path pattern of a `_headers` file with the headers to set for matching paths.
+#[derive(Debug)]
+pub struct HeaderRule {
+    pattern: Regex,
+    /// The headers to set, as name and value.
+    pub headers: Vec<(String, String)>,
+}
+
+impl HeaderRule {
+    /// Returns true if the rule applies to the path, which has to start with a slash.
+    pub fn matches(&self, path: &str) -> bool {
+        let path = if path.len() > 1 {
+            path.trim_right_matches('/')
+        } else {
+            path
+        };
+        self.pattern.is_match(path)
+    }
+}
+
+/// Parses all rules of a `_headers` file.
+///
+/// Paths start at the beginning of a line, the headers for a path follow on indented lines.
+/// Headers without a path and paths which can't be parsed are skipped.
+pub fn parse_headers(s: &str) -> Vec<HeaderRule> {
+    let mut rules: Vec<HeaderRule> = Vec::new();
+    let mut skipping = true;
+    for line in s.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if !line.starts_with(char::is_whitespace) {
+            match compile_pattern(trimmed) {
+                Ok((pattern, _)) => {
+                    rules.push(HeaderRule {
+                        pattern,
+                        headers: Vec::new(),
+                    });
+                    skipping = false;
+                }
+                Err(_) => skipping = true,
+            }
+            continue;
+        }
+
+        if skipping {
+            continue;
+        }
+
+        if let (Some(rule), Some(colon)) = (rules.last_mut(), trimmed.find(':')) {
+            let name = trimmed[..colon].trim().to_string();
+            let value = trimmed[colon + 1..].trim().to_string();
+            rule.headers.push((name, value));
+        }
+    }
+    rules
+}
+
+/// Parses all rules of a `_redirects` file, skipping lines which can't be parsed.
+pub fn parse_redirects(s: &str) -> Vec<Redirect> {
+    s.lines().filter_map(Redirect::parse).collect()
+}
+
+/// Compiles a path pattern with `:placeholder` segments and a trailing `*` into a regex.
+fn compile_pattern(pattern: &str) -> Result<(Regex, Vec<String>), regex::Error> {
+    let pattern = if pattern.len() > 1 {
+        pattern.trim_right_matches('/')
+    } else {
+        pattern
+    };
+
+    let mut placeholders = Vec::new();
+    let mut regex = String::from("^");
+    for (i, segment) in pattern.split('/').enumerate() {
+        if i > 0 {
+            regex.push('/');
+        }
+
+        if segment == "*" {
+            regex.push_str("(?P<splat>.*)");
+        } else if segment.starts_with(':') && segment.len() > 1 {
+            let name = &segment[1..];
+            regex.push_str(&format!("(?P<{}>[^/]+)", name));
+            placeholders.push(name.to_string());
+        } else {
+            regex.push_str(&regex::escape(segment));
+        }
+    }
+    regex.push('$');
+
+    Ok((Regex::new(&regex)?, placeholders))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_redirects() {
+        let rules = parse_redirects(
+            "# Comment\n\
+             /home / 301\n\
+             /news/* /blog/:splat\n\
+             /posts/:year/:slug /blog/:year-:slug 302!\n\
+             /app/* /index.html 200\n\
+             invalid\n",
+        );
+        assert_eq!(rules.len(), 4);
+
+        assert_eq!(rules[0].target("/home"), Some("/".to_string()));
+        assert_eq!(rules[0].target("/home/"), Some("/".to_string()));
+        assert_eq!(rules[0].target("/homer"), None);
+        assert_eq!(rules[0].status, 301);
+
+        assert_eq!(rules[1].target("/news/2018/a"), Some("/blog/2018/a".to_string()));
+
+        assert_eq!(rules[2].target("/posts/2018/a"), Some("/blog/2018-a".to_string()));
+        assert_eq!(rules[2].status, 302);
+        assert!(rules[2].force);
+
+        assert_eq!(rules[3].target("/app/x/y"), Some("/index.html".to_string()));
+        assert!(rules[3].is_rewrite());
+    }
+
+    #[test]
+    fn test_parse_headers() {
+        let rules = parse_headers(
+            "/*\n  X-Frame-Options: DENY\n\n/assets/*\n  Cache-Control: public, max-age=3600\n",
+        );
+        assert_eq!(rules.len(), 2);
+        assert!(rules[0].matches("/index.html"));
+        assert_eq!(
+            rules[0].headers,
+            vec![("X-Frame-Options".to_string(), "DENY".to_string())]
+        );
+        assert!(rules[1].matches("/assets/app.js"));
+        assert!(!rules[1].matches("/app.js"));
+        assert_eq!(
+            rules[1].headers,
+            vec![("Cache-Control".to_string(), "public, max-age=3600".to_string())]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Helpers for inspecting and cleaning up request paths before they reach a FileSystem.
+
+use std::fmt::Write;
+
+/// Maximum length in bytes of a whole request path.
+pub const MAX_PATH_LENGTH: usize = 4096;
+
+/// Maximum length in bytes of a single path segment.
+pub const MAX_SEGMENT_LENGTH: usize = 255;
+
+/// Returns true if the path is sane enough to hand to a FileSystem.
+///
+/// Paths containing NUL bytes or other control characters as well as paths exceeding
+/// `MAX_PATH_LENGTH` or containing segments longer than `MAX_SEGMENT_LENGTH` are rejected.
+pub fn is_sane(path: &str) -> bool {
+    if path.len() > MAX_PATH_LENGTH {
+        return false;
+    }
+
+    if path.chars().any(|c| c.is_control()) {
+        return false;
+    }
+
+    path.split('/').all(|segment| segment.len() <= MAX_SEGMENT_LENGTH)
+}
+
+/// Normalizes a request path lexically.
+///
+/// Duplicate slashes and `.` segments are removed and `..` segments are resolved against
+/// their parent. Returns `None` if the path tries to escape its root via `..`.
+///
+/// The returned path never starts or ends with a slash.
+pub fn normalize(path: &str) -> Option<String> {
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => {
+                segments.pop()?;
+            }
+            _ => segments.push(segment),
+        }
+    }
+    Some(segments.join("/"))
+}
+
+/// Joins a normalized directory path and a file name.
+pub fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Returns true if any component of the given path starts with a dot.
+///
+/// This catches things like `.git/config`, `.env` or `assets/.htaccess`.
+pub fn has_hidden_component(path: &str) -> bool {
+    path.split('/').any(|component| component.starts_with('.'))
+}
+
+/// Percent-encodes everything but unreserved characters and slashes.
+pub fn percent_encode(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(b as char)
+            }
+            _ => write!(encoded, "%{:02X}", b).unwrap(),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_sane() {
+        assert!(is_sane("assets/app.js"));
+        assert!(is_sane("assets/some file.txt"));
+        assert!(!is_sane("assets/app.js\0.png"));
+        assert!(!is_sane("assets/\napp.js"));
+        assert!(!is_sane(&"a".repeat(MAX_SEGMENT_LENGTH + 1)));
+        assert!(!is_sane(&"a/".repeat(MAX_PATH_LENGTH)));
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("app.js"), Some("app.js".to_string()));
+        assert_eq!(normalize("/js//app.js"), Some("js/app.js".to_string()));
+        assert_eq!(normalize("./js/./app.js"), Some("js/app.js".to_string()));
+        assert_eq!(normalize("js/../css/app.css"), Some("css/app.css".to_string()));
+        assert_eq!(normalize("docs/"), Some("docs".to_string()));
+        assert_eq!(normalize(""), Some("".to_string()));
+        assert_eq!(normalize("../secret"), None);
+        assert_eq!(normalize("js/../../secret"), None);
+    }
+
+    #[test]
+    fn test_has_hidden_component() {
+        assert!(has_hidden_component(".env"));
+        assert!(has_hidden_component(".git/config"));
+        assert!(has_hidden_component("assets/.htaccess"));
+        assert!(!has_hidden_component("assets/app.js"));
+        assert!(!has_hidden_component("assets/app.min.js"));
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode("docs/app-1.0_x~.js"), "docs/app-1.0_x~.js");
+        assert_eq!(percent_encode("a #b/c?d%"), "a%20%23b/c%3Fd%25");
+        assert_eq!(percent_encode("ü"), "%C3%BC");
+    }
+}
@@ -0,0 +1,105 @@
+//! A responder serving a single file from a route handler.
+
+use fs::FileSystem;
+use rocket::response::{self, Responder};
+use rocket::{Request, Response};
+use serve_file;
+
+/// A responder for a single file of a FileSystem.
+///
+/// Handles MIME types, conditional requests, ranges and compression just like the
+/// StaticFileServer does. Use it to serve files from your own routes, e.g. for downloads which
+/// require authentication. If the file can't be served, the request fails with the
+/// corresponding status and is passed on to Rocket's catchers.
+///
+/// # Example
+///
+/// ```
+/// #![feature(proc_macro_hygiene, decl_macro)]
+///
+/// #[macro_use]
+/// extern crate rocket;
+/// extern crate rocket_static_fs;
+///
+/// use rocket_static_fs::{fs, StaticFile};
+///
+/// #[get("/downloads/report")]
+/// fn report() -> StaticFile<fs::LocalFileSystem> {
+///     StaticFile::new(fs::LocalFileSystem::new("private"), "reports/latest.pdf")
+/// }
+///
+/// fn main() {
+///     rocket::ignite().mount("/", routes![report]);
+/// }
+/// ```
+pub struct StaticFile<T: FileSystem> {
+    fs: T,
+    path: String,
+}
+
+impl<T: FileSystem> StaticFile<T> {
+    /// Creates a responder for the file at `path`, which is relative to the root of the
+    /// FileSystem.
+    pub fn new(fs: T, path: &str) -> Self {
+        StaticFile {
+            fs,
+            path: path.to_string(),
+        }
+    }
+}
+
+impl<'r, T: FileSystem> Responder<'r> for StaticFile<T> {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        let mut response = Response::new();
+        serve_file(&self.fs, &self.path, request, &mut response)?;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StaticFile;
+    use fs::LocalFileSystem;
+    use rocket;
+    use rocket::http::{Header, Status};
+    use rocket::local::Client;
+    use rocket::Route;
+
+    fn routes() -> Vec<Route> {
+        use rocket::handler::Outcome;
+        use rocket::http::Method;
+        use rocket::{Data, Request};
+
+        fn hello<'r>(request: &'r Request, _: Data) -> Outcome<'r> {
+            Outcome::from(request, StaticFile::new(LocalFileSystem::new("testdata"), "hello.txt"))
+        }
+
+        fn escape<'r>(request: &'r Request, _: Data) -> Outcome<'r> {
+            Outcome::from(request, StaticFile::new(LocalFileSystem::new("testdata"), "../Cargo.toml"))
+        }
+
+        vec![
+            Route::new(Method::Get, "/hello", hello),
+            Route::new(Method::Get, "/escape", escape),
+        ]
+    }
+
+    #[test]
+    fn test_static_file() {
+        let client = Client::new(rocket::ignite().mount("/", routes())).expect("valid rocket");
+
+        let resp = client.get("/hello").dispatch();
+        assert_eq!(resp.status(), Status::Ok);
+        assert_eq!(resp.headers().get_one("Content-Type"), Some("text/plain"));
+
+        let resp = client
+            .get("/hello")
+            .header(Header::new("Range", "bytes=0-1"))
+            .dispatch();
+        assert_eq!(resp.status(), Status::PartialContent);
+        assert_eq!(resp.headers().get_one("Content-Length"), Some("2"));
+
+        let resp = client.get("/escape").dispatch();
+        assert_eq!(resp.status(), Status::Forbidden);
+    }
+}
@@ -0,0 +1,81 @@
+//! Rewrites request paths to FileSystem paths before they're looked up.
+
+use regex::{self, Regex};
+
+/// A rule rewriting request paths.
+///
+/// Paths are matched relative to the prefix of the server and without a leading slash, so a
+/// request to `/assets/old/page.html` with a prefix of `/assets` is matched as `old/page.html`.
+#[derive(Debug, Clone)]
+pub enum RewriteRule {
+    /// Rewrites exactly the given path to another path.
+    Exact(String, String),
+    /// Replaces the given prefix of a path with another prefix.
+    Prefix(String, String),
+    /// Replaces the match of a regex, `$1` etc. in the replacement refer to capture groups.
+    Regex(Regex, String),
+}
+
+impl RewriteRule {
+    /// Rewrites exactly `from` to `to`.
+    pub fn exact(from: &str, to: &str) -> Self {
+        RewriteRule::Exact(from.to_string(), to.to_string())
+    }
+
+    /// Rewrites paths starting with `from` so they start with `to` instead.
+    pub fn prefix(from: &str, to: &str) -> Self {
+        RewriteRule::Prefix(from.to_string(), to.to_string())
+    }
+
+    /// Rewrites paths matching `pattern` by replacing the match with `replacement`.
+    ///
+    /// Use `^` and `$` to match whole paths.
+    pub fn regex(pattern: &str, replacement: &str) -> Result<Self, regex::Error> {
+        Ok(RewriteRule::Regex(Regex::new(pattern)?, replacement.to_string()))
+    }
+
+    /// Returns the rewritten path or `None` if the rule doesn't match.
+    pub fn apply(&self, path: &str) -> Option<String> {
+        match *self {
+            RewriteRule::Exact(ref from, ref to) if path == from => Some(to.clone()),
+            RewriteRule::Prefix(ref from, ref to) if path.starts_with(from.as_str()) => {
+                Some(format!("{}{}", to, &path[from.len()..]))
+            }
+            RewriteRule::Regex(ref regex, ref replacement) if regex.is_match(path) => {
+                Some(regex.replace(path, replacement.as_str()).into_owned())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Applies the first matching rule to the path.
+///
+/// Returns the path unchanged if no rule matches.
+pub fn rewrite(rules: &[RewriteRule], path: &str) -> String {
+    rules
+        .iter()
+        .filter_map(|rule| rule.apply(path))
+        .next()
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite() {
+        let rules = vec![
+            RewriteRule::exact("old.html", "new.html"),
+            RewriteRule::prefix("legacy/", "archive/2017/"),
+            RewriteRule::regex(r"^posts/(\d+)/(\d+)\.html$", "blog/$1-$2.html").unwrap(),
+        ];
+
+        assert_eq!(rewrite(&rules, "old.html"), "new.html");
+        assert_eq!(rewrite(&rules, "old.html.bak"), "old.html.bak");
+        assert_eq!(rewrite(&rules, "legacy/a/b.css"), "archive/2017/a/b.css");
+        assert_eq!(rewrite(&rules, "posts/2018/03.html"), "blog/2018-03.html");
+        assert_eq!(rewrite(&rules, "app.js"), "app.js");
+    }
+}
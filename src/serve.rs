@@ -0,0 +1,459 @@
+//! The serving of a single file, shared by the fairing, the route handler and the responder.
+
+use chrono::prelude::*;
+use flate2::read::GzEncoder;
+use flate2::Compression;
+use fs::{FileSystem, Metadata};
+use mime_guess::get_mime_type_opt;
+use path;
+use rocket::http::{Header, Method, Status};
+use rocket::{Request, Response};
+use std::collections::HashMap;
+use std::error::Error as StdError;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+use {etag_matches, Error, Range, RangeError, LAST_MODIFIED_DATE_FORMAT};
+
+/// Serves the file at `path` of the FileSystem, just like the StaticFileServer does.
+///
+/// Sets the MIME type, answers conditional and range requests and compresses the body if the
+/// client accepts it. `path` is relative to the root of the FileSystem, paths escaping the root
+/// are refused with a 403. This is useful to serve files from catchers or your own fairings.
+///
+/// On failure, the status is set on the response and returned, so you can respond with an
+/// error page of your own.
+///
+/// # Example
+///
+/// ```
+/// extern crate rocket;
+/// extern crate rocket_static_fs;
+///
+/// use rocket::{Request, Response};
+/// use rocket_static_fs::{fs, serve_file};
+///
+/// fn serve_download(request: &Request, response: &mut Response) {
+///     let fs = fs::LocalFileSystem::new("downloads");
+///     let _ = serve_file(&fs, "latest.zip", request, response);
+/// }
+/// # fn main() {}
+/// ```
+pub fn serve_file<T: FileSystem + ?Sized>(
+    fs: &T,
+    path: &str,
+    request: &Request,
+    response: &mut Response,
+) -> Result<(), Status> {
+    let result = match path::normalize(path) {
+        Some(ref p) if fs.path_valid(Path::new(p)) => {
+            serve_with_status(fs, request, response, p, Status::Ok, &ServeOptions::default())
+                .map(|_| ())
+        }
+        _ => Err(Status::Forbidden),
+    };
+
+    if let Err(status) = result {
+        response.set_status(status);
+    }
+    result
+}
+
+/// Options of how files are served.
+pub struct ServeOptions {
+    pub compression: bool,
+    pub ranges: bool,
+    /// MIME types by lowercase extension, taking precedence over the guessed ones.
+    pub mime_types: HashMap<String, String>,
+    /// The MIME type of files without an extension or with an unknown one.
+    pub default_mime_type: String,
+    /// Determines the MIME type from the first bytes of files with unknown extensions.
+    #[cfg(feature = "sniff")]
+    pub sniff: bool,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        ServeOptions {
+            compression: true,
+            ranges: true,
+            mime_types: HashMap::new(),
+            default_mime_type: "application/octet-stream".to_string(),
+            #[cfg(feature = "sniff")]
+            sniff: false,
+        }
+    }
+}
+
+/// Sets status, Last-Modified and the range headers of a response carrying (part of) a file.
+fn set_body_headers(
+    response: &mut Response,
+    status: Status,
+    accept_ranges: &str,
+    modified: &DateTime<Utc>,
+    range: &Result<Range, Box<StdError>>,
+    size: u64,
+) {
+    response.set_header(Header::new("Accept-Ranges", accept_ranges.to_string()));
+    response.set_status(status);
+    response.set_raw_header(
+        "Last-Modified",
+        modified.format(LAST_MODIFIED_DATE_FORMAT).to_string(),
+    );
+
+    if let Ok(ref range) = *range {
+        response.set_header(Header::new("Content-Length", format!("{}", range.len())));
+        response.set_header(Header::new(
+            "Content-Range",
+            format!("{} {}-{}/{}", range.typ, range.start, range.end, size),
+        ));
+        response.set_status(Status::PartialContent);
+    }
+}
+
+/// Serves the file at `req_path` of the FileSystem with the given status.
+///
+/// Conditional and range requests are only honored for `200 OK` responses. Returns the number
+/// of bytes of the file sent in the body. On failure, the status to respond with is returned
+/// and the response is left for the caller to finish.
+pub fn serve_with_status<T: FileSystem + ?Sized>(
+    fs: &T,
+    request: &Request,
+    response: &mut Response,
+    req_path: &str,
+    status: Status,
+    options: &ServeOptions,
+) -> Result<u64, Status> {
+    response.set_raw_header("Content-Type", content_type(fs, req_path, options));
+
+    // Get the size and modification date of the file in one go
+    let metadata = {
+        let _span = trace_span!("static_fs.metadata", path = req_path);
+        fs.metadata(Path::new(req_path))
+    };
+    let (size, modified) = match metadata {
+        Ok(Metadata {
+            size,
+            modified: Some(modified),
+            is_file: true,
+            ..
+        }) => (size, modified),
+        Ok(_) => return Err(Status::InternalServerError),
+        Err(err) => return Err(err.status()),
+    };
+    let modified: DateTime<Utc> = DateTime::from(modified);
+
+    let accept_encoding = request.headers().get_one("Accept-Encoding");
+    let gzip = options.compression && accept_encoding.map_or(false, |e| e.contains("gzip"));
+
+    // Prefer the ETag of the FileSystem, otherwise we make up a weak one from size and
+    // modification date. The compressed variant is a different representation, so it gets a
+    // different ETag.
+    let etag = fs
+        .etag(Path::new(req_path))
+        .unwrap_or_else(|| format!("W/\"{:x}-{:x}\"", size, modified.timestamp()));
+    let etag = if gzip { encoded_etag(&etag, "gzip") } else { etag };
+    response.set_raw_header("ETag", etag.clone());
+
+    // Get the If-None-Match and If-Modified-Since header values
+    let if_none_match = request.headers().get_one("If-None-Match");
+    let if_modified_since = request.headers().get("If-Modified-Since").next();
+
+    // If the client already has the current version of the file, we respond with a 304. An
+    // If-None-Match header takes precedence over If-Modified-Since.
+    if let (Some(if_none_match), true) = (if_none_match, status == Status::Ok) {
+        if etag_matches(if_none_match, &etag) {
+            trace_event!(etag = %etag, "not modified, If-None-Match matched");
+            response.set_status(Status::NotModified);
+            return Ok(0);
+        }
+    }
+
+    // Only on a GET request: If the If-Modified-Since header and the modified time of the file are the same, we
+    // respond with a 304 here
+    if request.method() == Method::Get && status == Status::Ok && if_none_match.is_none() {
+        if let Some(time) = if_modified_since {
+            if let Ok(time) = Utc.datetime_from_str(&time, LAST_MODIFIED_DATE_FORMAT) {
+                let duration: chrono::Duration = time.signed_duration_since(modified);
+                if duration.num_seconds() == 0 {
+                    trace_event!("not modified, If-Modified-Since matched");
+                    response.set_status(Status::NotModified);
+                    return Ok(0);
+                };
+            };
+        };
+    }
+
+    // Tell clients whether they may request ranges at all
+    let accept_ranges = if options.ranges { "bytes" } else { "none" };
+
+    // In case someone heads the file, we inform him about the content length and
+    // that we support byte ranges.
+    if request.method() == Method::Head {
+        response.set_header(Header::new("Accept-Ranges", accept_ranges));
+        response.set_header(Header::new("Content-Length", format!("{}", size)));
+        response.set_status(status);
+        return Ok(0);
+    }
+
+    // Let's parse the range header if it exists
+    let range_header = request.headers().get_one("Range").unwrap_or("");
+
+    // If we get a multipart range request, we more or less fail gracefully here for the moment.
+    // We simply set the range here to an error and send the complete file cause of that.
+    // TODO: Support multipart ranges
+    let range: Result<Range, Box<StdError>> = if status != Status::Ok {
+        Err(Box::new(RangeError::new("ranges are only supported for successful responses")))
+    } else if !options.ranges {
+        Err(Box::new(RangeError::new("ranges are disabled")))
+    } else if range_header.contains(',') {
+        Err(Box::new(RangeError::new("multipart ranges not supported")))
+    } else {
+        Range::parse_for_size(range_header, size).and_then(|range| {
+            if range.typ == "bytes" {
+                Ok(range)
+            } else {
+                Err(Box::new(RangeError::new("only byte ranges are supported")) as Box<StdError>)
+            }
+        })
+    };
+
+    // Ranges starting beyond the end of the file can't be satisfied
+    if let Ok(ref range) = range {
+        if range.start >= size {
+            let content_range = format!("{} */{}", range.typ, size);
+            response.set_header(Header::new("Content-Range", content_range));
+            return Err(Status::RangeNotSatisfiable);
+        }
+    }
+
+    // Set the start byte and the length of the body for the request
+    let (start, len) = match range {
+        Ok(ref range) => (range.start, range.len()),
+        Err(_) => (0, size),
+    };
+
+    // Files the FileSystem holds in memory anyway are sent right from there as sized bodies,
+    // unless we compress them
+    if !gzip {
+        let bytes = fs
+            .as_bytes(Path::new(req_path))
+            .filter(|bytes| bytes.len() as u64 == size);
+        if let Some(bytes) = bytes {
+            set_body_headers(response, status, accept_ranges, &modified, &range, size);
+            trace_event!(bytes = len, "sized body");
+            response.set_sized_body(Cursor::new(&bytes[start as usize..(start + len) as usize]));
+            return Ok(len);
+        }
+    }
+
+    // Otherwise we try to send the file, which should work since that size above should have
+    // worked as well.
+    let opened = {
+        let _span = trace_span!("static_fs.open", path = req_path, start);
+        open_at(fs, Path::new(req_path), start)
+    };
+    match opened {
+        Ok(mut f) => {
+            set_body_headers(response, status, accept_ranges, &modified, &range, size);
+
+            // If we got a range header, we set f to a limit reader so it will stop when it
+            // reached the range len.
+            if range.is_ok() {
+                f = Box::new(f.take(len));
+            }
+
+            // In case the client accepts encodings, we handle these
+            // TODO: Support more encodings
+            if gzip {
+                // Backends may hold the whole file compressed already, saving us the work
+                let precompressed = match range {
+                    Ok(_) => None,
+                    Err(_) => fs.open_encoded(Path::new(req_path), "gzip"),
+                };
+                if let Some(encoded) = precompressed {
+                    response.set_raw_header("Content-Encoding", "gzip");
+                    trace_event!(bytes = len, encoding = "gzip", "streaming precompressed body");
+                    response.set_streamed_body(encoded);
+                    return Ok(len);
+                }
+
+                let mut encoder = GzEncoder::new(f, Compression::default());
+                response.set_raw_header("Content-Encoding", "gzip");
+                trace_event!(bytes = len, encoding = "gzip", "streaming body");
+                response.set_streamed_body(encoder);
+                return Ok(len);
+            }
+
+            trace_event!(bytes = len, "streaming body");
+            response.set_streamed_body(f);
+            Ok(len)
+        }
+        Err(err) => Err(err.status()),
+    }
+}
+
+/// Returns the ETag of the file encoded with `encoding`, e.g. `"abc-gzip"` for `"abc"`.
+fn encoded_etag(etag: &str, encoding: &str) -> String {
+    match etag.rfind('"') {
+        Some(end) if end > 0 => format!("{}-{}{}", &etag[..end], encoding, &etag[end..]),
+        _ => format!("{}-{}", etag, encoding),
+    }
+}
+
+/// Returns the MIME type of the file.
+///
+/// The MIME type known by the FileSystem is preferred, then the configured ones and finally the
+/// guessed one.
+fn content_type<T: FileSystem + ?Sized>(fs: &T, path: &str, options: &ServeOptions) -> String {
+    if let Some(mime) = fs.content_type(Path::new(path)) {
+        return mime;
+    }
+
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if let Some(mime) = options.mime_types.get(&extension) {
+        return mime.clone();
+    }
+
+    match get_mime_type_opt(&extension) {
+        Some(mime) if !extension.is_empty() => mime.to_string(),
+        _ => sniff(fs, path, options).unwrap_or_else(|| options.default_mime_type.clone()),
+    }
+}
+
+/// The number of bytes read to determine the MIME type of a file.
+#[cfg(feature = "sniff")]
+const SNIFF_LEN: u64 = 512;
+
+/// Determines the MIME type from the magic bytes at the start of the file, if enabled.
+#[cfg(feature = "sniff")]
+fn sniff<T: FileSystem + ?Sized>(fs: &T, path: &str, options: &ServeOptions) -> Option<String> {
+    if !options.sniff {
+        return None;
+    }
+
+    let mut head = Vec::new();
+    let f = fs.open(Path::new(path), None).ok()?;
+    f.take(SNIFF_LEN).read_to_end(&mut head).ok()?;
+    infer::get(&head).map(|kind| kind.mime_type().to_string())
+}
+
+#[cfg(not(feature = "sniff"))]
+fn sniff<T: FileSystem + ?Sized>(_fs: &T, _path: &str, _options: &ServeOptions) -> Option<String> {
+    None
+}
+
+/// Opens the file and positions it at the given byte.
+///
+/// Seekable readers are positioned by us, all other backends have to do it themselves.
+fn open_at<T: FileSystem + ?Sized>(fs: &T, path: &Path, start: u64) -> Result<Box<Read>, Error> {
+    if start == 0 {
+        return fs.open(path, None);
+    }
+
+    match fs.open_seek(path) {
+        Ok(mut f) => {
+            f.seek(SeekFrom::Start(start))?;
+            Ok(Box::new(f))
+        }
+        Err(_) => fs.open(path, Some(start)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{content_type, encoded_etag, serve_file, ServeOptions};
+    use fs::LocalFileSystem;
+    use rocket;
+    use rocket::fairing::AdHoc;
+    use rocket::http::{Header, Status};
+    use rocket::local::Client;
+
+    #[test]
+    fn test_encoded_etag() {
+        assert_eq!(encoded_etag("\"abc\"", "gzip"), "\"abc-gzip\"");
+        assert_eq!(encoded_etag("W/\"1-2\"", "gzip"), "W/\"1-2-gzip\"");
+    }
+
+    #[test]
+    fn test_content_type() {
+        let fs = LocalFileSystem::new("testdata");
+        let mut options = ServeOptions::default();
+        assert_eq!(content_type(&fs, "app.js", &options), "application/javascript");
+
+        options
+            .mime_types
+            .insert("js".to_string(), "text/javascript".to_string());
+        options
+            .mime_types
+            .insert("wasm".to_string(), "application/wasm".to_string());
+        assert_eq!(content_type(&fs, "app.JS", &options), "text/javascript");
+        assert_eq!(content_type(&fs, "dir/app.wasm", &options), "application/wasm");
+    }
+
+    #[test]
+    fn test_default_content_type() {
+        let fs = LocalFileSystem::new("testdata");
+        let mut options = ServeOptions::default();
+        assert_eq!(content_type(&fs, "LICENSE", &options), "application/octet-stream");
+
+        options.default_mime_type = "text/plain".to_string();
+        assert_eq!(content_type(&fs, "LICENSE", &options), "text/plain");
+        assert_eq!(content_type(&fs, "build.artifact-v2", &options), "text/plain");
+        assert_eq!(content_type(&fs, "index.html", &options), "text/html");
+    }
+
+    #[cfg(feature = "sniff")]
+    #[test]
+    fn test_sniff_content_type() {
+        use std::env;
+        use std::fs::{self, File};
+        use std::io::Write;
+
+        let dir = env::temp_dir().join("rocket_static_fs_sniff");
+        fs::create_dir_all(&dir).unwrap();
+        let png = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0x0D];
+        File::create(dir.join("upload")).unwrap().write_all(&png).unwrap();
+
+        let fs = LocalFileSystem::new(&dir);
+        let mut options = ServeOptions::default();
+        assert_eq!(content_type(&fs, "upload", &options), "application/octet-stream");
+
+        options.sniff = true;
+        assert_eq!(content_type(&fs, "upload", &options), "image/png");
+    }
+
+    #[test]
+    fn test_serve_file() {
+        let fairing = AdHoc::on_response("download", |request, response| {
+            let fs = LocalFileSystem::new("testdata");
+            let _ = match request.uri().path() {
+                "/download" => serve_file(&fs, "hello.txt", request, response),
+                "/missing" => serve_file(&fs, "missing.txt", request, response),
+                "/escape" => serve_file(&fs, "../Cargo.toml", request, response),
+                _ => Ok(()),
+            };
+        });
+        let client = Client::new(rocket::ignite().attach(fairing)).expect("valid rocket");
+
+        let mut response = client.get("/download").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.headers().get_one("Content-Type"), Some("text/plain"));
+        assert_eq!(response.body_string(), Some("Hello World!".to_string()));
+
+        let response = client
+            .get("/download")
+            .header(Header::new("Range", "bytes=0-4"))
+            .dispatch();
+        assert_eq!(response.status(), Status::PartialContent);
+
+        let response = client.get("/missing").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+        let response = client.get("/escape").dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+}
@@ -0,0 +1,32 @@
+//! Macros for the `tracing` instrumentation, which expand to nothing without the `tracing`
+//! feature.
+
+/// Enters a span, which is exited when the returned guard is dropped.
+#[cfg(feature = "tracing")]
+macro_rules! trace_span {
+    ($($args:tt)*) => {
+        ::tracing::debug_span!($($args)*).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_span {
+    ($($args:tt)*) => {
+        ()
+    };
+}
+
+/// Records an event in the current span.
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+    ($($args:tt)*) => {
+        ::tracing::debug!($($args)*)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_event {
+    ($($args:tt)*) => {
+        ()
+    };
+}
@@ -0,0 +1,106 @@
+//! Virtual hosting: serving different sites depending on the `Host` header.
+
+use fs::FileSystem;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Request, Response};
+use std::collections::HashMap;
+use StaticFileServer;
+
+/// A fairing dispatching requests to one of several StaticFileServers by the `Host` header.
+///
+/// # Example
+///
+/// ```
+/// use rocket_static_fs::{fs, StaticFileServer, VirtualHosts};
+///
+/// let blog = StaticFileServer::new(fs::LocalFileSystem::new("sites/blog"), "/").unwrap();
+/// let docs = StaticFileServer::new(fs::LocalFileSystem::new("sites/docs"), "/").unwrap();
+/// let fallback = StaticFileServer::new(fs::LocalFileSystem::new("sites/default"), "/").unwrap();
+///
+/// let hosts = VirtualHosts::new()
+///     .host("blog.example.com", blog)
+///     .host("docs.example.com", docs)
+///     .fallback(fallback);
+/// ```
+pub struct VirtualHosts<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    hosts: HashMap<String, StaticFileServer<T>>,
+    default: Option<StaticFileServer<T>>,
+}
+
+impl<T> VirtualHosts<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    pub fn new() -> Self {
+        VirtualHosts {
+            hosts: HashMap::new(),
+            default: None,
+        }
+    }
+
+    /// Serves requests for the given host name with the server.
+    ///
+    /// Host names are compared case-insensitively and without the port.
+    pub fn host(mut self, host: &str, server: StaticFileServer<T>) -> Self {
+        self.hosts.insert(host.to_lowercase(), server);
+        self
+    }
+
+    /// Serves requests for all other hosts with the server.
+    pub fn fallback(mut self, server: StaticFileServer<T>) -> Self {
+        self.default = Some(server);
+        self
+    }
+
+    /// Returns the server responsible for the request, if any.
+    fn server_for(&self, request: &Request) -> Option<&StaticFileServer<T>> {
+        let host = request
+            .headers()
+            .get_one("Host")
+            .map(|host| strip_port(host).to_lowercase());
+        host.and_then(|host| self.hosts.get(&host))
+            .or_else(|| self.default.as_ref())
+    }
+}
+
+impl<T: 'static> Fairing for VirtualHosts<T>
+where
+    T: FileSystem + Sized + Send + Sync,
+{
+    fn info(&self) -> Info {
+        Info {
+            name: "static_file_server_virtual_hosts",
+            kind: Kind::Response,
+        }
+    }
+
+    fn on_response(&self, request: &Request, response: &mut Response) {
+        if let Some(server) = self.server_for(request) {
+            server.on_response(request, response);
+        }
+    }
+}
+
+/// Strips the port from a `Host` header value, taking care of IPv6 addresses.
+fn strip_port(host: &str) -> &str {
+    match host.rfind(':') {
+        Some(i) if !host[i..].contains(']') => &host[..i],
+        _ => host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::strip_port;
+
+    #[test]
+    fn test_strip_port() {
+        assert_eq!(strip_port("example.com"), "example.com");
+        assert_eq!(strip_port("example.com:8000"), "example.com");
+        assert_eq!(strip_port("[::1]"), "[::1]");
+        assert_eq!(strip_port("[::1]:8000"), "[::1]");
+    }
+}